@@ -0,0 +1,22 @@
+#version 450
+
+layout(location = 0) in vec3 a_Pos;
+layout(location = 1) in vec2 i_Position;
+layout(location = 2) in float i_Angle;
+layout(location = 3) in float i_Size;
+layout(location = 4) in vec2 i_TexCoord;
+layout(location = 0) out vec2 v_TexCoord;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 u_Transform;
+};
+
+void main() {
+    // The quad lies on the ground; instance positions are map coordinates along x and z
+    float s = sin(i_Angle);
+    float c = cos(i_Angle);
+    vec2 corner = a_Pos.xz * i_Size;
+    vec2 rotated = vec2(corner.x * c - corner.y * s, corner.x * s + corner.y * c);
+    v_TexCoord = i_TexCoord;
+    gl_Position = u_Transform * vec4(i_Position.x + rotated.x, a_Pos.y, i_Position.y + rotated.y, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in vec2 v_TexCoord;
+layout(location = 1) in vec4 v_Color;
+layout(location = 0) out vec4 o_Target;
+layout(set = 0, binding = 1) uniform texture2D t_Color;
+layout(set = 0, binding = 2) uniform sampler s_Color;
+
+void main() {
+    o_Target = v_Color * texture(sampler2D(t_Color, s_Color), v_TexCoord);
+}
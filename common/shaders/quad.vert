@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec2 a_Pos;
+layout(location = 1) in vec2 a_TexCoord;
+layout(location = 2) in vec4 a_Color;
+layout(location = 0) out vec2 v_TexCoord;
+layout(location = 1) out vec4 v_Color;
+
+layout(set = 0, binding = 0) uniform Locals {
+    vec2 u_ScreenSize;
+};
+
+void main() {
+    // Positions are in pixels from the top left corner of the screen
+    vec2 ndc = a_Pos / u_ScreenSize * 2.0 - 1.0;
+    v_TexCoord = a_TexCoord;
+    v_Color = a_Color;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
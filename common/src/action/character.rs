@@ -0,0 +1,207 @@
+use crate::action::{ActionCreatorResult, ActionState, Tick};
+use crate::ecs::{ComponentGroup, EntityId};
+use crate::game::component::{CharacterStatus, Position};
+use crate::map::path::{calc_movement_time, MapPath};
+use crate::map::{Ground, TilePosition};
+use serde::{Deserialize, Serialize};
+
+const PICK_UP_TIME: Tick = 500;
+const DROP_TIME: Tick = 300;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CustomActionState {
+    MoveToPoint { start: Position, end: Position },
+    PickUp { item: EntityId },
+    Drop { item: EntityId, position: Position },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CustomActionCreatorState {
+    MoveToDestination {
+        start: Position,
+        end: Position,
+    },
+    BringItemToDestination {
+        item: EntityId,
+        destination: Position,
+    },
+    PickUp {
+        item: EntityId,
+    },
+    Drop {
+        item: EntityId,
+        position: Position,
+    },
+}
+
+pub type CustomActionCreatorResult =
+    ActionCreatorResult<CustomActionState, CustomActionCreatorState>;
+
+/// Components the character action creators read.
+pub struct ActionCreatorContext<'a> {
+    pub positions: &'a ComponentGroup<Position>,
+    pub character_status: &'a ComponentGroup<CharacterStatus>,
+    pub map_path: &'a MapPath,
+    pub ground: &'a Ground,
+}
+
+/// Expands `creator` for `entity_id`; pass this to `ActionScheduler::update`.
+pub fn create_action(
+    entity_id: &EntityId,
+    creator: &CustomActionCreatorState,
+    context: &ActionCreatorContext,
+) -> CustomActionCreatorResult {
+    match creator {
+        CustomActionCreatorState::MoveToDestination { start, end } => move_to_destination(
+            entity_id,
+            start,
+            end,
+            context.character_status,
+            context.map_path,
+            context.ground,
+        ),
+        CustomActionCreatorState::BringItemToDestination { item, destination } => {
+            bring_item_to_destination(entity_id, item, destination, context.positions)
+        }
+        CustomActionCreatorState::PickUp { item } => {
+            pick_up(entity_id, item, context.character_status)
+        }
+        CustomActionCreatorState::Drop { item, position } => ActionCreatorResult::Progressing {
+            action_states: vec![ActionState::new(
+                *entity_id,
+                DROP_TIME,
+                CustomActionState::Drop {
+                    item: *item,
+                    position: *position,
+                },
+            )],
+        },
+    }
+}
+
+fn move_to_destination(
+    // entity
+    entity_id: &EntityId,
+    // action creator state
+    start: &Position,
+    end: &Position,
+    // components
+    character_status: &ComponentGroup<CharacterStatus>,
+    map_path: &MapPath,
+    ground: &Ground,
+) -> CustomActionCreatorResult {
+    let target_character_status = match character_status.get(entity_id) {
+        Some(status) => status,
+        None => return ActionCreatorResult::Cancel,
+    };
+    let paths = match map_path.search_paths(
+        &TilePosition::from_position(start),
+        &TilePosition::from_position(end),
+    ) {
+        Some(paths) => paths,
+        None => return ActionCreatorResult::Cancel,
+    };
+
+    let mut action_states = Vec::with_capacity(paths.len());
+    for (i, path) in paths.iter().enumerate() {
+        let movement_time = match calc_movement_time(target_character_status, ground, path) {
+            Some(movement_time) => movement_time,
+            None => return ActionCreatorResult::Cancel,
+        };
+        // Paths run between tile centers; the first and last steps start and end exactly
+        // where the character is and where it was sent
+        let point_start = if i == 0 {
+            *start
+        } else {
+            path.start.to_position()
+        };
+        let point_end = if i + 1 == paths.len() {
+            *end
+        } else {
+            path.end.to_position()
+        };
+        action_states.push(ActionState::new(
+            *entity_id,
+            movement_time,
+            CustomActionState::MoveToPoint {
+                start: point_start,
+                end: point_end,
+            },
+        ));
+    }
+
+    if action_states.is_empty() && start != end {
+        // Already on the destination tile, just not at the exact point
+        let tile = TilePosition::from_position(end);
+        let cost = ground.movement_cost(&tile).unwrap_or(1.0f32);
+        let seconds = start.distance(end) * cost / target_character_status.speed.max(0.001f32);
+        action_states.push(ActionState::new(
+            *entity_id,
+            (seconds * 1000.0f32).round() as Tick,
+            CustomActionState::MoveToPoint {
+                start: *start,
+                end: *end,
+            },
+        ));
+    }
+
+    if action_states.is_empty() {
+        return ActionCreatorResult::Complete;
+    }
+
+    ActionCreatorResult::Progressing { action_states }
+}
+
+fn bring_item_to_destination(
+    entity_id: &EntityId,
+    item: &EntityId,
+    destination: &Position,
+    positions: &ComponentGroup<Position>,
+) -> CustomActionCreatorResult {
+    let (start, item_position) = match (positions.get(entity_id), positions.get(item)) {
+        (Some(start), Some(item_position)) => (*start, *item_position),
+        _ => return ActionCreatorResult::Cancel,
+    };
+
+    let action_creators = vec![
+        CustomActionCreatorState::MoveToDestination {
+            start,
+            end: item_position,
+        },
+        CustomActionCreatorState::PickUp { item: *item },
+        CustomActionCreatorState::MoveToDestination {
+            start: item_position,
+            end: *destination,
+        },
+        CustomActionCreatorState::Drop {
+            item: *item,
+            position: *destination,
+        },
+    ];
+    ActionCreatorResult::Chain {
+        creators: action_creators,
+    }
+}
+
+fn pick_up(
+    entity_id: &EntityId,
+    item: &EntityId,
+    character_status: &ComponentGroup<CharacterStatus>,
+) -> CustomActionCreatorResult {
+    // Hands are full, or someone else got there first
+    let is_taken = character_status
+        .iter()
+        .any(|(_, status)| status.carrying == Some(*item));
+    match character_status.get(entity_id) {
+        Some(status) if status.carrying.is_none() && !is_taken => {
+            ActionCreatorResult::Progressing {
+                action_states: vec![ActionState::new(
+                    *entity_id,
+                    PICK_UP_TIME,
+                    CustomActionState::PickUp { item: *item },
+                )],
+            }
+        }
+        _ => ActionCreatorResult::Cancel,
+    }
+}
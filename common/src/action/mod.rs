@@ -1,40 +1,438 @@
-/*
-
-fn move_to_destination(
-    // entity
-    entity_id: &EntityId,
-    // action creator state
-    start: &Position,
-    end: &Position,
-    // components
-    character_status: &ComponentGroup<CharacterStatus>,
-    map_path: &MapPath,
-    ground: &Ground,
-) -> ActionCreatorResult<CustomActionCreatorCommonState> {
-    let target_character_status = character_status.get(entity_id).unwrap();
-    let action_states = match map_path.search_paths(start, end) {
-        Some(paths) => paths,
-        None => return ActionCreatorResult::Cancel,
-    }.map(path => {
-        let movement_time = calc_movement_time(target_character_status, ground, &path.start);
-        ActionState::new(*entity_id, movement_time, CustomActionState::MoveToPointActionState::new(*path))
-    });
-
-    if actions.is_empty() {
-        return ActionCreatorResult::Complete;
-    }
-
-    ActionCreatorResult::Progressing { action_states }
-}
-
-fn bring_item_to_destination(
-    entity_id: &EntityId,
-    food_entity_id: &EntityId,
-    positions: &ComponentGroup<Position>,
-) -> ActionCreatorResult<CustomActionCreatorCommonState> {
-    let action_creators = vec![
-        CustomActionCreatorState::MoveToDestination { }
-    ];
-}
-
-*/
+pub mod character;
+
+use crate::ecs::EntityId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Time in the action system, counted in milliseconds so schedules stay exact.
+pub type Tick = u64;
+
+/// Upper bound on creators evaluated for one entity per update, so a creator that keeps
+/// chaining without producing actions can't hang the game.
+const MAX_CREATOR_EVALUATIONS: usize = 64;
+
+/// One step of an entity's plan: `state` runs for `duration` ticks.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActionState<S> {
+    pub entity_id: EntityId,
+    pub duration: Tick,
+    pub state: S,
+}
+
+impl<S> ActionState<S> {
+    pub fn new(entity_id: EntityId, duration: Tick, state: S) -> Self {
+        ActionState {
+            entity_id,
+            duration,
+            state,
+        }
+    }
+}
+
+/// What an action creator decided for its entity.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionCreatorResult<S, C> {
+    /// The creator can't run. The entity's remaining creators are dropped.
+    Cancel,
+    /// Nothing left to do; the next creator runs.
+    Complete,
+    /// Run these actions in order, then move on to the next creator.
+    Progressing { action_states: Vec<ActionState<S>> },
+    /// Replace this creator with `creators`, which run in order.
+    Chain { creators: Vec<C> },
+}
+
+/// An action placed on the timeline.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Action<S> {
+    pub entity_id: EntityId,
+    pub start: Tick,
+    pub end: Tick,
+    pub state: S,
+}
+
+impl<S> Action<S> {
+    /// How far the action has progressed at `tick`, from 0 to 1.
+    pub fn ratio(&self, tick: Tick) -> f32 {
+        if self.end <= self.start {
+            return 1.0f32;
+        }
+        let elapsed = tick.max(self.start).min(self.end) - self.start;
+        elapsed as f32 / (self.end - self.start) as f32
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionEvent<S> {
+    Start(Action<S>),
+    End(Action<S>),
+    /// The entity's plan was cancelled, either by a creator or through `ActionScheduler::cancel`.
+    /// `action` is the action that was interrupted, if one was running.
+    Cancel {
+        entity_id: EntityId,
+        action: Option<Action<S>>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct EntityActions<S, C> {
+    creators: VecDeque<C>,
+    pending: VecDeque<ActionState<S>>,
+    current: Option<Action<S>>,
+}
+
+impl<S, C> EntityActions<S, C> {
+    fn is_idle(&self) -> bool {
+        self.creators.is_empty() && self.pending.is_empty() && self.current.is_none()
+    }
+}
+
+/// Expands action creators into timed actions, one queue per entity.
+///
+/// Creators are evaluated lazily: the next creator only runs once every action of the previous
+/// one has ended, so it sees the state those actions left behind.
+/// Entities are processed in id order and events are returned in the order they happened
+/// per entity, so the same inputs always produce the same schedule.
+///
+/// The whole schedule, including creators that haven't run yet, can be serialized for saves,
+/// lockstep hashes and network snapshots.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActionScheduler<S, C> {
+    tick: Tick,
+    entities: BTreeMap<EntityId, EntityActions<S, C>>,
+}
+
+impl<S, C> ActionScheduler<S, C>
+where
+    S: Clone,
+{
+    pub fn new() -> Self {
+        ActionScheduler {
+            tick: 0,
+            entities: BTreeMap::new(),
+        }
+    }
+
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+
+    /// Queues `creator` after everything already planned for `entity_id`.
+    pub fn push_creator(&mut self, entity_id: EntityId, creator: C) {
+        self.entities
+            .entry(entity_id)
+            .or_insert_with(|| EntityActions {
+                creators: VecDeque::new(),
+                pending: VecDeque::new(),
+                current: None,
+            })
+            .creators
+            .push_back(creator);
+    }
+
+    /// Drops the plan of `entity_id`, returning the action it was running.
+    pub fn cancel(&mut self, entity_id: EntityId) -> Option<ActionEvent<S>> {
+        let actions = self.entities.remove(&entity_id)?;
+        Some(ActionEvent::Cancel {
+            entity_id,
+            action: actions.current,
+        })
+    }
+
+    pub fn current_action(&self, entity_id: EntityId) -> Option<&Action<S>> {
+        self.entities.get(&entity_id)?.current.as_ref()
+    }
+
+    pub fn is_idle(&self, entity_id: EntityId) -> bool {
+        self.entities
+            .get(&entity_id)
+            .map(|actions| actions.is_idle())
+            .unwrap_or(true)
+    }
+
+    /// Advances time by `delta` and returns what started, ended or was cancelled meanwhile.
+    ///
+    /// `create` expands a creator for an entity; it is called as many times as needed to keep
+    /// every busy entity running until the new tick.
+    pub fn update<F>(&mut self, delta: Tick, mut create: F) -> Vec<ActionEvent<S>>
+    where
+        F: FnMut(EntityId, &C) -> ActionCreatorResult<S, C>,
+    {
+        self.tick += delta;
+        let now = self.tick;
+        let mut events = vec![];
+
+        for (entity_id, actions) in self.entities.iter_mut() {
+            let entity_id = *entity_id;
+            let mut cursor = now;
+            let mut evaluations = 0;
+            loop {
+                if let Some(current) = &actions.current {
+                    if current.end > now {
+                        break;
+                    }
+                    cursor = current.end;
+                    events.push(ActionEvent::End(actions.current.take().unwrap()));
+                }
+
+                if let Some(next) = actions.pending.pop_front() {
+                    let action = Action {
+                        entity_id,
+                        start: cursor,
+                        end: cursor + next.duration,
+                        state: next.state,
+                    };
+                    events.push(ActionEvent::Start(action.clone()));
+                    actions.current = Some(action);
+                    continue;
+                }
+
+                if evaluations >= MAX_CREATOR_EVALUATIONS {
+                    log::warn!(
+                        "Entity {} ran too many action creators in one update",
+                        entity_id
+                    );
+                    break;
+                }
+                let result = match actions.creators.front() {
+                    Some(creator) => create(entity_id, creator),
+                    None => break,
+                };
+                evaluations += 1;
+                actions.creators.pop_front();
+                match result {
+                    ActionCreatorResult::Cancel => {
+                        actions.creators.clear();
+                        events.push(ActionEvent::Cancel {
+                            entity_id,
+                            action: None,
+                        });
+                        break;
+                    }
+                    ActionCreatorResult::Complete => {}
+                    ActionCreatorResult::Progressing { action_states } => {
+                        actions.pending.extend(action_states);
+                    }
+                    ActionCreatorResult::Chain { creators } => {
+                        for creator in creators.into_iter().rev() {
+                            actions.creators.push_front(creator);
+                        }
+                    }
+                }
+            }
+        }
+        self.entities.retain(|_, actions| !actions.is_idle());
+        events
+    }
+}
+
+impl<S, C> Default for ActionScheduler<S, C>
+where
+    S: Clone,
+{
+    fn default() -> Self {
+        ActionScheduler::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Creator {
+        Walk(Vec<Tick>),
+        Done,
+        Fail,
+        Then(Vec<Creator>),
+        Forever,
+    }
+
+    type TestScheduler = ActionScheduler<Tick, Creator>;
+
+    fn create(
+        log: &mut Vec<(Tick, Creator)>,
+        tick: Tick,
+        entity_id: EntityId,
+        creator: &Creator,
+    ) -> ActionCreatorResult<Tick, Creator> {
+        log.push((tick, creator.clone()));
+        match creator {
+            Creator::Walk(durations) => ActionCreatorResult::Progressing {
+                action_states: durations
+                    .iter()
+                    .map(|duration| ActionState::new(entity_id, *duration, *duration))
+                    .collect(),
+            },
+            Creator::Done => ActionCreatorResult::Complete,
+            Creator::Fail => ActionCreatorResult::Cancel,
+            Creator::Then(creators) => ActionCreatorResult::Chain {
+                creators: creators.clone(),
+            },
+            Creator::Forever => ActionCreatorResult::Chain {
+                creators: vec![Creator::Forever],
+            },
+        }
+    }
+
+    fn update(
+        scheduler: &mut TestScheduler,
+        delta: Tick,
+        log: &mut Vec<(Tick, Creator)>,
+    ) -> Vec<ActionEvent<Tick>> {
+        let tick = scheduler.tick() + delta;
+        scheduler.update(delta, |entity_id, creator| {
+            create(log, tick, entity_id, creator)
+        })
+    }
+
+    fn action(start: Tick, end: Tick) -> Action<Tick> {
+        Action {
+            entity_id: 1,
+            start,
+            end,
+            state: end - start,
+        }
+    }
+
+    #[test]
+    fn runs_actions_back_to_back_until_complete() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(1, Creator::Walk(vec![100, 200]));
+
+        let events = update(&mut scheduler, 50, &mut log);
+        assert_eq!(events, vec![ActionEvent::Start(action(50, 150))]);
+        assert_eq!(scheduler.current_action(1), Some(&action(50, 150)));
+        assert_eq!(action(50, 150).ratio(100), 0.5f32);
+
+        // The second action starts when the first ends, not at the update tick
+        let events = update(&mut scheduler, 150, &mut log);
+        assert_eq!(
+            events,
+            vec![
+                ActionEvent::End(action(50, 150)),
+                ActionEvent::Start(action(150, 350)),
+            ]
+        );
+
+        let events = update(&mut scheduler, 150, &mut log);
+        assert_eq!(events, vec![ActionEvent::End(action(150, 350))]);
+        assert!(scheduler.is_idle(1));
+        assert_eq!(log.len(), 1);
+    }
+
+    #[test]
+    fn completed_creators_move_on_to_the_next() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(1, Creator::Done);
+        scheduler.push_creator(1, Creator::Walk(vec![10]));
+
+        let events = update(&mut scheduler, 1, &mut log);
+        assert_eq!(events, vec![ActionEvent::Start(action(1, 11))]);
+        assert_eq!(log, vec![(1, Creator::Done), (1, Creator::Walk(vec![10]))]);
+    }
+
+    #[test]
+    fn creators_run_once_the_previous_actions_ended() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(1, Creator::Walk(vec![100]));
+        scheduler.push_creator(1, Creator::Done);
+
+        update(&mut scheduler, 0, &mut log);
+        update(&mut scheduler, 50, &mut log);
+        assert_eq!(log.len(), 1);
+        update(&mut scheduler, 50, &mut log);
+        assert_eq!(log[1], (100, Creator::Done));
+        assert!(scheduler.is_idle(1));
+    }
+
+    #[test]
+    fn cancelling_creators_drop_the_rest_of_the_plan() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(1, Creator::Walk(vec![10]));
+        scheduler.push_creator(1, Creator::Fail);
+        scheduler.push_creator(1, Creator::Walk(vec![10]));
+
+        let events = update(&mut scheduler, 20, &mut log);
+        assert_eq!(events, vec![ActionEvent::Start(action(20, 30)),]);
+        let events = update(&mut scheduler, 10, &mut log);
+        assert_eq!(
+            events,
+            vec![
+                ActionEvent::End(action(20, 30)),
+                ActionEvent::Cancel {
+                    entity_id: 1,
+                    action: None,
+                },
+            ]
+        );
+        assert!(scheduler.is_idle(1));
+        assert_eq!(log.len(), 2);
+    }
+
+    #[test]
+    fn cancel_interrupts_the_current_action() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(1, Creator::Walk(vec![100, 100]));
+        scheduler.push_creator(2, Creator::Walk(vec![100]));
+        update(&mut scheduler, 10, &mut log);
+
+        assert_eq!(
+            scheduler.cancel(1),
+            Some(ActionEvent::Cancel {
+                entity_id: 1,
+                action: Some(action(10, 110)),
+            })
+        );
+        assert!(scheduler.is_idle(1));
+        assert_eq!(scheduler.cancel(1), None);
+
+        // Other entities carry on
+        let events = update(&mut scheduler, 100, &mut log);
+        assert_eq!(events.len(), 1);
+        assert!(scheduler.is_idle(2));
+    }
+
+    #[test]
+    fn chained_creators_run_in_order_before_queued_ones() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(
+            1,
+            Creator::Then(vec![
+                Creator::Walk(vec![10]),
+                Creator::Then(vec![Creator::Done, Creator::Walk(vec![20])]),
+            ]),
+        );
+        scheduler.push_creator(1, Creator::Walk(vec![30]));
+
+        let mut events = vec![];
+        for _ in 0..7 {
+            events.extend(update(&mut scheduler, 10, &mut log));
+        }
+        let starts = events
+            .iter()
+            .filter_map(|event| match event {
+                ActionEvent::Start(action) => Some((action.start, action.end)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![(10, 20), (20, 40), (40, 70)]);
+        assert!(scheduler.is_idle(1));
+    }
+
+    #[test]
+    fn endless_chains_are_cut_off() {
+        let mut scheduler = TestScheduler::new();
+        let mut log = vec![];
+        scheduler.push_creator(1, Creator::Forever);
+        assert!(update(&mut scheduler, 10, &mut log).is_empty());
+        assert_eq!(log.len(), MAX_CREATOR_EVALUATIONS);
+        assert!(!scheduler.is_idle(1));
+    }
+}
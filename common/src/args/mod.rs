@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+/// Reads `--flag value` style command lines for the platform binaries.
+///
+/// ```ignore
+/// let mut args = ArgParser::new(std::env::args().skip(1));
+/// while let Some(arg) = args.next_flag() {
+///     match arg.as_str() {
+///         "--tick" => tick = args.number(&arg)?,
+///         _ => return Err(unknown_argument(&arg)),
+///     }
+/// }
+/// ```
+pub struct ArgParser<I> {
+    args: I,
+}
+
+impl<I: Iterator<Item = String>> ArgParser<I> {
+    pub fn new(args: I) -> Self {
+        ArgParser { args }
+    }
+
+    pub fn next_flag(&mut self) -> Option<String> {
+        self.args.next()
+    }
+
+    /// The value following `flag`.
+    pub fn value(&mut self, flag: &str) -> Result<String, String> {
+        self.args
+            .next()
+            .ok_or_else(|| format!("{} requires a value", flag))
+    }
+
+    pub fn number<T: FromStr>(&mut self, flag: &str) -> Result<T, String> {
+        parse_number(flag, &self.value(flag)?)
+    }
+
+    pub fn path(&mut self, flag: &str) -> Result<PathBuf, String> {
+        Ok(PathBuf::from(self.value(flag)?))
+    }
+
+    /// A `WIDTHxHEIGHT` value.
+    pub fn size(&mut self, flag: &str) -> Result<(u32, u32), String> {
+        parse_size(flag, &self.value(flag)?)
+    }
+}
+
+pub fn unknown_argument(flag: &str) -> String {
+    format!("unknown argument: {}", flag)
+}
+
+pub fn parse_number<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {:?}", name, value))
+}
+
+pub fn parse_size(name: &str, value: &str) -> Result<(u32, u32), String> {
+    let mut split = value.splitn(2, 'x');
+    match (split.next(), split.next()) {
+        (Some(width), Some(height)) => {
+            let size = (parse_number(name, width)?, parse_number(name, height)?);
+            if size.0 == 0 || size.1 == 0 {
+                return Err(format!("{} can't be empty, got {:?}", name, value));
+            }
+            Ok(size)
+        }
+        _ => Err(format!("{} expects WIDTHxHEIGHT, got {:?}", name, value)),
+    }
+}
+
+/// Cleared on Ctrl-C or SIGTERM, so a binary's main loop can end and the process exit cleanly.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn stop_on_interrupt() -> Arc<AtomicBool> {
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = Arc::clone(&running);
+    if let Err(e) = ctrlc::set_handler(move || handler_running.store(false, Ordering::Relaxed)) {
+        log::warn!("Failed to handle Ctrl-C: {}", e);
+    }
+    running
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser(args: &[&str]) -> ArgParser<std::vec::IntoIter<String>> {
+        ArgParser::new(
+            args.iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        )
+    }
+
+    #[test]
+    fn reads_flags_and_their_values() {
+        let mut args = parser(&["--tick", "50", "--size", "640x480", "--fast"]);
+        assert_eq!(args.next_flag().as_deref(), Some("--tick"));
+        assert_eq!(args.number::<u64>("--tick"), Ok(50));
+        assert_eq!(args.next_flag().as_deref(), Some("--size"));
+        assert_eq!(args.size("--size"), Ok((640, 480)));
+        assert_eq!(args.next_flag().as_deref(), Some("--fast"));
+        assert_eq!(args.next_flag(), None);
+    }
+
+    #[test]
+    fn reports_bad_values() {
+        assert_eq!(
+            parser(&[]).value("--tick"),
+            Err("--tick requires a value".to_string())
+        );
+        assert_eq!(
+            parser(&["fast"]).number::<u64>("--tick"),
+            Err("--tick expects a number, got \"fast\"".to_string())
+        );
+        assert!(parse_size("--size", "640").is_err());
+        assert!(parse_size("--size", "640xwide").is_err());
+        assert_eq!(
+            parse_size("--size", "0x0"),
+            Err("--size can't be empty, got \"0x0\"".to_string())
+        );
+        assert!(parse_size("--size", "640x0").is_err());
+    }
+}
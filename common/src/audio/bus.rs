@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// Submix that voices are routed into, so whole categories can be turned down or muted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bus {
+    Music,
+    Sfx,
+    Ui,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 3] = [Bus::Music, Bus::Sfx, Bus::Ui];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Bus::Music => "music",
+            Bus::Sfx => "sfx",
+            Bus::Ui => "ui",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Bus> {
+        Bus::ALL.iter().copied().find(|bus| bus.name() == name)
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BusState {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl BusState {
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0f32
+        } else {
+            self.volume
+        }
+    }
+}
+
+impl Default for BusState {
+    fn default() -> Self {
+        BusState {
+            volume: 1.0f32,
+            muted: false,
+        }
+    }
+}
@@ -0,0 +1,465 @@
+use crate::audio::bus::{Bus, BusState};
+use crate::audio::sound::Sound;
+use crate::audio::stream::MusicStream;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VoiceHandle(u64);
+
+#[derive(Clone, Copy, Debug)]
+pub struct PlayOptions {
+    pub volume: f32,
+    /// `-1.0` is fully left, `1.0` fully right.
+    pub pan: f32,
+    pub looping: bool,
+    /// Playback speed, which also shifts the pitch. `1.0` plays the sound as recorded.
+    pub rate: f32,
+    pub bus: Bus,
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        PlayOptions {
+            volume: 1.0f32,
+            pan: 0.0f32,
+            looping: false,
+            rate: 1.0f32,
+            bus: Bus::Sfx,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MusicOptions {
+    pub volume: f32,
+    pub looping: bool,
+    /// Seconds to fade in from silence.
+    pub fade_in: f32,
+}
+
+impl Default for MusicOptions {
+    fn default() -> Self {
+        MusicOptions {
+            volume: 1.0f32,
+            looping: true,
+            fade_in: 0.0f32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl Fade {
+    fn gain(&self) -> f32 {
+        if self.duration <= 0.0f32 {
+            return self.to;
+        }
+        let t = (self.elapsed / self.duration).min(1.0f32);
+        self.from + (self.to - self.from) * t
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// A streamed track on the music bus. Frames are taken from `stream` into `buffer` as playback
+/// reaches them.
+struct MusicTrack {
+    stream: MusicStream,
+    options: MusicOptions,
+    buffer: VecDeque<(f32, f32)>,
+    /// Fractional position inside `buffer`
+    position: f64,
+    fade: Fade,
+    /// Set once the stream has handed over its last frame.
+    ended: bool,
+}
+
+impl MusicTrack {
+    fn new(stream: MusicStream, options: MusicOptions) -> Self {
+        MusicTrack {
+            stream,
+            options,
+            buffer: VecDeque::new(),
+            position: 0.0,
+            fade: Fade {
+                from: 0.0f32,
+                to: 1.0f32,
+                elapsed: 0.0f32,
+                duration: options.fade_in,
+            },
+            ended: false,
+        }
+    }
+
+    fn fade_to(&mut self, to: f32, duration: f32) {
+        self.fade = Fade {
+            from: self.fade.gain(),
+            to,
+            elapsed: 0.0f32,
+            duration,
+        };
+    }
+
+    /// Takes enough frames from the stream to play `frames` output frames, if they are decoded.
+    fn fill(&mut self, frames: usize, step: f64) {
+        let needed = self.position as usize + (frames as f64 * step).ceil() as usize + 2;
+        let missing = needed.saturating_sub(self.buffer.len());
+        if !self.ended && missing > 0 {
+            self.ended = self.stream.take(&mut self.buffer, missing);
+        }
+    }
+
+    /// Returns `None` when the stream ran dry, either for good or until more is decoded.
+    fn next_frame(&mut self, step: f64) -> Option<(f32, f32)> {
+        let index = self.position as usize;
+        let (l0, r0) = *self.buffer.get(index)?;
+        let (l1, r1) = self.buffer.get(index + 1).copied().unwrap_or((l0, r0));
+        let t = (self.position - index as f64) as f32;
+
+        self.position += step;
+        let consumed = (self.position as usize).min(self.buffer.len());
+        self.buffer.drain(..consumed);
+        self.position -= consumed as f64;
+
+        Some((l0 + (l1 - l0) * t, r0 + (r1 - r0) * t))
+    }
+}
+
+struct Voice {
+    handle: VoiceHandle,
+    sound: Sound,
+    options: PlayOptions,
+    /// Position in source frames; fractional because the sound is resampled on the fly.
+    position: f64,
+}
+
+impl Voice {
+    /// Linearly interpolated source frame at the current position.
+    fn sample(&self) -> (f32, f32) {
+        let frame_count = self.sound.frame_count();
+        let index = self.position as usize;
+        let t = (self.position - index as f64) as f32;
+        let next = if index + 1 < frame_count {
+            index + 1
+        } else if self.options.looping {
+            0
+        } else {
+            index
+        };
+        let (l0, r0) = self.sound.stereo_frame(index);
+        let (l1, r1) = self.sound.stereo_frame(next);
+        (l0 + (l1 - l0) * t, r0 + (r1 - r0) * t)
+    }
+}
+
+/// Mixes any number of voices into interleaved `f32` output.
+///
+/// The output stream calls `render` from the audio thread, but nothing here depends on a device,
+/// so the mixer can also be driven manually to render into a buffer.
+pub struct Mixer {
+    sample_rate: u32,
+    channels: u16,
+    voices: Vec<Voice>,
+    next_handle: u64,
+    buses: [BusState; 3],
+    /// The current track is last; earlier ones are fading out.
+    music: Vec<MusicTrack>,
+    pub master_volume: f32,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Mixer {
+            sample_rate,
+            channels,
+            voices: vec![],
+            next_handle: 0,
+            buses: [BusState::default(); 3],
+            music: vec![],
+            master_volume: 1.0f32,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn play(&mut self, sound: &Sound, options: PlayOptions) -> VoiceHandle {
+        let handle = VoiceHandle(self.next_handle);
+        self.next_handle += 1;
+        if sound.frame_count() > 0 {
+            self.voices.push(Voice {
+                handle,
+                sound: sound.clone(),
+                options,
+                position: 0.0,
+            });
+        }
+        handle
+    }
+
+    pub fn stop(&mut self, handle: VoiceHandle) {
+        self.voices.retain(|voice| voice.handle != handle);
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+
+    pub fn is_playing(&self, handle: VoiceHandle) -> bool {
+        self.voices.iter().any(|voice| voice.handle == handle)
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn set_volume(&mut self, handle: VoiceHandle, volume: f32) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.options.volume = volume;
+        }
+    }
+
+    pub fn set_pan(&mut self, handle: VoiceHandle, pan: f32) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.options.pan = pan.max(-1.0f32).min(1.0f32);
+        }
+    }
+
+    pub fn set_looping(&mut self, handle: VoiceHandle, looping: bool) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.options.looping = looping;
+        }
+    }
+
+    pub fn set_rate(&mut self, handle: VoiceHandle, rate: f32) {
+        if let Some(voice) = self.voice_mut(handle) {
+            voice.options.rate = rate.max(0.0f32);
+        }
+    }
+
+    pub fn bus(&self, bus: Bus) -> BusState {
+        self.buses[bus.index()]
+    }
+
+    pub fn set_bus_volume(&mut self, bus: Bus, volume: f32) {
+        self.buses[bus.index()].volume = volume;
+    }
+
+    pub fn set_bus_muted(&mut self, bus: Bus, muted: bool) {
+        self.buses[bus.index()].muted = muted;
+    }
+
+    /// Replaces the music immediately, fading in over `options.fade_in` if it is set.
+    ///
+    /// `options.looping` is up to the `MusicDecoder` feeding `stream`.
+    pub fn play_music(&mut self, stream: MusicStream, options: MusicOptions) {
+        self.music.clear();
+        self.music.push(MusicTrack::new(stream, options));
+    }
+
+    /// Fades the current music out while `stream` fades in over `duration` seconds.
+    pub fn crossfade_music(&mut self, stream: MusicStream, options: MusicOptions, duration: f32) {
+        self.stop_music(duration);
+        self.music.push(MusicTrack::new(
+            stream,
+            MusicOptions {
+                fade_in: duration,
+                ..options
+            },
+        ));
+    }
+
+    pub fn stop_music(&mut self, fade_out: f32) {
+        for track in self.music.iter_mut() {
+            track.fade_to(0.0f32, fade_out);
+        }
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        self.music
+            .last()
+            .map(|track| !track.ended && track.fade.to > 0.0f32)
+            .unwrap_or(false)
+    }
+
+    /// Fills `output` with interleaved frames, replacing what was there.
+    pub fn render(&mut self, output: &mut [f32]) {
+        for sample in output.iter_mut() {
+            *sample = 0.0f32;
+        }
+
+        let channels = self.channels.max(1) as usize;
+        let output_rate = self.sample_rate as f64;
+        let master_volume = self.master_volume;
+        for voice in self.voices.iter_mut() {
+            let step = voice.sound.sample_rate() as f64 / output_rate * voice.options.rate as f64;
+            let frame_count = voice.sound.frame_count() as f64;
+            let (left_gain, right_gain) = pan_gains(voice.options.pan);
+            let volume =
+                voice.options.volume * self.buses[voice.options.bus.index()].gain() * master_volume;
+
+            for frame in output.chunks_mut(channels) {
+                if voice.position >= frame_count {
+                    if !voice.options.looping {
+                        break;
+                    }
+                    voice.position %= frame_count;
+                }
+                let (left, right) = voice.sample();
+                let left = left * left_gain * volume;
+                let right = right * right_gain * volume;
+                match frame.len() {
+                    1 => frame[0] += (left + right) * 0.5f32,
+                    _ => {
+                        frame[0] += left;
+                        frame[1] += right;
+                    }
+                }
+                voice.position += step;
+            }
+        }
+        self.voices.retain(|voice| {
+            voice.options.looping || voice.position < voice.sound.frame_count() as f64
+        });
+
+        let music_gain = self.buses[Bus::Music.index()].gain() * master_volume;
+        let frame_time = 1.0f32 / self.sample_rate as f32;
+        for track in self.music.iter_mut() {
+            let step = track.stream.sample_rate() as f64 / output_rate;
+            track.fill(output.len() / channels, step);
+            for frame in output.chunks_mut(channels) {
+                let (left, right) = match track.next_frame(step) {
+                    Some(frame) => frame,
+                    None => break,
+                };
+                let gain = track.options.volume * track.fade.gain() * music_gain;
+                track.fade.elapsed += frame_time;
+                match frame.len() {
+                    1 => frame[0] += (left + right) * 0.5f32 * gain,
+                    _ => {
+                        frame[0] += left * gain;
+                        frame[1] += right * gain;
+                    }
+                }
+            }
+        }
+        self.music.retain(|track| {
+            let faded_out = track.fade.to <= 0.0f32 && track.fade.is_finished();
+            let ended = track.ended && track.buffer.is_empty();
+            !faded_out && !ended
+        });
+
+        for sample in output.iter_mut() {
+            *sample = sample.max(-1.0f32).min(1.0f32);
+        }
+    }
+
+    fn voice_mut(&mut self, handle: VoiceHandle) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|voice| voice.handle == handle)
+    }
+}
+
+/// Constant-power panning: the perceived loudness stays the same across the stereo field.
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan.max(-1.0f32).min(1.0f32) + 1.0f32) * std::f32::consts::FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::stream::tests::TestSource;
+    use crate::audio::stream::MusicDecoder;
+
+    fn music(source: TestSource) -> (MusicDecoder, MusicStream) {
+        MusicDecoder::new(Box::new(source), false)
+    }
+
+    fn left_channel(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut output = vec![0.0f32; frames * 2];
+        mixer.render(&mut output);
+        output.chunks(2).map(|frame| frame[0]).collect()
+    }
+
+    #[test]
+    fn pulls_decoded_music_and_removes_ended_tracks() {
+        let mut mixer = Mixer::new(4, 2);
+        let (mut decoder, stream) = music(TestSource::mono(vec![0.5f32; 3]));
+        decoder.decode_ahead(16);
+        mixer.play_music(stream, MusicOptions::default());
+        assert!(mixer.is_music_playing());
+
+        assert_eq!(
+            left_channel(&mut mixer, 4),
+            vec![0.5f32, 0.5f32, 0.5f32, 0.0f32]
+        );
+        assert!(!mixer.is_music_playing());
+        assert!(mixer.music.is_empty());
+    }
+
+    #[test]
+    fn waits_for_the_decoder_on_underrun() {
+        let mut mixer = Mixer::new(4, 2);
+        let (mut decoder, stream) = music(TestSource::mono(vec![0.5f32; 8]));
+        decoder.decode_ahead(2);
+        mixer.play_music(stream, MusicOptions::default());
+
+        assert_eq!(
+            left_channel(&mut mixer, 4),
+            vec![0.5f32, 0.5f32, 0.0f32, 0.0f32]
+        );
+        // Not ended, only starved; playback resumes once more is decoded
+        assert!(mixer.is_music_playing());
+        decoder.decode_ahead(8);
+        assert_eq!(left_channel(&mut mixer, 2), vec![0.5f32, 0.5f32]);
+    }
+
+    #[test]
+    fn resamples_music_to_the_output_rate() {
+        let mut mixer = Mixer::new(4, 2);
+        let source = TestSource {
+            sample_rate: 8,
+            ..TestSource::mono(vec![0.0f32, 0.1f32, 0.2f32, 0.3f32, 0.4f32, 0.5f32])
+        };
+        let (mut decoder, stream) = music(source);
+        decoder.decode_ahead(16);
+        mixer.play_music(stream, MusicOptions::default());
+
+        let left = left_channel(&mut mixer, 3);
+        for (sample, expected) in left.iter().zip([0.0f32, 0.2f32, 0.4f32].iter()) {
+            assert!((sample - expected).abs() < 1e-6f32);
+        }
+    }
+
+    #[test]
+    fn crossfades_between_tracks() {
+        let mut mixer = Mixer::new(4, 2);
+        let (mut old, stream) = music(TestSource::mono(vec![1.0f32; 8]));
+        old.decode_ahead(16);
+        mixer.play_music(stream, MusicOptions::default());
+        let (mut new, stream) = music(TestSource::mono(vec![1.0f32; 8]));
+        new.decode_ahead(16);
+        mixer.crossfade_music(stream, MusicOptions::default(), 1.0f32);
+
+        // Linear fades add up to the full volume at every frame
+        let left = left_channel(&mut mixer, 4);
+        for sample in left {
+            assert!((sample - 1.0f32).abs() < 1e-6f32);
+        }
+        left_channel(&mut mixer, 1);
+        assert_eq!(mixer.music.len(), 1);
+    }
+}
@@ -0,0 +1,401 @@
+pub mod bus;
+pub mod mixer;
+pub mod output;
+pub mod sound;
+pub mod spatial;
+pub mod stream;
+
+use crate::audio::bus::Bus;
+use crate::audio::mixer::{Mixer, MusicOptions, PlayOptions, VoiceHandle};
+use crate::audio::output::AudioOutput;
+use crate::audio::sound::{AudioError, Sound};
+use crate::audio::stream::{open_source, MusicDecoder, MusicStream};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tearchan::fs::{file_util, read_bytes_from_file};
+
+/// Sample rate of the silent mixer used when no output device could be opened.
+const FALLBACK_SAMPLE_RATE: u32 = 44100;
+
+/// Seconds of music decoded ahead of playback, enough to ride out a slow frame.
+const MUSIC_LOOKAHEAD: f32 = 1.0f32;
+
+/// Seconds the music of one scene takes to blend into the next one's.
+pub const SCENE_MUSIC_CROSSFADE: f32 = 1.5f32;
+
+/// Plays sounds through the shared output stream.
+///
+/// Cloning is cheap; every clone talks to the same mixer and sound bank.
+#[derive(Clone)]
+pub struct Audio {
+    mixer: Arc<Mutex<Mixer>>,
+    sounds: Arc<Mutex<HashMap<String, Sound>>>,
+    /// Encoded music files, decoded while they play
+    music: Arc<Mutex<HashMap<String, Arc<[u8]>>>>,
+    /// Decoders of the tracks the mixer is playing, topped up by `update`
+    decoders: Arc<Mutex<Vec<MusicDecoder>>>,
+    current_music: Arc<Mutex<Option<String>>>,
+}
+
+impl Audio {
+    /// Creates an `Audio` that renders nowhere; drive it with `render` to inspect the output.
+    pub fn offline(sample_rate: u32, channels: u16) -> Self {
+        Audio::with_mixer(Arc::new(Mutex::new(Mixer::new(sample_rate, channels))))
+    }
+
+    fn with_mixer(mixer: Arc<Mutex<Mixer>>) -> Self {
+        Audio {
+            mixer,
+            sounds: Arc::new(Mutex::new(HashMap::new())),
+            music: Arc::new(Mutex::new(HashMap::new())),
+            decoders: Arc::new(Mutex::new(vec![])),
+            current_music: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn mixer(&self) -> &Arc<Mutex<Mixer>> {
+        &self.mixer
+    }
+
+    /// Reads and decodes a WAV or Ogg Vorbis file through `tearchan::fs` and stores it as `name`.
+    pub async fn load<P: AsRef<Path>>(&self, name: &str, path: P) -> Result<(), AudioError> {
+        let path = path.as_ref().to_path_buf();
+        let bytes = read_bytes_from_file(path.clone())
+            .await
+            .map_err(|e| AudioError::Load(format!("{:?}: {:?}", path, e)))?;
+        let sound = Sound::decode(&bytes)?;
+        self.insert(name, sound);
+        Ok(())
+    }
+
+    /// Reads a music file and stores it as `name` without decoding it.
+    pub async fn load_music<P: AsRef<Path>>(&self, name: &str, path: P) -> Result<(), AudioError> {
+        let path = path.as_ref().to_path_buf();
+        let bytes = read_bytes_from_file(path.clone())
+            .await
+            .map_err(|e| AudioError::Load(format!("{:?}: {:?}", path, e)))?;
+        let bytes: Arc<[u8]> = Arc::from(&bytes[..]);
+        // Fail early on files that can't be streamed or would never produce a sample
+        let mut source = open_source(Arc::clone(&bytes))?;
+        if source.read(&mut vec![], 1)? == 0 {
+            return Err(AudioError::EmptyStream);
+        }
+        self.music.lock().unwrap().insert(name.to_string(), bytes);
+        Ok(())
+    }
+
+    pub fn insert(&self, name: &str, sound: Sound) {
+        self.sounds.lock().unwrap().insert(name.to_string(), sound);
+    }
+
+    pub fn sound(&self, name: &str) -> Option<Sound> {
+        self.sounds.lock().unwrap().get(name).cloned()
+    }
+
+    /// Plays the sound stored as `name`, or returns `None` if it hasn't finished loading.
+    pub fn play(&self, name: &str, options: PlayOptions) -> Option<VoiceHandle> {
+        let sound = self.sound(name)?;
+        Some(self.play_sound(&sound, options))
+    }
+
+    pub fn play_sound(&self, sound: &Sound, options: PlayOptions) -> VoiceHandle {
+        self.mixer.lock().unwrap().play(sound, options)
+    }
+
+    pub fn stop(&self, handle: VoiceHandle) {
+        self.mixer.lock().unwrap().stop(handle);
+    }
+
+    pub fn set_volume(&self, handle: VoiceHandle, volume: f32) {
+        self.mixer.lock().unwrap().set_volume(handle, volume);
+    }
+
+    pub fn set_pan(&self, handle: VoiceHandle, pan: f32) {
+        self.mixer.lock().unwrap().set_pan(handle, pan);
+    }
+
+    pub fn set_looping(&self, handle: VoiceHandle, looping: bool) {
+        self.mixer.lock().unwrap().set_looping(handle, looping);
+    }
+
+    pub fn set_rate(&self, handle: VoiceHandle, rate: f32) {
+        self.mixer.lock().unwrap().set_rate(handle, rate);
+    }
+
+    /// Starts the music stored as `name`, replacing whatever was playing.
+    pub fn play_music(&self, name: &str, options: MusicOptions) -> Result<(), AudioError> {
+        let stream = self.open_music(name, options)?;
+        self.mixer.lock().unwrap().play_music(stream, options);
+        Ok(())
+    }
+
+    /// Crossfades from the current music to `name`.
+    ///
+    /// The mixer outlives scenes, so calling this from a scene factory blends the music across
+    /// the transition; see `crossfade_to_scene_music`.
+    pub fn crossfade_music(
+        &self,
+        name: &str,
+        options: MusicOptions,
+        duration: f32,
+    ) -> Result<(), AudioError> {
+        let stream = self.open_music(name, options)?;
+        self.mixer
+            .lock()
+            .unwrap()
+            .crossfade_music(stream, options, duration);
+        Ok(())
+    }
+
+    pub fn stop_music(&self, fade_out: f32) {
+        self.mixer.lock().unwrap().stop_music(fade_out);
+        *self.current_music.lock().unwrap() = None;
+    }
+
+    /// Name of the music last started with `play_music` or `crossfade_music`, unless stopped since.
+    pub fn current_music(&self) -> Option<String> {
+        self.current_music.lock().unwrap().clone()
+    }
+
+    /// Decodes music ahead of playback. Call once per frame; `GameContext` does this for scenes.
+    pub fn update(&self) {
+        let mut decoders = self.decoders.lock().unwrap();
+        let active = decoders
+            .drain(..)
+            .filter_map(|mut decoder| {
+                let lookahead = lookahead_frames(decoder.sample_rate());
+                if decoder.decode_ahead(lookahead) {
+                    Some(decoder)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        *decoders = active;
+    }
+
+    pub fn set_bus_volume(&self, bus: Bus, volume: f32) {
+        self.mixer.lock().unwrap().set_bus_volume(bus, volume);
+    }
+
+    pub fn set_bus_muted(&self, bus: Bus, muted: bool) {
+        self.mixer.lock().unwrap().set_bus_muted(bus, muted);
+    }
+
+    pub fn render(&self, output: &mut [f32]) {
+        self.mixer.lock().unwrap().render(output);
+    }
+
+    /// Opens a decoder for `name` and decodes the first `MUSIC_LOOKAHEAD` seconds right away,
+    /// so playback doesn't start with a gap.
+    fn open_music(&self, name: &str, options: MusicOptions) -> Result<MusicStream, AudioError> {
+        let bytes = self
+            .music
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AudioError::Load(format!("Music {:?} is not loaded", name)))?;
+        let (mut decoder, stream) = MusicDecoder::new(open_source(bytes)?, options.looping);
+        let lookahead = lookahead_frames(stream.sample_rate());
+        if decoder.decode_ahead(lookahead) {
+            self.decoders.lock().unwrap().push(decoder);
+        }
+        *self.current_music.lock().unwrap() = Some(name.to_string());
+        Ok(stream)
+    }
+}
+
+fn lookahead_frames(sample_rate: u32) -> usize {
+    (sample_rate as f32 * MUSIC_LOOKAHEAD) as usize
+}
+
+/// Crossfades to the music file at `path`, relative to the assets directory, loading it first
+/// if needed. Scene factories spawn this so each scene brings its music along.
+pub async fn crossfade_to_scene_music(audio: Audio, path: &'static str) {
+    if audio.current_music().as_deref() == Some(path) {
+        return;
+    }
+    if !audio.music.lock().unwrap().contains_key(path) {
+        let mut asset_path = PathBuf::new();
+        asset_path.push(file_util().assets_path());
+        asset_path.push(path);
+        if let Err(e) = audio.load_music(path, asset_path).await {
+            log::error!("{}", e);
+            audio.stop_music(SCENE_MUSIC_CROSSFADE);
+            return;
+        }
+    }
+    if let Err(e) = audio.crossfade_music(path, MusicOptions::default(), SCENE_MUSIC_CROSSFADE) {
+        log::error!("{}", e);
+    }
+}
+
+thread_local! {
+    // cpal streams aren't Send on every platform, so the output lives on the thread that opened it
+    static AUDIO: RefCell<Option<(Option<AudioOutput>, Audio)>> = RefCell::new(None);
+}
+
+/// Returns the app-wide `Audio`, opening the default output device the first time.
+///
+/// Scenes get it through `GameContext::audio`, which shares it across scene transitions.
+/// If no device is available, a silent offline mixer is used so scenes keep working.
+pub(crate) fn shared_audio() -> Audio {
+    AUDIO.with(|cell| {
+        cell.borrow_mut()
+            .get_or_insert_with(|| match AudioOutput::open() {
+                Ok(output) => {
+                    let audio = Audio::with_mixer(Arc::clone(output.mixer()));
+                    (Some(output), audio)
+                }
+                Err(e) => {
+                    log::warn!("Audio is disabled: {}", e);
+                    (None, Audio::offline(FALLBACK_SAMPLE_RATE, 2))
+                }
+            })
+            .1
+            .clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant(value: f32, frames: usize) -> Sound {
+        Sound::new(4, 1, vec![value; frames])
+    }
+
+    #[test]
+    fn renders_voices_into_a_buffer() {
+        let audio = Audio::offline(4, 2);
+        audio.insert("tone", constant(0.5f32, 2));
+        let options = PlayOptions {
+            pan: -1.0f32,
+            ..PlayOptions::default()
+        };
+        assert!(audio.play("tone", options).is_some());
+        assert!(audio.play("missing", options).is_none());
+
+        let mut output = [1.0f32; 8];
+        audio.render(&mut output);
+        let (left, right) = mixer::pan_gains(-1.0f32);
+        for frame in output[..4].chunks(2) {
+            assert!((frame[0] - 0.5f32 * left).abs() < 1e-6f32);
+            assert!((frame[1] - 0.5f32 * right).abs() < 1e-6f32);
+        }
+        // The sound ended after two frames, and the rest of the buffer is cleared
+        assert_eq!(&output[4..], &[0.0f32; 4]);
+        assert_eq!(audio.mixer().lock().unwrap().voice_count(), 0);
+    }
+
+    #[test]
+    fn mixes_voices_and_clips() {
+        let audio = Audio::offline(4, 1);
+        audio.insert("loud", constant(0.75f32, 4));
+        audio.play("loud", PlayOptions::default());
+        audio.play("loud", PlayOptions::default());
+
+        let mut output = [0.0f32; 4];
+        audio.render(&mut output);
+        assert_eq!(output, [1.0f32; 4]);
+    }
+
+    #[test]
+    fn muted_buses_are_silent() {
+        let audio = Audio::offline(4, 1);
+        audio.insert("tone", constant(0.5f32, 4));
+        audio.set_bus_muted(Bus::Sfx, true);
+        audio.play("tone", PlayOptions::default());
+        let ui = audio.play(
+            "tone",
+            PlayOptions {
+                bus: Bus::Ui,
+                volume: 0.5f32,
+                ..PlayOptions::default()
+            },
+        );
+
+        // Centered voices play at equal power on both sides, which a mono output averages
+        let (center, _) = mixer::pan_gains(0.0f32);
+        let mut output = [0.0f32; 4];
+        audio.render(&mut output);
+        assert_eq!(output, [0.25f32 * center; 4]);
+        assert!(!audio.mixer().lock().unwrap().is_playing(ui.unwrap()));
+    }
+
+    fn write_wav(name: &str, samples: &[i16]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 4,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    #[test]
+    fn load_music_rejects_empty_streams() {
+        let audio = Audio::offline(4, 1);
+        let path = write_wav("empty-music", &[]);
+        let result = futures::executor::block_on(audio.load_music("empty", &path));
+        assert!(matches!(result, Err(AudioError::EmptyStream)));
+        assert!(audio.play_music("empty", MusicOptions::default()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn plays_loaded_music() {
+        let audio = Audio::offline(4, 1);
+        let path = write_wav("music", &[16384; 4]);
+        futures::executor::block_on(audio.load_music("music", &path)).unwrap();
+        audio.play_music("music", MusicOptions::default()).unwrap();
+        assert_eq!(audio.current_music().as_deref(), Some("music"));
+
+        let mut output = [0.0f32; 4];
+        audio.render(&mut output);
+        assert_eq!(output, [0.5f32; 4]);
+
+        audio.stop_music(0.0f32);
+        assert_eq!(audio.current_music(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn looping_voices_wrap_around() {
+        let audio = Audio::offline(4, 1);
+        audio.insert("step", Sound::new(4, 1, vec![0.0f32, 0.5f32]));
+        let handle = audio
+            .play(
+                "step",
+                PlayOptions {
+                    looping: true,
+                    ..PlayOptions::default()
+                },
+            )
+            .unwrap();
+
+        let (center, _) = mixer::pan_gains(0.0f32);
+        let mut output = [0.0f32; 6];
+        audio.render(&mut output);
+        for (index, sample) in output.iter().enumerate() {
+            let expected = if index % 2 == 0 {
+                0.0f32
+            } else {
+                0.5f32 * center
+            };
+            assert_eq!(*sample, expected);
+        }
+        assert!(audio.mixer().lock().unwrap().is_playing(handle));
+        audio.stop(handle);
+        assert!(!audio.mixer().lock().unwrap().is_playing(handle));
+    }
+}
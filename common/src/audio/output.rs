@@ -0,0 +1,67 @@
+use crate::audio::mixer::Mixer;
+use crate::audio::sound::AudioError;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::sync::{Arc, Mutex};
+
+/// The default output device, pulling samples from a shared `Mixer`.
+pub struct AudioOutput {
+    // Dropping the stream stops playback
+    _stream: cpal::Stream,
+    mixer: Arc<Mutex<Mixer>>,
+}
+
+impl AudioOutput {
+    pub fn open() -> Result<Self, AudioError> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or(AudioError::NoOutputDevice)?;
+        let supported_config = device
+            .default_output_config()
+            .map_err(|e| AudioError::Output(e.to_string()))?;
+        let sample_format = supported_config.sample_format();
+        let config: cpal::StreamConfig = supported_config.into();
+
+        let mixer = Arc::new(Mutex::new(Mixer::new(
+            config.sample_rate.0,
+            config.channels,
+        )));
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, Arc::clone(&mixer)),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, Arc::clone(&mixer)),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, Arc::clone(&mixer)),
+        }
+        .map_err(|e| AudioError::Output(e.to_string()))?;
+        stream
+            .play()
+            .map_err(|e| AudioError::Output(e.to_string()))?;
+
+        Ok(AudioOutput {
+            _stream: stream,
+            mixer,
+        })
+    }
+
+    pub fn mixer(&self) -> &Arc<Mutex<Mixer>> {
+        &self.mixer
+    }
+}
+
+fn build_stream<T: cpal::Sample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mixer: Arc<Mutex<Mixer>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    let mut buffer = vec![];
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            buffer.resize(data.len(), 0.0f32);
+            mixer.lock().unwrap().render(&mut buffer);
+            for (output, sample) in data.iter_mut().zip(buffer.iter()) {
+                *output = T::from(sample);
+            }
+        },
+        |e| log::error!("Audio stream error: {}", e),
+    )
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::io::Cursor;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub enum AudioError {
+    UnsupportedFormat,
+    EmptyStream,
+    Wav(hound::Error),
+    Vorbis(lewton::VorbisError),
+    Load(String),
+    NoOutputDevice,
+    Output(String),
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioError::UnsupportedFormat => write!(f, "unsupported audio format"),
+            AudioError::EmptyStream => write!(f, "the audio stream has no samples"),
+            AudioError::Wav(e) => write!(f, "failed to decode WAV: {}", e),
+            AudioError::Vorbis(e) => write!(f, "failed to decode Ogg Vorbis: {}", e),
+            AudioError::Load(e) => write!(f, "failed to load audio: {}", e),
+            AudioError::NoOutputDevice => write!(f, "no audio output device is available"),
+            AudioError::Output(e) => write!(f, "failed to open the audio output: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// Fully decoded audio, interleaved `f32` samples in `-1.0..=1.0`.
+#[derive(Clone, Debug)]
+pub struct Sound {
+    sample_rate: u32,
+    channels: u16,
+    samples: Arc<[f32]>,
+}
+
+impl Sound {
+    pub fn new(sample_rate: u32, channels: u16, samples: Vec<f32>) -> Self {
+        Sound {
+            sample_rate,
+            channels,
+            samples: samples.into(),
+        }
+    }
+
+    /// Decodes WAV or Ogg Vorbis, detected from the file header.
+    pub fn decode(bytes: &[u8]) -> Result<Self, AudioError> {
+        if bytes.starts_with(b"RIFF") {
+            Self::decode_wav(bytes)
+        } else if bytes.starts_with(b"OggS") {
+            Self::decode_vorbis(bytes)
+        } else {
+            Err(AudioError::UnsupportedFormat)
+        }
+    }
+
+    pub fn decode_wav(bytes: &[u8]) -> Result<Self, AudioError> {
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).map_err(AudioError::Wav)?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(AudioError::Wav)?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0f32 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|sample| sample.map(|sample| sample as f32 * scale))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(AudioError::Wav)?
+            }
+        };
+        Ok(Sound::new(spec.sample_rate, spec.channels, samples))
+    }
+
+    pub fn decode_vorbis(bytes: &[u8]) -> Result<Self, AudioError> {
+        let mut reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))
+            .map_err(AudioError::Vorbis)?;
+        let mut samples = vec![];
+        while let Some(packet) = reader.read_dec_packet_itl().map_err(AudioError::Vorbis)? {
+            samples.extend(packet.iter().map(|sample| *sample as f32 / 32768.0f32));
+        }
+        Ok(Sound::new(
+            reader.ident_hdr.audio_sample_rate,
+            reader.ident_hdr.audio_channels as u16,
+            samples,
+        ))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frame_count() as f32 / self.sample_rate as f32
+    }
+
+    /// Returns the left and right sample of `frame`; mono sounds are duplicated.
+    pub(crate) fn stereo_frame(&self, frame: usize) -> (f32, f32) {
+        match self.channels {
+            1 => {
+                let sample = self.samples[frame];
+                (sample, sample)
+            }
+            channels => {
+                let offset = frame * channels as usize;
+                (self.samples[offset], self.samples[offset + 1])
+            }
+        }
+    }
+}
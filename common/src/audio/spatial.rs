@@ -0,0 +1,446 @@
+use crate::audio::bus::Bus;
+use crate::audio::mixer::{PlayOptions, VoiceHandle};
+use crate::audio::Audio;
+use nalgebra_glm::{cross, dot, length, normalize, vec3, Vec3};
+use std::collections::HashMap;
+use tearchan_gfx::camera::Camera3D;
+
+/// World units per second; matches metres in air.
+pub const DEFAULT_SPEED_OF_SOUND: f32 = 343.3f32;
+
+const MIN_DOPPLER_RATE: f32 = 0.25f32;
+const MAX_DOPPLER_RATE: f32 = 4.0f32;
+
+/// How gain falls off with distance. The formulas follow the Web Audio `PannerNode`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttenuationModel {
+    /// Reaches zero at `max_distance` when `rolloff` is 1.
+    Linear,
+    /// Roughly physical: halves each time the distance doubles.
+    Inverse,
+    Exponential,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Attenuation {
+    pub model: AttenuationModel,
+    /// Distance below which the sound plays at full volume.
+    pub reference_distance: f32,
+    /// Distance beyond which the gain stops changing.
+    pub max_distance: f32,
+    pub rolloff: f32,
+}
+
+impl Attenuation {
+    pub fn gain(&self, distance: f32) -> f32 {
+        let reference = self.reference_distance.max(std::f32::EPSILON);
+        let max = self.max_distance.max(reference);
+        let distance = distance.max(reference).min(max);
+        let gain = match self.model {
+            AttenuationModel::Linear => {
+                if max <= reference {
+                    1.0f32
+                } else {
+                    1.0f32 - self.rolloff * (distance - reference) / (max - reference)
+                }
+            }
+            AttenuationModel::Inverse => {
+                reference / (reference + self.rolloff * (distance - reference))
+            }
+            AttenuationModel::Exponential => (distance / reference).powf(-self.rolloff),
+        };
+        gain.max(0.0f32).min(1.0f32)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Attenuation {
+            model: AttenuationModel::Inverse,
+            reference_distance: 1.0f32,
+            max_distance: 100.0f32,
+            rolloff: 1.0f32,
+        }
+    }
+}
+
+/// The ears of the scene, usually following the camera.
+#[derive(Clone, Copy, Debug)]
+pub struct Listener {
+    pub position: Vec3,
+    pub forward: Vec3,
+    pub up: Vec3,
+    /// World units per second, used for doppler.
+    pub velocity: Vec3,
+}
+
+impl Listener {
+    pub fn from_camera(camera: &Camera3D) -> Self {
+        Listener {
+            position: camera.position,
+            forward: direction_or(
+                camera.target_position - camera.position,
+                vec3(0.0f32, 0.0f32, -1.0f32),
+            ),
+            up: direction_or(camera.up, vec3(0.0f32, 1.0f32, 0.0f32)),
+            velocity: Vec3::zeros(),
+        }
+    }
+
+    /// Moves to the camera pose, deriving the velocity from how far it moved over `delta` seconds.
+    pub fn follow_camera(&mut self, camera: &Camera3D, delta: f32) {
+        let next = Listener::from_camera(camera);
+        self.velocity = if delta > 0.0f32 {
+            (next.position - self.position) / delta
+        } else {
+            Vec3::zeros()
+        };
+        self.position = next.position;
+        self.forward = next.forward;
+        self.up = next.up;
+    }
+
+    pub fn right(&self) -> Vec3 {
+        direction_or(cross(&self.forward, &self.up), vec3(1.0f32, 0.0f32, 0.0f32))
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Listener {
+            position: Vec3::zeros(),
+            forward: vec3(0.0f32, 0.0f32, -1.0f32),
+            up: vec3(0.0f32, 1.0f32, 0.0f32),
+            velocity: Vec3::zeros(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EmitterOptions {
+    pub volume: f32,
+    pub looping: bool,
+    pub bus: Bus,
+    pub attenuation: Attenuation,
+    /// Shifts the pitch when the emitter and listener move relative to each other.
+    pub doppler: bool,
+}
+
+impl Default for EmitterOptions {
+    fn default() -> Self {
+        EmitterOptions {
+            volume: 1.0f32,
+            looping: false,
+            bus: Bus::Sfx,
+            attenuation: Attenuation::default(),
+            doppler: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Emitter {
+    position: Vec3,
+    previous_position: Vec3,
+    velocity: Vec3,
+    options: EmitterOptions,
+}
+
+/// Gain, pan and playback rate of an emitter as heard by a listener.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spatialized {
+    pub gain: f32,
+    pub pan: f32,
+    pub rate: f32,
+}
+
+/// Plays sounds from positions in world space.
+///
+/// Call `follow_camera` and `update` once per frame; voices are re-panned and re-attenuated
+/// against the listener each time.
+pub struct SpatialAudio {
+    audio: Audio,
+    listener: Listener,
+    // Until the listener is placed, it sits at the origin and has no meaningful velocity
+    listener_placed: bool,
+    emitters: HashMap<VoiceHandle, Emitter>,
+    pub speed_of_sound: f32,
+    /// Exaggerates (> 1) or softens (< 1) the doppler shift.
+    pub doppler_factor: f32,
+}
+
+impl SpatialAudio {
+    pub fn new(audio: Audio) -> Self {
+        SpatialAudio {
+            audio,
+            listener: Listener::default(),
+            listener_placed: false,
+            emitters: HashMap::new(),
+            speed_of_sound: DEFAULT_SPEED_OF_SOUND,
+            doppler_factor: 1.0f32,
+        }
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+        self.listener_placed = true;
+    }
+
+    /// Moves the listener to the camera. The first call jumps there without any velocity,
+    /// so it doesn't read as a sudden flight from the origin.
+    pub fn follow_camera(&mut self, camera: &Camera3D, delta: f32) {
+        if self.listener_placed {
+            self.listener.follow_camera(camera, delta);
+        } else {
+            self.set_listener(Listener::from_camera(camera));
+        }
+    }
+
+    /// Plays the sound stored as `name` at `position`, or returns `None` if it isn't loaded.
+    pub fn play(
+        &mut self,
+        name: &str,
+        position: Vec3,
+        options: EmitterOptions,
+    ) -> Option<VoiceHandle> {
+        let emitter = Emitter {
+            position,
+            previous_position: position,
+            velocity: Vec3::zeros(),
+            options,
+        };
+        let spatialized = self.spatialize(&emitter);
+        let handle = self.audio.play(
+            name,
+            PlayOptions {
+                volume: options.volume * spatialized.gain,
+                pan: spatialized.pan,
+                looping: options.looping,
+                rate: spatialized.rate,
+                bus: options.bus,
+            },
+        )?;
+        self.emitters.insert(handle, emitter);
+        Some(handle)
+    }
+
+    pub fn set_position(&mut self, handle: VoiceHandle, position: Vec3) {
+        if let Some(emitter) = self.emitters.get_mut(&handle) {
+            emitter.position = position;
+        }
+    }
+
+    pub fn stop(&mut self, handle: VoiceHandle) {
+        self.emitters.remove(&handle);
+        self.audio.stop(handle);
+    }
+
+    pub fn emitter_count(&self) -> usize {
+        self.emitters.len()
+    }
+
+    /// Applies the listener and emitter positions to the playing voices.
+    /// `delta` is the time since the last update, used to derive emitter velocities.
+    pub fn update(&mut self, delta: f32) {
+        let mixer = self.audio.mixer().clone();
+        let mut mixer = mixer.lock().unwrap();
+        self.emitters.retain(|handle, _| mixer.is_playing(*handle));
+
+        let listener = self.listener;
+        let speed_of_sound = self.speed_of_sound;
+        let doppler_factor = self.doppler_factor;
+        for (handle, emitter) in self.emitters.iter_mut() {
+            emitter.velocity = if delta > 0.0f32 {
+                (emitter.position - emitter.previous_position) / delta
+            } else {
+                Vec3::zeros()
+            };
+            emitter.previous_position = emitter.position;
+
+            let spatialized = spatialize(
+                &listener,
+                emitter.position,
+                emitter.velocity,
+                &emitter.options,
+                speed_of_sound,
+                doppler_factor,
+            );
+            mixer.set_volume(*handle, emitter.options.volume * spatialized.gain);
+            mixer.set_pan(*handle, spatialized.pan);
+            mixer.set_rate(*handle, spatialized.rate);
+        }
+    }
+
+    fn spatialize(&self, emitter: &Emitter) -> Spatialized {
+        spatialize(
+            &self.listener,
+            emitter.position,
+            emitter.velocity,
+            &emitter.options,
+            self.speed_of_sound,
+            self.doppler_factor,
+        )
+    }
+}
+
+/// Computes how an emitter at `position` moving with `velocity` sounds to `listener`.
+pub fn spatialize(
+    listener: &Listener,
+    position: Vec3,
+    velocity: Vec3,
+    options: &EmitterOptions,
+    speed_of_sound: f32,
+    doppler_factor: f32,
+) -> Spatialized {
+    let offset = position - listener.position;
+    let distance = length(&offset);
+    let gain = options.attenuation.gain(distance);
+    let pan = if distance > std::f32::EPSILON {
+        dot(&(offset / distance), &listener.right())
+    } else {
+        0.0f32
+    };
+    let rate = if options.doppler && distance > std::f32::EPSILON {
+        doppler_rate(
+            -offset / distance,
+            listener.velocity,
+            velocity,
+            speed_of_sound,
+            doppler_factor,
+        )
+    } else {
+        1.0f32
+    };
+    Spatialized { gain, pan, rate }
+}
+
+/// The OpenAL doppler formula. `direction` points from the emitter to the listener.
+fn doppler_rate(
+    direction: Vec3,
+    listener_velocity: Vec3,
+    emitter_velocity: Vec3,
+    speed_of_sound: f32,
+    doppler_factor: f32,
+) -> f32 {
+    if speed_of_sound <= 0.0f32 || doppler_factor <= 0.0f32 {
+        return 1.0f32;
+    }
+    let limit = speed_of_sound / doppler_factor;
+    let listener_speed = dot(&direction, &listener_velocity).min(limit);
+    let emitter_speed = dot(&direction, &emitter_velocity).min(limit);
+    let denominator = speed_of_sound - doppler_factor * emitter_speed;
+    if denominator <= std::f32::EPSILON {
+        return MAX_DOPPLER_RATE;
+    }
+    let rate = (speed_of_sound - doppler_factor * listener_speed) / denominator;
+    rate.max(MIN_DOPPLER_RATE).min(MAX_DOPPLER_RATE)
+}
+
+fn direction_or(v: Vec3, fallback: Vec3) -> Vec3 {
+    if length(&v) > std::f32::EPSILON {
+        normalize(&v)
+    } else {
+        fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4f32;
+
+    fn options(doppler: bool) -> EmitterOptions {
+        EmitterOptions {
+            doppler,
+            ..EmitterOptions::default()
+        }
+    }
+
+    fn heard(position: Vec3, velocity: Vec3, doppler: bool) -> Spatialized {
+        spatialize(
+            &Listener::default(),
+            position,
+            velocity,
+            &options(doppler),
+            DEFAULT_SPEED_OF_SOUND,
+            1.0f32,
+        )
+    }
+
+    fn camera(position: Vec3) -> Camera3D {
+        let mut camera = Camera3D::default_with_aspect(1.0f32);
+        camera.position = position;
+        camera.target_position = position + vec3(0.0f32, 0.0f32, -1.0f32);
+        camera.up = vec3(0.0f32, 1.0f32, 0.0f32);
+        camera
+    }
+
+    #[test]
+    fn pans_towards_the_emitter() {
+        let still = Vec3::zeros();
+        assert!((heard(vec3(2.0f32, 0.0f32, 0.0f32), still, false).pan - 1.0f32).abs() < EPSILON);
+        assert!((heard(vec3(-2.0f32, 0.0f32, 0.0f32), still, false).pan + 1.0f32).abs() < EPSILON);
+        assert!(heard(vec3(0.0f32, 0.0f32, -2.0f32), still, false).pan.abs() < EPSILON);
+        assert_eq!(heard(Vec3::zeros(), still, false).pan, 0.0f32);
+    }
+
+    #[test]
+    fn attenuates_with_distance() {
+        let attenuation = Attenuation::default();
+        assert_eq!(attenuation.gain(0.5f32), 1.0f32);
+        assert!((attenuation.gain(2.0f32) - 0.5f32).abs() < EPSILON);
+        assert!((attenuation.gain(4.0f32) - 0.25f32).abs() < EPSILON);
+        assert_eq!(attenuation.gain(1000.0f32), attenuation.gain(100.0f32));
+
+        let linear = Attenuation {
+            model: AttenuationModel::Linear,
+            max_distance: 11.0f32,
+            ..Attenuation::default()
+        };
+        assert!((linear.gain(6.0f32) - 0.5f32).abs() < EPSILON);
+        assert_eq!(linear.gain(20.0f32), 0.0f32);
+    }
+
+    #[test]
+    fn doppler_raises_approaching_and_lowers_receding_emitters() {
+        let position = vec3(0.0f32, 0.0f32, -10.0f32);
+        let speed = DEFAULT_SPEED_OF_SOUND * 0.1f32;
+        let approaching = heard(position, vec3(0.0f32, 0.0f32, speed), true).rate;
+        let receding = heard(position, vec3(0.0f32, 0.0f32, -speed), true).rate;
+        assert!((approaching - 1.0f32 / 0.9f32).abs() < EPSILON);
+        assert!((receding - 1.0f32 / 1.1f32).abs() < EPSILON);
+        assert_eq!(
+            heard(position, vec3(0.0f32, 0.0f32, speed), false).rate,
+            1.0f32
+        );
+        // Passing sideways doesn't change the distance, so the pitch stays
+        assert!((heard(position, vec3(speed, 0.0f32, 0.0f32), true).rate - 1.0f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn doppler_rate_is_clamped() {
+        let position = vec3(0.0f32, 0.0f32, -10.0f32);
+        let supersonic = vec3(0.0f32, 0.0f32, DEFAULT_SPEED_OF_SOUND * 2.0f32);
+        assert_eq!(heard(position, supersonic, true).rate, MAX_DOPPLER_RATE);
+        assert_eq!(
+            heard(position, -supersonic * 10.0f32, true).rate,
+            MIN_DOPPLER_RATE
+        );
+    }
+
+    #[test]
+    fn first_follow_camera_has_no_velocity() {
+        let mut audio = SpatialAudio::new(Audio::offline(4, 2));
+        audio.follow_camera(&camera(vec3(0.0f32, 2.0f32, 40.0f32)), 1.0f32 / 60.0f32);
+        assert_eq!(audio.listener().position, vec3(0.0f32, 2.0f32, 40.0f32));
+        assert_eq!(audio.listener().velocity, Vec3::zeros());
+
+        audio.follow_camera(&camera(vec3(0.0f32, 2.0f32, 39.0f32)), 0.5f32);
+        assert_eq!(audio.listener().velocity, vec3(0.0f32, 0.0f32, -2.0f32));
+    }
+}
@@ -0,0 +1,370 @@
+use crate::audio::sound::AudioError;
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+/// Most frames a `MusicDecoder` reads from its source at once.
+const MUSIC_DECODE_FRAMES: usize = 4096;
+
+/// Audio decoded a little at a time instead of all at once, for long tracks such as music.
+pub trait AudioSource: Send {
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u16;
+
+    /// Appends up to `frames` interleaved frames to `output` and returns how many were appended.
+    /// Returns 0 once the end is reached.
+    fn read(&mut self, output: &mut Vec<f32>, frames: usize) -> Result<usize, AudioError>;
+
+    fn rewind(&mut self) -> Result<(), AudioError>;
+}
+
+/// Opens a WAV or Ogg Vorbis stream, detected from the header of `bytes`.
+pub fn open_source(bytes: Arc<[u8]>) -> Result<Box<dyn AudioSource>, AudioError> {
+    if bytes.starts_with(b"RIFF") {
+        Ok(Box::new(WavSource::new(bytes)?))
+    } else if bytes.starts_with(b"OggS") {
+        Ok(Box::new(VorbisSource::new(bytes)?))
+    } else {
+        Err(AudioError::UnsupportedFormat)
+    }
+}
+
+/// Frames decoded by a `MusicDecoder` and waiting to be mixed.
+#[derive(Default)]
+struct MusicBuffer {
+    frames: VecDeque<(f32, f32)>,
+    ended: bool,
+}
+
+/// The mixer's end of a music track: stereo frames decoded ahead by a `MusicDecoder`.
+pub struct MusicStream {
+    sample_rate: u32,
+    buffer: Arc<Mutex<MusicBuffer>>,
+}
+
+impl MusicStream {
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Moves up to `frames` decoded frames to `output`.
+    /// Returns `true` once the track has ended and every frame has been taken.
+    pub fn take(&self, output: &mut VecDeque<(f32, f32)>, frames: usize) -> bool {
+        let mut buffer = self.buffer.lock().unwrap();
+        let count = frames.min(buffer.frames.len());
+        output.extend(buffer.frames.drain(..count));
+        buffer.ended && buffer.frames.is_empty()
+    }
+}
+
+/// Decodes a music source ahead of playback so the audio callback only copies samples.
+///
+/// `Audio` keeps its decoders on the game thread and tops them up once per frame.
+pub struct MusicDecoder {
+    source: Box<dyn AudioSource>,
+    looping: bool,
+    decoded: Vec<f32>,
+    buffer: Arc<Mutex<MusicBuffer>>,
+}
+
+impl MusicDecoder {
+    pub fn new(source: Box<dyn AudioSource>, looping: bool) -> (MusicDecoder, MusicStream) {
+        let buffer = Arc::new(Mutex::new(MusicBuffer::default()));
+        let stream = MusicStream {
+            sample_rate: source.sample_rate(),
+            buffer: Arc::clone(&buffer),
+        };
+        let decoder = MusicDecoder {
+            source,
+            looping,
+            decoded: vec![],
+            buffer,
+        };
+        (decoder, stream)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    /// Decodes until `frames` frames are waiting in the stream or the source ends.
+    ///
+    /// Returns `false` once there is nothing left to decode, either because the source ended
+    /// or because the mixer dropped the stream.
+    pub fn decode_ahead(&mut self, frames: usize) -> bool {
+        if Arc::strong_count(&self.buffer) == 1 {
+            return false;
+        }
+        let mut needed = {
+            let buffer = self.buffer.lock().unwrap();
+            if buffer.ended {
+                return false;
+            }
+            frames.saturating_sub(buffer.frames.len())
+        };
+
+        // Decoded without holding the lock, so the mixer never waits for the decoder
+        let mut frames = vec![];
+        let mut rewound = false;
+        let mut ended = false;
+        while needed > 0 {
+            self.decoded.clear();
+            let count = match self
+                .source
+                .read(&mut self.decoded, needed.min(MUSIC_DECODE_FRAMES))
+            {
+                Ok(count) => count,
+                Err(e) => {
+                    log::error!("Stopped music: {}", e);
+                    0
+                }
+            };
+            if count == 0 {
+                // A source that is still empty right after a rewind has nothing to loop
+                if self.looping && !rewound && self.source.rewind().is_ok() {
+                    rewound = true;
+                    continue;
+                }
+                ended = true;
+                break;
+            }
+            rewound = false;
+            needed = needed.saturating_sub(count);
+            let channels = self.source.channels().max(1) as usize;
+            frames.extend(self.decoded.chunks(channels).map(|frame| {
+                let left = frame[0];
+                let right = if channels > 1 { frame[1] } else { left };
+                (left, right)
+            }));
+        }
+
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.frames.extend(frames);
+        buffer.ended = ended;
+        !ended
+    }
+}
+
+pub struct WavSource {
+    reader: hound::WavReader<Cursor<Arc<[u8]>>>,
+    spec: hound::WavSpec,
+}
+
+impl WavSource {
+    pub fn new(bytes: Arc<[u8]>) -> Result<Self, AudioError> {
+        let reader = hound::WavReader::new(Cursor::new(bytes)).map_err(AudioError::Wav)?;
+        let spec = reader.spec();
+        Ok(WavSource { reader, spec })
+    }
+}
+
+impl AudioSource for WavSource {
+    fn sample_rate(&self) -> u32 {
+        self.spec.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.spec.channels
+    }
+
+    fn read(&mut self, output: &mut Vec<f32>, frames: usize) -> Result<usize, AudioError> {
+        let count = frames * self.spec.channels as usize;
+        let before = output.len();
+        match self.spec.sample_format {
+            hound::SampleFormat::Float => {
+                for sample in self.reader.samples::<f32>().take(count) {
+                    output.push(sample.map_err(AudioError::Wav)?);
+                }
+            }
+            hound::SampleFormat::Int => {
+                let scale = 1.0f32 / (1u32 << (self.spec.bits_per_sample - 1)) as f32;
+                for sample in self.reader.samples::<i32>().take(count) {
+                    output.push(sample.map_err(AudioError::Wav)? as f32 * scale);
+                }
+            }
+        }
+        Ok((output.len() - before) / self.spec.channels as usize)
+    }
+
+    fn rewind(&mut self) -> Result<(), AudioError> {
+        self.reader
+            .seek(0)
+            .map_err(|e| AudioError::Wav(hound::Error::IoError(e)))
+    }
+}
+
+pub struct VorbisSource {
+    reader: lewton::inside_ogg::OggStreamReader<Cursor<Arc<[u8]>>>,
+    // Samples of the last packet that didn't fit into the previous `read`
+    pending: Vec<f32>,
+}
+
+impl VorbisSource {
+    pub fn new(bytes: Arc<[u8]>) -> Result<Self, AudioError> {
+        let reader = lewton::inside_ogg::OggStreamReader::new(Cursor::new(bytes))
+            .map_err(AudioError::Vorbis)?;
+        Ok(VorbisSource {
+            reader,
+            pending: vec![],
+        })
+    }
+}
+
+impl AudioSource for VorbisSource {
+    fn sample_rate(&self) -> u32 {
+        self.reader.ident_hdr.audio_sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.reader.ident_hdr.audio_channels as u16
+    }
+
+    fn read(&mut self, output: &mut Vec<f32>, frames: usize) -> Result<usize, AudioError> {
+        let channels = self.channels() as usize;
+        let count = frames * channels;
+        while self.pending.len() < count {
+            match self
+                .reader
+                .read_dec_packet_itl()
+                .map_err(AudioError::Vorbis)?
+            {
+                Some(packet) => self
+                    .pending
+                    .extend(packet.iter().map(|sample| *sample as f32 / 32768.0f32)),
+                None => break,
+            }
+        }
+        let count = count.min(self.pending.len());
+        output.extend(self.pending.drain(..count));
+        Ok(count / channels)
+    }
+
+    fn rewind(&mut self) -> Result<(), AudioError> {
+        self.pending.clear();
+        self.reader.seek_absgp_pg(0).map_err(AudioError::Vorbis)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Plays back `samples`, optionally failing every read.
+    pub(crate) struct TestSource {
+        pub sample_rate: u32,
+        pub channels: u16,
+        pub samples: Vec<f32>,
+        pub position: usize,
+        pub fail: bool,
+    }
+
+    impl TestSource {
+        pub(crate) fn mono(samples: Vec<f32>) -> Self {
+            TestSource {
+                sample_rate: 4,
+                channels: 1,
+                samples,
+                position: 0,
+                fail: false,
+            }
+        }
+    }
+
+    impl AudioSource for TestSource {
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn channels(&self) -> u16 {
+            self.channels
+        }
+
+        fn read(&mut self, output: &mut Vec<f32>, frames: usize) -> Result<usize, AudioError> {
+            if self.fail {
+                return Err(AudioError::UnsupportedFormat);
+            }
+            let channels = self.channels as usize;
+            let end = (self.position + frames * channels).min(self.samples.len());
+            output.extend_from_slice(&self.samples[self.position..end]);
+            let count = (end - self.position) / channels;
+            self.position = end;
+            Ok(count)
+        }
+
+        fn rewind(&mut self) -> Result<(), AudioError> {
+            self.position = 0;
+            Ok(())
+        }
+    }
+
+    fn taken(stream: &MusicStream, frames: usize) -> (Vec<(f32, f32)>, bool) {
+        let mut output = VecDeque::new();
+        let ended = stream.take(&mut output, frames);
+        (output.into_iter().collect(), ended)
+    }
+
+    #[test]
+    fn decodes_ahead_and_duplicates_mono() {
+        let source = TestSource::mono(vec![0.1f32, 0.2f32, 0.3f32]);
+        let (mut decoder, stream) = MusicDecoder::new(Box::new(source), false);
+        assert!(decoder.decode_ahead(2));
+        assert_eq!(
+            taken(&stream, 8),
+            (vec![(0.1f32, 0.1f32), (0.2f32, 0.2f32)], false)
+        );
+
+        assert!(!decoder.decode_ahead(2));
+        assert_eq!(taken(&stream, 8), (vec![(0.3f32, 0.3f32)], true));
+    }
+
+    #[test]
+    fn keeps_stereo_channels_apart() {
+        let source = TestSource {
+            channels: 2,
+            ..TestSource::mono(vec![0.1f32, -0.1f32, 0.2f32, -0.2f32])
+        };
+        let (mut decoder, stream) = MusicDecoder::new(Box::new(source), false);
+        decoder.decode_ahead(4);
+        assert_eq!(
+            taken(&stream, 4).0,
+            vec![(0.1f32, -0.1f32), (0.2f32, -0.2f32)]
+        );
+    }
+
+    #[test]
+    fn looping_wraps_around() {
+        let source = TestSource::mono(vec![0.1f32, 0.2f32]);
+        let (mut decoder, stream) = MusicDecoder::new(Box::new(source), true);
+        assert!(decoder.decode_ahead(5));
+        let frames = taken(&stream, 8).0;
+        let left = frames.iter().map(|(left, _)| *left).collect::<Vec<_>>();
+        assert_eq!(left, vec![0.1f32, 0.2f32, 0.1f32, 0.2f32, 0.1f32]);
+    }
+
+    #[test]
+    fn looping_an_empty_source_ends() {
+        let (mut decoder, stream) = MusicDecoder::new(Box::new(TestSource::mono(vec![])), true);
+        assert!(!decoder.decode_ahead(4));
+        assert_eq!(taken(&stream, 4), (vec![], true));
+    }
+
+    #[test]
+    fn read_errors_end_the_track() {
+        let source = TestSource {
+            fail: true,
+            ..TestSource::mono(vec![0.1f32])
+        };
+        let (mut decoder, stream) = MusicDecoder::new(Box::new(source), true);
+        assert!(!decoder.decode_ahead(4));
+        assert!(taken(&stream, 4).1);
+    }
+
+    #[test]
+    fn stops_once_the_stream_is_dropped() {
+        let source = TestSource::mono(vec![0.1f32; 16]);
+        let (mut decoder, stream) = MusicDecoder::new(Box::new(source), true);
+        drop(stream);
+        assert!(!decoder.decode_ahead(4));
+    }
+}
@@ -0,0 +1,415 @@
+use nalgebra_glm::{vec3, Vec3};
+use std::collections::HashMap;
+use tearchan_gfx::camera::Camera3D;
+use winit::dpi::PhysicalPosition;
+use winit::event::{
+    ElementState, KeyboardInput, MouseButton, MouseScrollDelta, Touch, TouchPhase, VirtualKeyCode,
+    WindowEvent,
+};
+
+const MODE_TOGGLE_KEY: VirtualKeyCode = VirtualKeyCode::F;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    /// Rotates around `target`. Drag to orbit, right-drag or two fingers to pan, wheel or pinch to zoom.
+    Orbit,
+    /// Moves freely. Drag to look around, WASD to move, Q/E to go down/up.
+    Fly,
+}
+
+#[derive(Clone, Debug)]
+pub struct CameraControllerConfig {
+    /// Radians per pixel of drag.
+    pub rotate_sensitivity: f32,
+    /// World units per pixel of pan, scaled by the orbit distance.
+    pub pan_sensitivity: f32,
+    /// Distance ratio per wheel line or pinch pixel.
+    pub zoom_sensitivity: f32,
+    /// World units per second in fly mode.
+    pub fly_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    /// Seconds to close ~63% of the gap to the requested pose. 0 disables smoothing.
+    pub smoothing: f32,
+    /// Radians per second the orbit yaw advances while nobody is dragging.
+    pub auto_orbit_speed: f32,
+}
+
+impl Default for CameraControllerConfig {
+    fn default() -> Self {
+        CameraControllerConfig {
+            rotate_sensitivity: 0.005f32,
+            pan_sensitivity: 0.002f32,
+            zoom_sensitivity: 0.1f32,
+            fly_speed: 4.0f32,
+            min_distance: 0.5f32,
+            max_distance: 50.0f32,
+            min_pitch: -1.5f32,
+            max_pitch: 1.5f32,
+            smoothing: 0.08f32,
+            auto_orbit_speed: 0.0f32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pose {
+    target: Vec3,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+}
+
+impl Pose {
+    fn direction(&self) -> Vec3 {
+        vec3(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        )
+    }
+
+    fn eye(&self) -> Vec3 {
+        self.target + self.direction() * self.distance
+    }
+
+    fn lerp(&self, other: &Pose, t: f32) -> Pose {
+        Pose {
+            target: self.target + (other.target - self.target) * t,
+            yaw: self.yaw + (other.yaw - self.yaw) * t,
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+            distance: self.distance + (other.distance - self.distance) * t,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FlyKeys {
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+/// Turns mouse, keyboard and touch events into camera movement.
+///
+/// Feed every event from `Scene::update` to `handle_event`, then call `update` once per frame.
+pub struct CameraController {
+    pub config: CameraControllerConfig,
+    mode: CameraMode,
+    initial: Pose,
+    desired: Pose,
+    current: Pose,
+    cursor: Option<PhysicalPosition<f64>>,
+    rotating: bool,
+    panning: bool,
+    keys: FlyKeys,
+    touches: HashMap<u64, PhysicalPosition<f64>>,
+}
+
+impl CameraController {
+    /// Orbits `target` from `position`, clamped to the configured distance and pitch limits.
+    pub fn new(config: CameraControllerConfig, position: Vec3, target: Vec3) -> Self {
+        let offset = position - target;
+        let length = offset.norm();
+        let pitch = if length > 0.0f32 {
+            (offset.y / length).asin()
+        } else {
+            0.0f32
+        };
+        let pose = Pose {
+            target,
+            yaw: offset.x.atan2(offset.z),
+            pitch: pitch.max(config.min_pitch).min(config.max_pitch),
+            distance: length.max(config.min_distance).min(config.max_distance),
+        };
+        CameraController {
+            config,
+            mode: CameraMode::Orbit,
+            initial: pose,
+            desired: pose,
+            current: pose,
+            cursor: None,
+            rotating: false,
+            panning: false,
+            keys: FlyKeys::default(),
+            touches: HashMap::new(),
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    /// Returns to the pose the controller was created with.
+    pub fn reset(&mut self) {
+        self.desired = self.initial;
+        self.mode = CameraMode::Orbit;
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.desired.distance
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.desired.distance = distance
+            .max(self.config.min_distance)
+            .min(self.config.max_distance);
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Left => self.rotating = pressed,
+                    MouseButton::Right | MouseButton::Middle => self.panning = pressed,
+                    _ => return false,
+                }
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(last) = self.cursor.replace(*position) {
+                    let dx = (position.x - last.x) as f32;
+                    let dy = (position.y - last.y) as f32;
+                    if self.rotating {
+                        self.rotate(dx, dy);
+                    } else if self.panning {
+                        self.pan(dx, dy);
+                    }
+                }
+                self.rotating || self.panning
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0f32,
+                };
+                self.zoom(lines);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.handle_key(*keycode, *state == ElementState::Pressed),
+            WindowEvent::Touch(touch) => {
+                self.handle_touch(touch);
+                true
+            }
+            WindowEvent::Focused(false) => {
+                self.keys = FlyKeys::default();
+                self.rotating = false;
+                self.panning = false;
+                self.touches.clear();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self, delta: f32, camera: &mut Camera3D) {
+        match self.mode {
+            CameraMode::Orbit => {
+                if !self.is_interacting() {
+                    self.desired.yaw += self.config.auto_orbit_speed * delta;
+                }
+            }
+            CameraMode::Fly => self.fly(delta),
+        }
+
+        self.current = if self.config.smoothing > 0.0f32 {
+            let t = 1.0f32 - (-delta / self.config.smoothing).exp();
+            self.current.lerp(&self.desired, t)
+        } else {
+            self.desired
+        };
+
+        camera.position = self.current.eye();
+        camera.target_position = self.current.target;
+        camera.up = vec3(0.0f32, 1.0f32, 0.0f32);
+        camera.update();
+    }
+
+    fn is_interacting(&self) -> bool {
+        self.rotating || self.panning || !self.touches.is_empty()
+    }
+
+    fn handle_key(&mut self, keycode: VirtualKeyCode, pressed: bool) -> bool {
+        let key = match keycode {
+            VirtualKeyCode::W => &mut self.keys.forward,
+            VirtualKeyCode::S => &mut self.keys.backward,
+            VirtualKeyCode::A => &mut self.keys.left,
+            VirtualKeyCode::D => &mut self.keys.right,
+            VirtualKeyCode::E => &mut self.keys.up,
+            VirtualKeyCode::Q => &mut self.keys.down,
+            MODE_TOGGLE_KEY => {
+                if pressed {
+                    self.mode = match self.mode {
+                        CameraMode::Orbit => CameraMode::Fly,
+                        CameraMode::Fly => CameraMode::Orbit,
+                    };
+                }
+                return true;
+            }
+            _ => return false,
+        };
+        *key = pressed;
+        true
+    }
+
+    fn handle_touch(&mut self, touch: &Touch) {
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, touch.location);
+            }
+            TouchPhase::Moved => {
+                let previous = match self.touches.get(&touch.id) {
+                    Some(previous) => *previous,
+                    None => return,
+                };
+                match self.touches.len() {
+                    1 => self.rotate(
+                        (touch.location.x - previous.x) as f32,
+                        (touch.location.y - previous.y) as f32,
+                    ),
+                    2 => {
+                        let other = self
+                            .touches
+                            .iter()
+                            .find(|(id, _)| **id != touch.id)
+                            .map(|(_, location)| *location)
+                            .unwrap();
+                        // Half of the finger movement moves the midpoint between both fingers
+                        self.pan(
+                            (touch.location.x - previous.x) as f32 * 0.5f32,
+                            (touch.location.y - previous.y) as f32 * 0.5f32,
+                        );
+                        let before = distance(&previous, &other);
+                        let after = distance(&touch.location, &other);
+                        self.zoom((after - before) * 0.05f32);
+                    }
+                    _ => {}
+                }
+                self.touches.insert(touch.id, touch.location);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+            }
+        }
+    }
+
+    fn rotate(&mut self, dx: f32, dy: f32) {
+        let eye = self.desired.eye();
+        let sensitivity = self.config.rotate_sensitivity;
+        self.desired.yaw -= dx * sensitivity;
+        self.desired.pitch = (self.desired.pitch + dy * sensitivity)
+            .max(self.config.min_pitch)
+            .min(self.config.max_pitch);
+        if self.mode == CameraMode::Fly {
+            // Looking around keeps the eye in place and swings the target instead
+            self.desired.target = eye - self.desired.direction() * self.desired.distance;
+        }
+    }
+
+    fn pan(&mut self, dx: f32, dy: f32) {
+        let (right, up) = self.basis();
+        let scale = self.config.pan_sensitivity * self.desired.distance;
+        self.desired.target += (-right * dx + up * dy) * scale;
+    }
+
+    fn zoom(&mut self, amount: f32) {
+        let ratio = (1.0f32 - amount * self.config.zoom_sensitivity).max(0.1f32);
+        self.set_distance(self.desired.distance * ratio);
+    }
+
+    fn fly(&mut self, delta: f32) {
+        let forward = -self.desired.direction();
+        let (right, _) = self.basis();
+        let up = vec3(0.0f32, 1.0f32, 0.0f32);
+        let mut movement = vec3(0.0f32, 0.0f32, 0.0f32);
+        let keys = &self.keys;
+        for (pressed, direction) in [
+            (keys.forward, forward),
+            (keys.backward, -forward),
+            (keys.right, right),
+            (keys.left, -right),
+            (keys.up, up),
+            (keys.down, -up),
+        ]
+        .iter()
+        {
+            if *pressed {
+                movement += direction;
+            }
+        }
+        if movement.norm_squared() > 0.0f32 {
+            self.desired.target += movement.normalize() * self.config.fly_speed * delta;
+        }
+    }
+
+    fn basis(&self) -> (Vec3, Vec3) {
+        let forward = -self.desired.direction();
+        let right = forward.cross(&vec3(0.0f32, 1.0f32, 0.0f32)).normalize();
+        let up = right.cross(&forward);
+        (right, up)
+    }
+}
+
+fn distance(a: &PhysicalPosition<f64>, b: &PhysicalPosition<f64>) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(position: Vec3) -> CameraController {
+        CameraController::new(
+            CameraControllerConfig::default(),
+            position,
+            vec3(0.0f32, 0.0f32, 0.0f32),
+        )
+    }
+
+    #[test]
+    fn new_clamps_the_initial_distance() {
+        assert_eq!(controller(vec3(0.0f32, 0.0f32, 0.1f32)).distance(), 0.5f32);
+        assert_eq!(
+            controller(vec3(0.0f32, 0.0f32, 80.0f32)).distance(),
+            50.0f32
+        );
+        assert_eq!(controller(vec3(0.0f32, 0.0f32, 4.0f32)).distance(), 4.0f32);
+    }
+
+    #[test]
+    fn new_keeps_the_direction_when_clamping() {
+        let controller = controller(vec3(0.0f32, 60.0f32, 80.0f32));
+        assert!((controller.desired.pitch - 0.6f32.asin()).abs() < 1e-6f32);
+    }
+
+    #[test]
+    fn set_distance_clamps() {
+        let mut controller = controller(vec3(0.0f32, 0.0f32, 4.0f32));
+        controller.set_distance(1000.0f32);
+        assert_eq!(controller.distance(), 50.0f32);
+        controller.set_distance(0.0f32);
+        assert_eq!(controller.distance(), 0.5f32);
+    }
+}
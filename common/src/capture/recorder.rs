@@ -0,0 +1,218 @@
+use crate::capture::save_png;
+use crate::headless::{HeadlessRunner, HeadlessSceneFactory};
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+pub struct RecordConfig {
+    pub frames: usize,
+    pub fps: u32,
+    /// Directory for `frame_00000.png`, `frame_00001.png`, ...
+    pub directory: Option<PathBuf>,
+    /// External encoder command that receives raw RGBA8 frames on stdin.
+    /// `{width}`, `{height}` and `{fps}` are replaced before the process is spawned,
+    /// e.g. `ffmpeg -y -f rawvideo -pix_fmt rgba -s {width}x{height} -r {fps} -i - demo.mp4`.
+    /// Arguments containing spaces can be quoted with `"` or `'`, and `\` escapes a character
+    /// like in a POSIX shell.
+    pub encoder: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    NoOutput,
+    InvalidFps,
+    EmptyEncoderCommand,
+    UnterminatedQuote,
+    Image(image::ImageError),
+    Encoder(std::io::Error),
+    EncoderExit(std::process::ExitStatus),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::NoOutput => {
+                write!(f, "either an output directory or an encoder is required")
+            }
+            RecordError::InvalidFps => write!(f, "the frame rate must be at least 1"),
+            RecordError::EmptyEncoderCommand => write!(f, "the encoder command is empty"),
+            RecordError::UnterminatedQuote => {
+                write!(f, "the encoder command has an unterminated quote or escape")
+            }
+            RecordError::Image(e) => write!(f, "failed to write a frame: {}", e),
+            RecordError::Encoder(e) => write!(f, "failed to pipe a frame to the encoder: {}", e),
+            RecordError::EncoderExit(status) => write!(f, "the encoder exited with {}", status),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+/// Renders `config.frames` frames at a fixed time step of `1 / config.fps` seconds.
+///
+/// Wall-clock time is never consulted, so the same scene always produces the same frames.
+pub fn record(
+    runner: &HeadlessRunner,
+    factory: HeadlessSceneFactory,
+    config: &RecordConfig,
+) -> Result<(), RecordError> {
+    if config.directory.is_none() && config.encoder.is_none() {
+        return Err(RecordError::NoOutput);
+    }
+    if config.fps == 0 {
+        return Err(RecordError::InvalidFps);
+    }
+
+    let context = runner.context();
+    let mut encoder = match &config.encoder {
+        Some(command) => Some(spawn_encoder(
+            command,
+            context.width,
+            context.height,
+            config.fps,
+        )?),
+        None => None,
+    };
+
+    if let Err(e) = record_frames(runner, factory, config, encoder.as_mut()) {
+        if let Some(mut encoder) = encoder {
+            // Don't leave the encoder waiting for frames that won't come
+            let _ = encoder.kill();
+            let _ = encoder.wait();
+        }
+        return Err(e);
+    }
+
+    if let Some(mut encoder) = encoder {
+        // Closing stdin tells the encoder that the stream has ended
+        drop(encoder.stdin.take());
+        let status = encoder.wait().map_err(RecordError::Encoder)?;
+        if !status.success() {
+            return Err(RecordError::EncoderExit(status));
+        }
+    }
+    Ok(())
+}
+
+fn record_frames(
+    runner: &HeadlessRunner,
+    factory: HeadlessSceneFactory,
+    config: &RecordConfig,
+    mut encoder: Option<&mut Child>,
+) -> Result<(), RecordError> {
+    let delta = 1.0f32 / config.fps as f32;
+    let mut scene = runner.create_scene(factory);
+    for index in 0..config.frames {
+        let image = runner.render_frame(scene.as_mut(), delta);
+
+        if let Some(directory) = &config.directory {
+            let mut path = directory.clone();
+            path.push(format!("frame_{:05}.png", index));
+            save_png(&image, &path).map_err(RecordError::Image)?;
+        }
+        if let Some(encoder) = encoder.as_mut() {
+            let stdin = encoder.stdin.as_mut().unwrap();
+            stdin.write_all(&image).map_err(RecordError::Encoder)?;
+        }
+        log::info!("Recorded frame {}/{}", index + 1, config.frames);
+    }
+    Ok(())
+}
+
+fn spawn_encoder(command: &str, width: u32, height: u32, fps: u32) -> Result<Child, RecordError> {
+    // Placeholders are replaced after splitting, so their values can't add arguments
+    let args = split_command(command)?
+        .into_iter()
+        .map(|arg| {
+            arg.replace("{width}", &width.to_string())
+                .replace("{height}", &height.to_string())
+                .replace("{fps}", &fps.to_string())
+        })
+        .collect::<Vec<_>>();
+    let (program, args) = args.split_first().ok_or(RecordError::EmptyEncoderCommand)?;
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(RecordError::Encoder)
+}
+
+/// Characters a backslash escapes inside double quotes; before anything else it's kept.
+const ESCAPED_IN_DOUBLE_QUOTES: &str = "$`\"\\";
+
+/// Splits a command line at whitespace, keeping quoted parts together like a POSIX shell.
+pub fn split_command(command: &str) -> Result<Vec<String>, RecordError> {
+    let mut args = vec![];
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => {
+                let current = current.get_or_insert_with(String::new);
+                match chars.peek() {
+                    Some(next) if ESCAPED_IN_DOUBLE_QUOTES.contains(*next) => {
+                        current.push(*next);
+                        chars.next();
+                    }
+                    _ => current.push('\\'),
+                }
+            }
+            (None, '\\') => {
+                let escaped = chars.next().ok_or(RecordError::UnterminatedQuote)?;
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err(RecordError::UnterminatedQuote);
+    }
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_command_keeps_quoted_arguments_together() {
+        let args = split_command(r#"ffmpeg -i - "my videos/out.mp4" 'a b' c\ d"#).unwrap();
+        assert_eq!(
+            args,
+            vec!["ffmpeg", "-i", "-", "my videos/out.mp4", "a b", "c d"]
+        );
+        assert_eq!(split_command(r#"x "" y"#).unwrap(), vec!["x", "", "y"]);
+        assert!(split_command("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_command_only_escapes_some_characters_in_double_quotes() {
+        assert_eq!(
+            split_command(r#""a\"b" "c\\d" "\$x" "\n\t" 'e\f' g\h"#).unwrap(),
+            vec![r#"a"b"#, r"c\d", "$x", r"\n\t", r"e\f", "gh"]
+        );
+        // A Windows path keeps its separators
+        assert_eq!(
+            split_command(r#"encoder "C:\Videos\out.mp4""#).unwrap(),
+            vec!["encoder", r"C:\Videos\out.mp4"]
+        );
+    }
+
+    #[test]
+    fn split_command_rejects_unterminated_quotes() {
+        assert!(matches!(
+            split_command(r#"ffmpeg "out.mp4"#),
+            Err(RecordError::UnterminatedQuote)
+        ));
+    }
+}
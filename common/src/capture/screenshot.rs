@@ -0,0 +1,136 @@
+use crate::capture::{save_png, OffscreenTarget};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+pub const SCREENSHOT_KEY: VirtualKeyCode = VirtualKeyCode::F12;
+pub const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
+/// Screenshots are written to the local file system, which only the desktop build has.
+pub const IS_SCREENSHOT_SUPPORTED: bool = cfg!(not(any(
+    target_arch = "wasm32",
+    target_os = "android",
+    target_os = "ios"
+)));
+
+/// Saves the next rendered frame as a PNG when `SCREENSHOT_KEY` is pressed.
+///
+/// The swapchain texture can't be read back, so the scene draws the frame a second time,
+/// overlays included, into an offscreen texture of the same size and format:
+///
+/// ```ignore
+/// if let Some(target) = screenshot.begin(device, width, height, format) {
+///     draw_frame(target.view());
+///     screenshot.save(device, queue, target)?;
+/// }
+/// ```
+pub struct ScreenshotCapture {
+    directory: PathBuf,
+    requested: bool,
+}
+
+impl ScreenshotCapture {
+    pub fn new<P: AsRef<Path>>(directory: P) -> Self {
+        ScreenshotCapture {
+            directory: directory.as_ref().to_path_buf(),
+            requested: false,
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        if !IS_SCREENSHOT_SUPPORTED {
+            return false;
+        }
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(SCREENSHOT_KEY),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.requested = true;
+            return true;
+        }
+        false
+    }
+
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+
+    /// Starts a requested screenshot: returns the target to draw the whole frame into, which
+    /// then goes to `save`.
+    pub fn begin(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Option<OffscreenTarget> {
+        if !self.requested {
+            return None;
+        }
+        self.requested = false;
+        Some(OffscreenTarget::new(device, width, height, format))
+    }
+
+    /// Reads back a frame drawn into `target` by `begin` and writes it as a timestamped PNG.
+    pub fn save(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target: OffscreenTarget,
+    ) -> image::ImageResult<PathBuf> {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        target.copy_to_buffer(&mut encoder);
+        queue.submit(Some(encoder.finish()));
+
+        let image = target.read_image(device);
+        let mut path = self.directory.clone();
+        path.push(format!("screenshot_{}.png", timestamp()));
+        save_png(&image, &path)?;
+        Ok(path)
+    }
+}
+
+/// Formats the current UTC time as `YYYYMMDD_HHMMSS_mmm`.
+fn timestamp() -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = elapsed.as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+    let secs_of_day = secs.rem_euclid(86400);
+    format!(
+        "{:04}{:02}{:02}_{:02}{:02}{:02}_{:03}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
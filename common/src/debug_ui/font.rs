@@ -0,0 +1,112 @@
+/// Width of every glyph in texels, not counting spacing.
+pub const GLYPH_WIDTH: u32 = 5;
+/// Height of every glyph in texels, including one row for descenders.
+pub const GLYPH_HEIGHT: u32 = 8;
+/// First character in `GLYPHS`; the table covers printable ASCII.
+pub const FIRST_CHAR: char = ' ';
+
+/// Printable ASCII as 5x8 bitmaps, one string per row.
+#[rustfmt::skip]
+pub const GLYPHS: [[&str; 8]; 95] = [
+    [".....", ".....", ".....", ".....", ".....", ".....", ".....", "....."], // space
+    ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#..", "....."], // !
+    [".#.#.", ".#.#.", ".#.#.", ".....", ".....", ".....", ".....", "....."], // "
+    [".#.#.", ".#.#.", "#####", ".#.#.", "#####", ".#.#.", ".#.#.", "....."], // #
+    ["..#..", ".####", "#.#..", ".###.", "..#.#", "####.", "..#..", "....."], // $
+    ["##...", "##..#", "...#.", "..#..", ".#...", "#..##", "...##", "....."], // %
+    [".##..", "#..#.", "#.#..", ".#...", "#.#.#", "#..#.", ".##.#", "....."], // &
+    ["..#..", "..#..", "..#..", ".....", ".....", ".....", ".....", "....."], // '
+    ["...#.", "..#..", ".#...", ".#...", ".#...", "..#..", "...#.", "....."], // (
+    [".#...", "..#..", "...#.", "...#.", "...#.", "..#..", ".#...", "....."], // )
+    [".....", "..#..", "#.#.#", ".###.", "#.#.#", "..#..", ".....", "....."], // *
+    [".....", "..#..", "..#..", "#####", "..#..", "..#..", ".....", "....."], // +
+    [".....", ".....", ".....", ".....", ".....", "..#..", "..#..", ".#..."], // ,
+    [".....", ".....", ".....", "#####", ".....", ".....", ".....", "....."], // -
+    [".....", ".....", ".....", ".....", ".....", ".##..", ".##..", "....."], // .
+    [".....", "....#", "...#.", "..#..", ".#...", "#....", ".....", "....."], // /
+    [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###.", "....."], // 0
+    ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###.", "....."], // 1
+    [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####", "....."], // 2
+    ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###.", "....."], // 3
+    ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#.", "....."], // 4
+    ["#####", "#....", "####.", "....#", "....#", "#...#", ".###.", "....."], // 5
+    ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###.", "....."], // 6
+    ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#...", "....."], // 7
+    [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###.", "....."], // 8
+    [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##..", "....."], // 9
+    [".....", ".##..", ".##..", ".....", ".##..", ".##..", ".....", "....."], // :
+    [".....", ".##..", ".##..", ".....", ".##..", "..#..", ".#...", "....."], // ;
+    ["...#.", "..#..", ".#...", "#....", ".#...", "..#..", "...#.", "....."], // <
+    [".....", ".....", "#####", ".....", "#####", ".....", ".....", "....."], // =
+    [".#...", "..#..", "...#.", "....#", "...#.", "..#..", ".#...", "....."], // >
+    [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#..", "....."], // ?
+    [".###.", "#...#", "....#", ".##.#", "#.#.#", "#.#.#", ".###.", "....."], // @
+    [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#", "....."], // A
+    ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####.", "....."], // B
+    [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###.", "....."], // C
+    ["###..", "#..#.", "#...#", "#...#", "#...#", "#..#.", "###..", "....."], // D
+    ["#####", "#....", "#....", "####.", "#....", "#....", "#####", "....."], // E
+    ["#####", "#....", "#....", "####.", "#....", "#....", "#....", "....."], // F
+    [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####", "....."], // G
+    ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#", "....."], // H
+    [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###.", "....."], // I
+    ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##..", "....."], // J
+    ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#", "....."], // K
+    ["#....", "#....", "#....", "#....", "#....", "#....", "#####", "....."], // L
+    ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#", "....."], // M
+    ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#", "....."], // N
+    [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###.", "....."], // O
+    ["####.", "#...#", "#...#", "####.", "#....", "#....", "#....", "....."], // P
+    [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#", "....."], // Q
+    ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#", "....."], // R
+    [".####", "#....", "#....", ".###.", "....#", "....#", "####.", "....."], // S
+    ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#..", "....."], // T
+    ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###.", "....."], // U
+    ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#..", "....."], // V
+    ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#.", "....."], // W
+    ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#", "....."], // X
+    ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#..", "....."], // Y
+    ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####", "....."], // Z
+    [".###.", ".#...", ".#...", ".#...", ".#...", ".#...", ".###.", "....."], // [
+    [".....", "#....", ".#...", "..#..", "...#.", "....#", ".....", "....."], // \
+    [".###.", "...#.", "...#.", "...#.", "...#.", "...#.", ".###.", "....."], // ]
+    ["..#..", ".#.#.", "#...#", ".....", ".....", ".....", ".....", "....."], // ^
+    [".....", ".....", ".....", ".....", ".....", ".....", ".....", "#####"], // _
+    [".#...", "..#..", ".....", ".....", ".....", ".....", ".....", "....."], // `
+    [".....", ".....", ".###.", "....#", ".####", "#...#", ".####", "....."], // a
+    ["#....", "#....", "#.##.", "##..#", "#...#", "#...#", "####.", "....."], // b
+    [".....", ".....", ".###.", "#....", "#....", "#...#", ".###.", "....."], // c
+    ["....#", "....#", ".##.#", "#..##", "#...#", "#...#", ".####", "....."], // d
+    [".....", ".....", ".###.", "#...#", "#####", "#....", ".###.", "....."], // e
+    ["..##.", ".#..#", ".#...", "###..", ".#...", ".#...", ".#...", "....."], // f
+    [".....", ".....", ".####", "#...#", "#...#", ".####", "....#", ".###."], // g
+    ["#....", "#....", "#.##.", "##..#", "#...#", "#...#", "#...#", "....."], // h
+    ["..#..", ".....", ".##..", "..#..", "..#..", "..#..", ".###.", "....."], // i
+    ["...#.", ".....", "..##.", "...#.", "...#.", "...#.", "#..#.", ".##.."], // j
+    ["#....", "#....", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "....."], // k
+    [".##..", "..#..", "..#..", "..#..", "..#..", "..#..", ".###.", "....."], // l
+    [".....", ".....", "##.#.", "#.#.#", "#.#.#", "#...#", "#...#", "....."], // m
+    [".....", ".....", "#.##.", "##..#", "#...#", "#...#", "#...#", "....."], // n
+    [".....", ".....", ".###.", "#...#", "#...#", "#...#", ".###.", "....."], // o
+    [".....", ".....", "####.", "#...#", "#...#", "####.", "#....", "#...."], // p
+    [".....", ".....", ".####", "#...#", "#...#", ".####", "....#", "....#"], // q
+    [".....", ".....", "#.##.", "##..#", "#....", "#....", "#....", "....."], // r
+    [".....", ".....", ".####", "#....", ".###.", "....#", "####.", "....."], // s
+    [".#...", ".#...", "###..", ".#...", ".#...", ".#..#", "..##.", "....."], // t
+    [".....", ".....", "#...#", "#...#", "#...#", "#..##", ".##.#", "....."], // u
+    [".....", ".....", "#...#", "#...#", "#...#", ".#.#.", "..#..", "....."], // v
+    [".....", ".....", "#...#", "#...#", "#.#.#", "#.#.#", ".#.#.", "....."], // w
+    [".....", ".....", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "....."], // x
+    [".....", ".....", "#...#", "#...#", "#...#", ".####", "....#", ".###."], // y
+    [".....", ".....", "#####", "...#.", "..#..", ".#...", "#####", "....."], // z
+    ["...#.", "..#..", "..#..", ".#...", "..#..", "..#..", "...#.", "....."], // {
+    ["..#..", "..#..", "..#..", "..#..", "..#..", "..#..", "..#..", "....."], // |
+    [".#...", "..#..", "..#..", "...#.", "..#..", "..#..", ".#...", "....."], // }
+    [".....", ".....", ".#...", "#.#.#", "...#.", ".....", ".....", "....."], // ~
+];
+
+/// The bitmap of `c`, or `None` outside printable ASCII.
+pub fn glyph(c: char) -> Option<&'static [&'static str; 8]> {
+    let index = (c as u32).checked_sub(FIRST_CHAR as u32)?;
+    GLYPHS.get(index as usize)
+}
@@ -0,0 +1,618 @@
+pub mod font;
+
+use crate::debug_ui::font::{glyph, FIRST_CHAR, GLYPHS, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::render::quad::{QuadList, QuadRenderer, QuadTexture, Rect};
+use crate::settings::current_settings;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use winit::event::{
+    ElementState, KeyboardInput, MouseButton, Touch, TouchPhase, VirtualKeyCode, WindowEvent,
+};
+
+pub const TOGGLE_KEY: VirtualKeyCode = VirtualKeyCode::F3;
+
+/// Frames kept for the frame time graph.
+const FRAME_HISTORY: usize = 120;
+/// Frame time that fills the graph, in seconds.
+const GRAPH_MAX_FRAME_TIME: f32 = 1.0f32 / 20.0f32;
+const GRAPH_HEIGHT: f32 = 24.0f32;
+
+/// Atlas cells are one texel larger than glyphs so neighbours never bleed into each other.
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 6;
+/// The cell after the last glyph is solid white, for drawing untextured rectangles.
+const WHITE_CELL: u32 = GLYPHS.len() as u32;
+
+/// Characters per panel row.
+const PANEL_COLUMNS: usize = 40;
+/// Panel margin and padding, in font texels.
+const MARGIN: f32 = 4.0f32;
+const PADDING: f32 = 3.0f32;
+/// Columns taken by the label in front of a slider.
+const LABEL_COLUMNS: usize = 15;
+
+const TEXT_COLOR: [f32; 4] = [0.92f32, 0.92f32, 0.92f32, 1.0f32];
+const DIM_TEXT_COLOR: [f32; 4] = [0.6f32, 0.6f32, 0.6f32, 1.0f32];
+const PANEL_COLOR: [f32; 4] = [0.0f32, 0.0f32, 0.0f32, 0.65f32];
+const WIDGET_COLOR: [f32; 4] = [0.22f32, 0.22f32, 0.27f32, 1.0f32];
+const HOVER_COLOR: [f32; 4] = [0.3f32, 0.3f32, 0.37f32, 1.0f32];
+const FILL_COLOR: [f32; 4] = [0.3f32, 0.5f32, 0.8f32, 1.0f32];
+const GOOD_FRAME_COLOR: [f32; 4] = [0.3f32, 0.8f32, 0.3f32, 1.0f32];
+const SLOW_FRAME_COLOR: [f32; 4] = [0.9f32, 0.8f32, 0.2f32, 1.0f32];
+const BAD_FRAME_COLOR: [f32; 4] = [0.9f32, 0.25f32, 0.2f32, 1.0f32];
+
+/// An immediate-mode overlay for tweaking values at runtime, drawn after the scene.
+///
+/// Call `begin_frame`, then any widgets, then `render` once per frame. Widgets return
+/// whether the user changed their value this frame and are identified by their label,
+/// so labels must be unique within a frame. `TOGGLE_KEY` shows and hides the panel;
+/// while hidden, widgets do nothing.
+pub struct DebugUi {
+    renderer: QuadRenderer,
+    atlas: QuadTexture,
+    visible: bool,
+    backend: String,
+    frame_times: VecDeque<f32>,
+    scale_factor: f64,
+    cursor: Option<(f32, f32)>,
+    pointer_down: bool,
+    pointer_clicked: bool,
+    /// Set when a press landed on the panel, so the release is ours too.
+    pointer_captured: bool,
+    touch_id: Option<u64>,
+    active: Option<u64>,
+    panel: Rect,
+    screen_size: (u32, u32),
+    scale: f32,
+    row: f32,
+    quads: QuadList,
+}
+
+impl DebugUi {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let renderer = QuadRenderer::new(device, format);
+        let atlas = renderer.create_texture(
+            device,
+            "Debug UI Font",
+            ATLAS_COLUMNS * CELL_WIDTH,
+            ATLAS_ROWS * CELL_HEIGHT,
+            wgpu::FilterMode::Nearest,
+        );
+        atlas.write(
+            queue,
+            0,
+            0,
+            atlas.width(),
+            atlas.height(),
+            &font_atlas_texels(),
+        );
+
+        DebugUi {
+            renderer,
+            atlas,
+            visible: false,
+            backend: backend_description(),
+            frame_times: VecDeque::with_capacity(FRAME_HISTORY),
+            scale_factor: 1.0f64,
+            cursor: None,
+            pointer_down: false,
+            pointer_clicked: false,
+            pointer_captured: false,
+            touch_id: None,
+            active: None,
+            panel: Rect::default(),
+            screen_size: (1, 1),
+            scale: 1.0f32,
+            row: 0.0f32,
+            quads: QuadList::new(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if !visible {
+            self.release_pointer();
+        }
+    }
+
+    /// Sets the window's scale factor until the next `ScaleFactorChanged`; see
+    /// `initial_scale_factor`.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Returns true if the overlay used the event, in which case the scene should ignore it.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(TOGGLE_KEY),
+                        ..
+                    },
+                ..
+            } => {
+                self.set_visible(!self.visible);
+                true
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor;
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = Some((position.x as f32, position.y as f32));
+                self.pointer_captured
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if !self.pointer_captured {
+                    self.cursor = None;
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => self.press_pointer(),
+                ElementState::Released => self.release_pointer(),
+            },
+            WindowEvent::Touch(Touch {
+                phase,
+                location,
+                id,
+                ..
+            }) => {
+                let is_tracked = self.touch_id == Some(*id);
+                match phase {
+                    TouchPhase::Started if self.touch_id.is_none() => {
+                        self.cursor = Some((location.x as f32, location.y as f32));
+                        let captured = self.press_pointer();
+                        if captured {
+                            self.touch_id = Some(*id);
+                        }
+                        captured
+                    }
+                    TouchPhase::Moved if is_tracked => {
+                        self.cursor = Some((location.x as f32, location.y as f32));
+                        true
+                    }
+                    TouchPhase::Ended | TouchPhase::Cancelled if is_tracked => {
+                        self.release_pointer();
+                        self.cursor = None;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn press_pointer(&mut self) -> bool {
+        let over_panel = match self.cursor {
+            Some((x, y)) => self.visible && self.panel.contains(x, y),
+            None => false,
+        };
+        if over_panel {
+            self.pointer_down = true;
+            self.pointer_clicked = true;
+            self.pointer_captured = true;
+        }
+        over_panel
+    }
+
+    fn release_pointer(&mut self) -> bool {
+        let captured = self.pointer_captured;
+        self.pointer_down = false;
+        self.pointer_captured = false;
+        self.touch_id = None;
+        self.active = None;
+        captured
+    }
+
+    /// Records the frame time and, if visible, starts the panel with the built-in stats.
+    /// `delta` should be the measured wall-clock time, not a replayed or fixed step.
+    pub fn begin_frame(&mut self, delta: f32, width: u32, height: u32) {
+        if self.frame_times.len() == FRAME_HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(delta);
+
+        self.quads.clear();
+        self.screen_size = (width.max(1), height.max(1));
+        self.scale = (2.0f64 * self.scale_factor).round().max(1.0f64) as f32;
+        self.row = (MARGIN + PADDING) * self.scale;
+        if !self.visible {
+            return;
+        }
+
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32;
+        let fps = if average > 0.0f32 {
+            1.0f32 / average
+        } else {
+            0.0f32
+        };
+        let worst = self.frame_times.iter().cloned().fold(0.0f32, f32::max);
+        self.label(&format!(
+            "FPS {:.1}  {:.2} ms  max {:.2} ms",
+            fps,
+            average * 1000.0f32,
+            worst * 1000.0f32
+        ));
+        self.frame_graph();
+        let backend = std::mem::take(&mut self.backend);
+        self.text_row(&backend, DIM_TEXT_COLOR);
+        self.backend = backend;
+        self.separator();
+    }
+
+    pub fn label(&mut self, text: &str) {
+        if self.visible {
+            self.text_row(text, TEXT_COLOR);
+        }
+    }
+
+    /// Returns true when clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let width = (label.chars().count() as f32 + 2.0f32) * self.advance();
+        let rect = Rect::new(self.left(), self.row, width, self.line_height());
+        let hovered = self.is_hovered(&rect);
+        let clicked = hovered && self.pointer_clicked;
+        let color = if hovered { HOVER_COLOR } else { WIDGET_COLOR };
+        self.fill(rect, color);
+        self.text(
+            rect.x + self.advance(),
+            self.text_top(rect.y),
+            label,
+            TEXT_COLOR,
+        );
+        self.next_row();
+        clicked
+    }
+
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let size = self.line_height();
+        let rect = Rect::new(
+            self.left(),
+            self.row,
+            size + (label.chars().count() as f32 + 1.0f32) * self.advance(),
+            size,
+        );
+        let hovered = self.is_hovered(&rect);
+        let changed = hovered && self.pointer_clicked;
+        if changed {
+            *value = !*value;
+        }
+        let color = if hovered { HOVER_COLOR } else { WIDGET_COLOR };
+        self.fill(Rect::new(rect.x, rect.y, size, size), color);
+        if *value {
+            let inset = 2.0f32 * self.scale;
+            let mark = Rect::new(
+                rect.x + inset,
+                rect.y + inset,
+                size - inset * 2.0f32,
+                size - inset * 2.0f32,
+            );
+            self.fill(mark, FILL_COLOR);
+        }
+        self.text(
+            rect.x + size + self.advance(),
+            self.text_top(rect.y),
+            label,
+            TEXT_COLOR,
+        );
+        self.next_row();
+        changed
+    }
+
+    /// Drag anywhere on the bar to set the value.
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        self.slider_with_id(widget_id(label), label, value, range)
+    }
+
+    fn slider_with_id(
+        &mut self,
+        id: u64,
+        label: &str,
+        value: &mut f32,
+        range: RangeInclusive<f32>,
+    ) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let (min, max) = (*range.start(), *range.end());
+        let label_width = LABEL_COLUMNS as f32 * self.advance();
+        let bar = Rect::new(
+            self.left() + label_width,
+            self.row,
+            self.content_width() - label_width,
+            self.line_height(),
+        );
+        let hovered = self.is_hovered(&bar);
+        if hovered && self.pointer_clicked {
+            self.active = Some(id);
+        }
+
+        let mut changed = false;
+        if self.active == Some(id) && self.pointer_down {
+            if let Some((x, _)) = self.cursor {
+                let t = ((x - bar.x) / bar.width).max(0.0f32).min(1.0f32);
+                let new_value = min + (max - min) * t;
+                changed = (new_value - *value).abs() > std::f32::EPSILON;
+                *value = new_value;
+            }
+        }
+
+        let t = if max > min {
+            ((*value - min) / (max - min)).max(0.0f32).min(1.0f32)
+        } else {
+            0.0f32
+        };
+        let color = if hovered || self.active == Some(id) {
+            HOVER_COLOR
+        } else {
+            WIDGET_COLOR
+        };
+        self.fill(bar, color);
+        self.fill(
+            Rect::new(bar.x, bar.y, bar.width * t, bar.height),
+            FILL_COLOR,
+        );
+        let text_top = self.text_top(bar.y);
+        self.text(
+            self.left(),
+            text_top,
+            &truncate(label, LABEL_COLUMNS - 1),
+            TEXT_COLOR,
+        );
+        self.text(
+            bar.x + self.advance() * 0.5f32,
+            text_top,
+            &format!("{:.3}", value),
+            TEXT_COLOR,
+        );
+        self.next_row();
+        changed
+    }
+
+    /// One slider per channel, with a swatch of the current color.
+    pub fn color(&mut self, label: &str, value: &mut [f32; 3]) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let swatch = Rect::new(
+            self.left() + LABEL_COLUMNS as f32 * self.advance(),
+            self.row,
+            self.content_width() - LABEL_COLUMNS as f32 * self.advance(),
+            self.line_height(),
+        );
+        self.fill(swatch, [value[0], value[1], value[2], 1.0f32]);
+        self.text(self.left(), self.text_top(self.row), label, TEXT_COLOR);
+        self.next_row();
+
+        let mut changed = false;
+        for (channel, name) in value.iter_mut().zip(&["r", "g", "b"]) {
+            let id = widget_id(&format!("{}/{}", label, name));
+            changed |= self.slider_with_id(id, &format!("  {}", name), channel, 0.0f32..=1.0f32);
+        }
+        changed
+    }
+
+    /// Draws the panel over `view`. Call after the scene has been drawn; calling it again
+    /// draws the same panel, e.g. into a screenshot.
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
+        self.pointer_clicked = false;
+        if !self.visible {
+            self.panel = Rect::default();
+            return;
+        }
+
+        let margin = MARGIN * self.scale;
+        self.panel = Rect::new(
+            margin,
+            margin,
+            self.content_width() + PADDING * self.scale * 2.0f32,
+            self.row - margin + (PADDING - 1.0f32) * self.scale,
+        );
+        let mut background = QuadList::new();
+        background.push(self.panel, self.white_texcoords(), PANEL_COLOR);
+        self.renderer.draw(
+            device,
+            queue,
+            view,
+            self.screen_size,
+            &[(&self.atlas, &background), (&self.atlas, &self.quads)],
+        );
+    }
+
+    fn frame_graph(&mut self) {
+        let height = GRAPH_HEIGHT * self.scale;
+        let width = self.content_width();
+        let bar_width = width / FRAME_HISTORY as f32;
+        let left = self.left() + width - bar_width * self.frame_times.len() as f32;
+        let bottom = self.row + height;
+        let bars = self
+            .frame_times
+            .iter()
+            .enumerate()
+            .map(|(i, delta)| {
+                let fill = (delta / GRAPH_MAX_FRAME_TIME).min(1.0f32) * height;
+                let color = if *delta <= 1.0f32 / 55.0f32 {
+                    GOOD_FRAME_COLOR
+                } else if *delta <= 1.0f32 / 28.0f32 {
+                    SLOW_FRAME_COLOR
+                } else {
+                    BAD_FRAME_COLOR
+                };
+                let rect = Rect::new(left + bar_width * i as f32, bottom - fill, bar_width, fill);
+                (rect, color)
+            })
+            .collect::<Vec<_>>();
+        self.fill(
+            Rect::new(self.left(), self.row, width, height),
+            WIDGET_COLOR,
+        );
+        for (rect, color) in bars {
+            self.fill(rect, color);
+        }
+        // Marks 60 fps
+        let target = bottom - height * (1.0f32 / 60.0f32) / GRAPH_MAX_FRAME_TIME;
+        self.fill(
+            Rect::new(self.left(), target, width, self.scale * 0.5f32),
+            DIM_TEXT_COLOR,
+        );
+        self.row = bottom + self.scale * 2.0f32;
+    }
+
+    fn separator(&mut self) {
+        let y = self.row + self.scale;
+        self.fill(
+            Rect::new(self.left(), y, self.content_width(), self.scale * 0.5f32),
+            DIM_TEXT_COLOR,
+        );
+        self.row = y + self.scale * 2.0f32;
+    }
+
+    fn text_row(&mut self, text: &str, color: [f32; 4]) {
+        let top = self.text_top(self.row);
+        self.text(self.left(), top, &truncate(text, PANEL_COLUMNS), color);
+        self.next_row();
+    }
+
+    fn text(&mut self, x: f32, y: f32, text: &str, color: [f32; 4]) {
+        let (width, height) = (
+            GLYPH_WIDTH as f32 * self.scale,
+            GLYPH_HEIGHT as f32 * self.scale,
+        );
+        for (i, c) in text.chars().enumerate() {
+            if c == ' ' {
+                continue;
+            }
+            let index = glyph(c).map_or('?' as u32, |_| c as u32) - FIRST_CHAR as u32;
+            let rect = Rect::new(x + i as f32 * self.advance(), y, width, height);
+            let texcoords = self.cell_texcoords(index, GLYPH_WIDTH, GLYPH_HEIGHT);
+            self.quads.push(rect, texcoords, color);
+        }
+    }
+
+    fn fill(&mut self, rect: Rect, color: [f32; 4]) {
+        let texcoords = self.white_texcoords();
+        self.quads.push(rect, texcoords, color);
+    }
+
+    fn is_hovered(&self, rect: &Rect) -> bool {
+        match self.cursor {
+            Some((x, y)) => rect.contains(x, y),
+            None => false,
+        }
+    }
+
+    fn next_row(&mut self) {
+        self.row += self.line_height() + self.scale;
+    }
+
+    fn advance(&self) -> f32 {
+        CELL_WIDTH as f32 * self.scale
+    }
+
+    fn line_height(&self) -> f32 {
+        (GLYPH_HEIGHT + 2) as f32 * self.scale
+    }
+
+    fn text_top(&self, row_top: f32) -> f32 {
+        row_top + self.scale
+    }
+
+    fn left(&self) -> f32 {
+        (MARGIN + PADDING) * self.scale
+    }
+
+    fn content_width(&self) -> f32 {
+        PANEL_COLUMNS as f32 * self.advance()
+    }
+
+    fn cell_texcoords(&self, index: u32, width: u32, height: u32) -> Rect {
+        let x = (index % ATLAS_COLUMNS) * CELL_WIDTH;
+        let y = (index / ATLAS_COLUMNS) * CELL_HEIGHT;
+        self.atlas.texcoords(x, y, width, height)
+    }
+
+    fn white_texcoords(&self) -> Rect {
+        // The middle texel, so filtering never reaches a neighbouring cell
+        let cell = self.cell_texcoords(WHITE_CELL, CELL_WIDTH, CELL_HEIGHT);
+        Rect::new(
+            cell.x + cell.width * 0.5f32,
+            cell.y + cell.height * 0.5f32,
+            0.0f32,
+            0.0f32,
+        )
+    }
+}
+
+/// White glyphs with coverage in alpha, laid out in `ATLAS_COLUMNS` by `ATLAS_ROWS` cells.
+fn font_atlas_texels() -> Vec<u8> {
+    let width = ATLAS_COLUMNS * CELL_WIDTH;
+    let height = ATLAS_ROWS * CELL_HEIGHT;
+    let mut texels = vec![0u8; (width * height * 4) as usize];
+    let mut set = |x: u32, y: u32| {
+        let offset = ((y * width + x) * 4) as usize;
+        texels[offset..offset + 4].copy_from_slice(&[255, 255, 255, 255]);
+    };
+    for (index, rows) in GLYPHS.iter().enumerate() {
+        let left = (index as u32 % ATLAS_COLUMNS) * CELL_WIDTH;
+        let top = (index as u32 / ATLAS_COLUMNS) * CELL_HEIGHT;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, _) in row.chars().enumerate().filter(|(_, c)| *c == '#') {
+                set(left + x as u32, top + y as u32);
+            }
+        }
+    }
+    let left = (WHITE_CELL % ATLAS_COLUMNS) * CELL_WIDTH;
+    let top = (WHITE_CELL / ATLAS_COLUMNS) * CELL_HEIGHT;
+    for y in top..top + CELL_HEIGHT {
+        for x in left..left + CELL_WIDTH {
+            set(x, y);
+        }
+    }
+    texels
+}
+
+fn widget_id(label: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn truncate(text: &str, columns: usize) -> String {
+    if text.chars().count() <= columns {
+        return text.to_string();
+    }
+    let mut truncated = text.chars().take(columns - 1).collect::<String>();
+    truncated.push('~');
+    truncated
+}
+
+/// The engine requests the adapter itself and doesn't hand it to scenes, so all that can be
+/// shown is the backend the settings asked for.
+fn backend_description() -> String {
+    match current_settings().backend {
+        Some(backend) => format!("Backend: {} (requested)", backend.name()),
+        None => "Backend: picked by wgpu".to_string(),
+    }
+}
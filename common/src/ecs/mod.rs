@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+pub type EntityId = u64;
+
+/// Components of one type, keyed by entity.
+///
+/// Backed by a `BTreeMap` so iteration follows entity order, which keeps systems deterministic.
+/// Groups serialize as plain maps, which saves and network snapshots are built from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ComponentGroup<T> {
+    components: BTreeMap<EntityId, T>,
+}
+
+impl<T> ComponentGroup<T> {
+    pub fn new() -> Self {
+        ComponentGroup {
+            components: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the previous component of `entity_id`, if any.
+    pub fn insert(&mut self, entity_id: EntityId, component: T) -> Option<T> {
+        self.components.insert(entity_id, component)
+    }
+
+    pub fn remove(&mut self, entity_id: &EntityId) -> Option<T> {
+        self.components.remove(entity_id)
+    }
+
+    pub fn get(&self, entity_id: &EntityId) -> Option<&T> {
+        self.components.get(entity_id)
+    }
+
+    pub fn get_mut(&mut self, entity_id: &EntityId) -> Option<&mut T> {
+        self.components.get_mut(entity_id)
+    }
+
+    pub fn contains(&self, entity_id: &EntityId) -> bool {
+        self.components.contains_key(entity_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = &EntityId> {
+        self.components.keys()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, EntityId, T> {
+        self.components.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<'_, EntityId, T> {
+        self.components.iter_mut()
+    }
+}
+
+impl<T> Default for ComponentGroup<T> {
+    fn default() -> Self {
+        ComponentGroup::new()
+    }
+}
+
+/// Hands out entity ids. Ids are never reused.
+///
+/// Allocation is atomic so command buffers filled on worker threads can reserve ids.
+pub struct EntityManager {
+    next_id: Arc<AtomicU64>,
+}
+
+impl EntityManager {
+    pub fn new() -> Self {
+        EntityManager::starting_at(1)
+    }
+
+    /// Continues numbering from `next_id`, e.g. after loading a saved world.
+    pub fn starting_at(next_id: EntityId) -> Self {
+        EntityManager {
+            next_id: Arc::new(AtomicU64::new(next_id)),
+        }
+    }
+
+    pub fn create_entity(&self) -> EntityId {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// The id the next entity will get.
+    pub fn next_id(&self) -> EntityId {
+        self.next_id.load(Ordering::Relaxed)
+    }
+
+    pub fn create_command_buffer<T>(&self) -> EntityCommandBuffer<T> {
+        EntityCommandBuffer {
+            next_id: Arc::clone(&self.next_id),
+            commands: VecDeque::new(),
+        }
+    }
+}
+
+impl Default for EntityManager {
+    fn default() -> Self {
+        EntityManager::new()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EntityCommand<T> {
+    CreateEntity { entity_id: EntityId },
+    DestroyEntity { entity_id: EntityId },
+    SetComponent { entity_id: EntityId, component: T },
+}
+
+/// Structural changes recorded while systems run, applied once they are done.
+///
+/// `T` is usually an enum with one variant per component type.
+pub struct EntityCommandBuffer<T> {
+    next_id: Arc<AtomicU64>,
+    commands: VecDeque<EntityCommand<T>>,
+}
+
+impl<T> EntityCommandBuffer<T> {
+    /// Reserves an id right away; the entity exists once the buffer is applied.
+    pub fn create_entity(&mut self) -> EntityId {
+        let entity_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.commands
+            .push_back(EntityCommand::CreateEntity { entity_id });
+        entity_id
+    }
+
+    pub fn destroy_entity(&mut self, entity_id: EntityId) {
+        self.commands
+            .push_back(EntityCommand::DestroyEntity { entity_id });
+    }
+
+    pub fn set_component(&mut self, entity_id: EntityId, component: T) {
+        self.commands.push_back(EntityCommand::SetComponent {
+            entity_id,
+            component,
+        });
+    }
+
+    /// Moves the commands of `other` after the ones already recorded.
+    pub fn append(&mut self, other: &mut EntityCommandBuffer<T>) {
+        self.commands.append(&mut other.commands);
+    }
+
+    /// Takes the oldest command.
+    pub fn pull(&mut self) -> Option<EntityCommand<T>> {
+        self.commands.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::ecs::EntityId;
+use serde::{Deserialize, Serialize};
+
+/// Position on the map, in tiles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Position {
+    pub fn new(x: f32, y: f32) -> Self {
+        Position { x, y }
+    }
+
+    pub fn distance(&self, other: &Position) -> f32 {
+        ((other.x - self.x).powi(2) + (other.y - self.y).powi(2)).sqrt()
+    }
+
+    pub fn lerp(&self, other: &Position, t: f32) -> Position {
+        Position {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+        }
+    }
+}
+
+/// Facing direction in radians, counterclockwise from +x.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Angle(pub f32);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CharacterStatus {
+    /// Tiles per second on plain ground.
+    pub speed: f32,
+    /// Item the character is holding.
+    pub carrying: Option<EntityId>,
+}
+
+impl Default for CharacterStatus {
+    fn default() -> Self {
+        CharacterStatus {
+            speed: 2.0f32,
+            carrying: None,
+        }
+    }
+}
+
+/// Marks an entity as an item characters can carry.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    /// Character holding the item; it follows them around.
+    pub carried_by: Option<EntityId>,
+}
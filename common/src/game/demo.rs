@@ -0,0 +1,142 @@
+use crate::action::character::CustomActionCreatorState;
+use crate::action::Tick;
+use crate::ecs::EntityId;
+use crate::game::component::{CharacterStatus, Position};
+use crate::game::GameState;
+use crate::map::Ground;
+use crate::save::SaveData;
+use serde::{Deserialize, Serialize};
+
+/// The small map shared by the character scene and the multiplayer server.
+pub const DEMO_MAP: &str = "
+..........
+..,,,.....
+..,~~~..#.
+...~~...#.
+........#.
+.####.....
+......,,..
+..........
+";
+
+// Where characters bring the items, in turn
+const DROP_POINTS: [(i32, i32); 4] = [(0, 0), (9, 0), (9, 7), (0, 7)];
+
+/// A saved `DemoWorld`: the game state and the demo's errand counter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DemoSaveData {
+    pub state: SaveData,
+    pub next_order: usize,
+}
+
+pub struct DemoWorld {
+    pub state: GameState,
+    pub characters: Vec<EntityId>,
+    pub items: Vec<EntityId>,
+    pub next_order: usize,
+}
+
+impl DemoWorld {
+    /// Picks the characters and items back out of `state`, e.g. after loading it.
+    pub fn from_state(state: GameState, next_order: usize) -> Self {
+        let characters = state.character_status.entities().copied().collect();
+        let items = state.items.entities().copied().collect();
+        DemoWorld {
+            state,
+            characters,
+            items,
+            next_order,
+        }
+    }
+
+    pub fn from_save_data(data: DemoSaveData) -> Self {
+        DemoWorld::from_state(data.state.into_state(), data.next_order)
+    }
+
+    pub fn to_save_data(&self) -> DemoSaveData {
+        DemoSaveData {
+            state: SaveData::from_state(&self.state),
+            next_order: self.next_order,
+        }
+    }
+
+    /// Sends idle characters to bring the next item to the next drop point.
+    pub fn order_idle_characters(&mut self) {
+        for character in self.characters.iter() {
+            if !self.state.is_idle(*character) || self.items.is_empty() {
+                continue;
+            }
+            let item = self.items[self.next_order % self.items.len()];
+            let (x, y) = DROP_POINTS[self.next_order % DROP_POINTS.len()];
+            self.next_order += 1;
+            self.state.order(
+                *character,
+                CustomActionCreatorState::BringItemToDestination {
+                    item,
+                    destination: Position::new(x as f32, y as f32),
+                },
+            );
+        }
+    }
+
+    /// Gives out errands, then advances the world by `delta` ticks.
+    pub fn update(&mut self, delta: Tick) {
+        self.order_idle_characters();
+        self.state.update(delta);
+    }
+}
+
+/// Three characters and three items on `DEMO_MAP`.
+pub fn create_demo_world() -> DemoWorld {
+    let ground = Ground::parse(DEMO_MAP).expect("Invalid demo map");
+    let mut state = GameState::new(ground);
+    let characters = [(1.0f32, 1.0f32), (6.0f32, 4.0f32), (8.0f32, 7.0f32)]
+        .iter()
+        .enumerate()
+        .map(|(i, (x, y))| {
+            state.spawn_character(
+                Position::new(*x, *y),
+                CharacterStatus {
+                    speed: 1.5f32 + i as f32 * 0.5f32,
+                    carrying: None,
+                },
+            )
+        })
+        .collect();
+    let items = [(4.0f32, 0.0f32), (5.0f32, 6.0f32), (1.0f32, 4.0f32)]
+        .iter()
+        .map(|(x, y)| state.spawn_item(Position::new(*x, *y)))
+        .collect();
+    DemoWorld {
+        state,
+        characters,
+        items,
+        next_order: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save::{decode_save, encode_save};
+
+    #[test]
+    fn loaded_worlds_carry_on_like_the_original() {
+        let mut world = create_demo_world();
+        for _ in 0..50 {
+            world.update(100);
+        }
+        let bytes = encode_save(&world.to_save_data()).unwrap();
+        let mut loaded = DemoWorld::from_save_data(decode_save(&bytes).unwrap());
+        assert_eq!(loaded.characters, world.characters);
+        assert_eq!(loaded.items, world.items);
+        assert_eq!(loaded.next_order, world.next_order);
+
+        for _ in 0..100 {
+            world.update(100);
+            loaded.update(100);
+        }
+        assert_eq!(loaded.next_order, world.next_order);
+        assert_eq!(loaded.to_save_data(), world.to_save_data());
+    }
+}
@@ -0,0 +1,485 @@
+pub mod component;
+pub mod demo;
+
+use crate::action::character::{
+    create_action, ActionCreatorContext, CustomActionCreatorState, CustomActionState,
+};
+use crate::action::{ActionEvent, ActionScheduler, Tick};
+use crate::ecs::{ComponentGroup, EntityCommand, EntityCommandBuffer, EntityId, EntityManager};
+use crate::game::component::{Angle, CharacterStatus, Item, Position};
+use crate::job::{JobAccess, JobWorker, SystemJob};
+use crate::map::path::MapPath;
+use crate::map::Ground;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameStateComponent {
+    Position(Position),
+    Angle(Angle),
+    CharacterStatus(CharacterStatus),
+    Item(Item),
+}
+
+/// The simulated world: entities, their components, the map and everyone's plans.
+pub struct GameState {
+    pub entity_manager: EntityManager,
+    pub entities: BTreeSet<EntityId>,
+    pub positions: ComponentGroup<Position>,
+    pub angles: ComponentGroup<Angle>,
+    pub character_status: ComponentGroup<CharacterStatus>,
+    pub items: ComponentGroup<Item>,
+    pub ground: Ground,
+    pub map_path: MapPath,
+    pub actions: ActionScheduler<CustomActionState, CustomActionCreatorState>,
+    pub job_worker: JobWorker,
+}
+
+impl GameState {
+    pub fn new(ground: Ground) -> Self {
+        GameState {
+            entity_manager: EntityManager::new(),
+            entities: BTreeSet::new(),
+            positions: ComponentGroup::new(),
+            angles: ComponentGroup::new(),
+            character_status: ComponentGroup::new(),
+            items: ComponentGroup::new(),
+            map_path: MapPath::new(&ground),
+            ground,
+            actions: ActionScheduler::new(),
+            job_worker: JobWorker::new(),
+        }
+    }
+
+    pub fn tick(&self) -> Tick {
+        self.actions.tick()
+    }
+
+    pub fn spawn_character(&mut self, position: Position, status: CharacterStatus) -> EntityId {
+        let mut commands = self.entity_manager.create_command_buffer();
+        let entity_id = commands.create_entity();
+        commands.set_component(entity_id, GameStateComponent::Position(position));
+        commands.set_component(entity_id, GameStateComponent::Angle(Angle::default()));
+        commands.set_component(entity_id, GameStateComponent::CharacterStatus(status));
+        self.apply_commands(&mut commands);
+        entity_id
+    }
+
+    pub fn spawn_item(&mut self, position: Position) -> EntityId {
+        let mut commands = self.entity_manager.create_command_buffer();
+        let entity_id = commands.create_entity();
+        commands.set_component(entity_id, GameStateComponent::Position(position));
+        commands.set_component(entity_id, GameStateComponent::Item(Item::default()));
+        self.apply_commands(&mut commands);
+        entity_id
+    }
+
+    /// Queues `creator` after whatever `entity_id` is already doing.
+    pub fn order(&mut self, entity_id: EntityId, creator: CustomActionCreatorState) {
+        self.actions.push_creator(entity_id, creator);
+    }
+
+    pub fn is_idle(&self, entity_id: EntityId) -> bool {
+        self.actions.is_idle(entity_id)
+    }
+
+    /// Advances the world by `delta` ticks.
+    pub fn update(&mut self, delta: Tick) {
+        let events = {
+            let context = ActionCreatorContext {
+                positions: &self.positions,
+                character_status: &self.character_status,
+                map_path: &self.map_path,
+                ground: &self.ground,
+            };
+            self.actions.update(delta, |entity_id, creator| {
+                create_action(&entity_id, creator, &context)
+            })
+        };
+
+        // Each system records into its own buffer; they are applied in a fixed order afterwards
+        // so the result doesn't depend on which finished first
+        let mut event_commands = self.entity_manager.create_command_buffer();
+        let mut movement_commands = self.entity_manager.create_command_buffer();
+        let mut cancelled = vec![];
+        {
+            let character_status = &self.character_status;
+            let items = &self.items;
+            let actions = &self.actions;
+            let entities = &self.entities;
+            let event_commands = &mut event_commands;
+            let movement_commands = &mut movement_commands;
+            let cancelled = &mut cancelled;
+            self.job_worker.barrier(vec![
+                SystemJob::new(
+                    "action_event_system",
+                    JobAccess::new()
+                        .read::<ComponentGroup<CharacterStatus>>()
+                        .read::<ComponentGroup<Item>>()
+                        .write_one(&*event_commands)
+                        .write_one(&*cancelled),
+                    move || {
+                        action_event_system(
+                            &events,
+                            character_status,
+                            items,
+                            event_commands,
+                            cancelled,
+                        )
+                    },
+                ),
+                SystemJob::new(
+                    "movement_system",
+                    JobAccess::new()
+                        .read::<ActionScheduler<CustomActionState, CustomActionCreatorState>>()
+                        .read::<BTreeSet<EntityId>>()
+                        .write_one(&*movement_commands),
+                    move || movement_system(actions, entities, movement_commands),
+                ),
+            ]);
+        }
+        event_commands.append(&mut movement_commands);
+        for entity_id in cancelled {
+            self.actions.cancel(entity_id);
+        }
+
+        self.apply_commands(&mut event_commands);
+        carry_system(&self.character_status, &mut self.positions);
+    }
+
+    /// Applies the structural changes systems recorded, in order.
+    pub fn apply_commands(&mut self, commands: &mut EntityCommandBuffer<GameStateComponent>) {
+        while let Some(command) = commands.pull() {
+            match command {
+                EntityCommand::CreateEntity { entity_id } => {
+                    self.entities.insert(entity_id);
+                }
+                EntityCommand::DestroyEntity { entity_id } => {
+                    self.entities.remove(&entity_id);
+                    self.positions.remove(&entity_id);
+                    self.angles.remove(&entity_id);
+                    self.character_status.remove(&entity_id);
+                    self.items.remove(&entity_id);
+                    self.actions.cancel(entity_id);
+                }
+                EntityCommand::SetComponent {
+                    entity_id,
+                    component,
+                } => {
+                    if !self.entities.contains(&entity_id) {
+                        continue;
+                    }
+                    match component {
+                        GameStateComponent::Position(position) => {
+                            self.positions.insert(entity_id, position);
+                        }
+                        GameStateComponent::Angle(angle) => {
+                            self.angles.insert(entity_id, angle);
+                        }
+                        GameStateComponent::CharacterStatus(status) => {
+                            self.character_status.insert(entity_id, status);
+                        }
+                        GameStateComponent::Item(item) => {
+                            self.items.insert(entity_id, item);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applies the outcome of finished actions.
+///
+/// Characters whose pick-up came too late, because someone else took the item first, or who
+/// drop an item they aren't holding are added to `cancelled` so the rest of their plan can be
+/// dropped.
+fn action_event_system(
+    events: &[ActionEvent<CustomActionState>],
+    character_status: &ComponentGroup<CharacterStatus>,
+    items: &ComponentGroup<Item>,
+    commands: &mut EntityCommandBuffer<GameStateComponent>,
+    cancelled: &mut Vec<EntityId>,
+) {
+    // Who carries each item, and what each character holds, after the events handled so far;
+    // the commands aren't applied yet
+    let mut carriers: BTreeMap<EntityId, Option<EntityId>> = BTreeMap::new();
+    let mut holding: BTreeMap<EntityId, Option<EntityId>> = BTreeMap::new();
+    for event in events {
+        let action = match event {
+            ActionEvent::End(action) => action,
+            _ => continue,
+        };
+        let entity_id = action.entity_id;
+        match &action.state {
+            CustomActionState::MoveToPoint { end, .. } => {
+                commands.set_component(entity_id, GameStateComponent::Position(*end));
+            }
+            CustomActionState::PickUp { item } => {
+                let carried_by = match carriers.get(item) {
+                    Some(carried_by) => Some(*carried_by),
+                    None => items.get(item).map(|item| item.carried_by),
+                };
+                let status = character_status.get(&entity_id);
+                if let (Some(status), Some(None)) = (status, carried_by) {
+                    carriers.insert(*item, Some(entity_id));
+                    holding.insert(entity_id, Some(*item));
+                    commands.set_component(
+                        entity_id,
+                        GameStateComponent::CharacterStatus(CharacterStatus {
+                            carrying: Some(*item),
+                            ..status.clone()
+                        }),
+                    );
+                    commands.set_component(
+                        *item,
+                        GameStateComponent::Item(Item {
+                            carried_by: Some(entity_id),
+                        }),
+                    );
+                } else {
+                    cancelled.push(entity_id);
+                }
+            }
+            CustomActionState::Drop { item, position } => {
+                let status = character_status.get(&entity_id);
+                let carrying = match holding.get(&entity_id) {
+                    Some(carrying) => *carrying,
+                    None => status.and_then(|status| status.carrying),
+                };
+                match status {
+                    Some(status) if carrying == Some(*item) => {
+                        carriers.insert(*item, None);
+                        holding.insert(entity_id, None);
+                        commands.set_component(
+                            entity_id,
+                            GameStateComponent::CharacterStatus(CharacterStatus {
+                                carrying: None,
+                                ..status.clone()
+                            }),
+                        );
+                        commands.set_component(
+                            *item,
+                            GameStateComponent::Item(Item { carried_by: None }),
+                        );
+                        commands.set_component(*item, GameStateComponent::Position(*position));
+                    }
+                    _ => cancelled.push(entity_id),
+                }
+            }
+        }
+    }
+}
+
+/// Places moving characters along their current step and turns them to face it.
+fn movement_system(
+    actions: &ActionScheduler<CustomActionState, CustomActionCreatorState>,
+    entities: &BTreeSet<EntityId>,
+    commands: &mut EntityCommandBuffer<GameStateComponent>,
+) {
+    let tick = actions.tick();
+    for entity_id in entities {
+        let action = match actions.current_action(*entity_id) {
+            Some(action) => action,
+            None => continue,
+        };
+        if let CustomActionState::MoveToPoint { start, end } = &action.state {
+            let position = start.lerp(end, action.ratio(tick));
+            commands.set_component(*entity_id, GameStateComponent::Position(position));
+            if start != end {
+                let angle = (end.y - start.y).atan2(end.x - start.x);
+                commands.set_component(*entity_id, GameStateComponent::Angle(Angle(angle)));
+            }
+        }
+    }
+}
+
+/// Keeps carried items in their carrier's hands.
+fn carry_system(
+    character_status: &ComponentGroup<CharacterStatus>,
+    positions: &mut ComponentGroup<Position>,
+) {
+    for (entity_id, status) in character_status.iter() {
+        let item = match status.carrying {
+            Some(item) => item,
+            None => continue,
+        };
+        if let Some(position) = positions.get(entity_id).copied() {
+            positions.insert(item, position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+
+    const DESTINATION: Position = Position {
+        x: 2.0f32,
+        y: 2.0f32,
+    };
+
+    fn race_for_one_item() -> (GameState, EntityId, EntityId, EntityId) {
+        let ground = Ground::parse(
+            "
+            .....
+            .....
+            .....
+            ",
+        )
+        .unwrap();
+        let mut state = GameState::new(ground);
+        let left = state.spawn_character(Position::new(0.0f32, 0.0f32), CharacterStatus::default());
+        let right =
+            state.spawn_character(Position::new(4.0f32, 0.0f32), CharacterStatus::default());
+        let item = state.spawn_item(Position::new(2.0f32, 0.0f32));
+        for character in [left, right].iter() {
+            state.order(
+                *character,
+                CustomActionCreatorState::BringItemToDestination {
+                    item,
+                    destination: DESTINATION,
+                },
+            );
+        }
+        (state, left, right, item)
+    }
+
+    #[test]
+    fn only_one_character_picks_up_a_contested_item() {
+        let (mut state, left, right, item) = race_for_one_item();
+        // Both arrive after a second and finish picking up half a second later
+        for _ in 0..16 {
+            state.update(100);
+        }
+
+        let carriers = [left, right]
+            .iter()
+            .copied()
+            .filter(|character| {
+                state.character_status.get(character).unwrap().carrying == Some(item)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(carriers.len(), 1);
+        let winner = carriers[0];
+        let loser = if winner == left { right } else { left };
+        assert_eq!(state.items.get(&item).unwrap().carried_by, Some(winner));
+        assert!(state.is_idle(loser));
+        assert!(!state.is_idle(winner));
+
+        while !state.is_idle(winner) {
+            state.update(100);
+        }
+        assert_eq!(state.items.get(&item).unwrap().carried_by, None);
+        assert_eq!(state.positions.get(&item), Some(&DESTINATION));
+        assert_eq!(state.character_status.get(&winner).unwrap().carrying, None);
+    }
+
+    fn carried_item(
+        carrier: EntityId,
+        item: EntityId,
+        others: &[EntityId],
+    ) -> (ComponentGroup<CharacterStatus>, ComponentGroup<Item>) {
+        let mut character_status = ComponentGroup::new();
+        character_status.insert(
+            carrier,
+            CharacterStatus {
+                carrying: Some(item),
+                ..CharacterStatus::default()
+            },
+        );
+        for character in others {
+            character_status.insert(*character, CharacterStatus::default());
+        }
+        let mut items = ComponentGroup::new();
+        items.insert(
+            item,
+            Item {
+                carried_by: Some(carrier),
+            },
+        );
+        (character_status, items)
+    }
+
+    fn end(entity_id: EntityId, state: CustomActionState) -> ActionEvent<CustomActionState> {
+        ActionEvent::End(Action {
+            entity_id,
+            start: 0,
+            end: 100,
+            state,
+        })
+    }
+
+    #[test]
+    fn items_dropped_can_be_picked_up_in_the_same_update() {
+        let (first, second, third, item) = (1, 2, 3, 4);
+        let (character_status, items) = carried_item(first, item, &[second, third]);
+        let events = vec![
+            end(second, CustomActionState::PickUp { item }),
+            end(
+                first,
+                CustomActionState::Drop {
+                    item,
+                    position: Position::default(),
+                },
+            ),
+            end(third, CustomActionState::PickUp { item }),
+        ];
+
+        let mut commands = EntityManager::new().create_command_buffer();
+        let mut cancelled = vec![];
+        action_event_system(
+            &events,
+            &character_status,
+            &items,
+            &mut commands,
+            &mut cancelled,
+        );
+        assert_eq!(cancelled, vec![second]);
+        let mut item_updates = vec![];
+        while let Some(command) = commands.pull() {
+            if let EntityCommand::SetComponent {
+                component: GameStateComponent::Item(component),
+                ..
+            } = command
+            {
+                item_updates.push(component.carried_by);
+            }
+        }
+        assert_eq!(item_updates, vec![None, Some(third)]);
+    }
+
+    #[test]
+    fn only_the_carrier_can_drop_an_item() {
+        let (carrier, other, item) = (1, 2, 3);
+        let (character_status, items) = carried_item(carrier, item, &[other]);
+        let drop = CustomActionState::Drop {
+            item,
+            position: Position::default(),
+        };
+        let events = vec![
+            end(other, drop.clone()),
+            end(carrier, drop.clone()),
+            end(carrier, drop),
+        ];
+
+        let mut commands = EntityManager::new().create_command_buffer();
+        let mut cancelled = vec![];
+        action_event_system(
+            &events,
+            &character_status,
+            &items,
+            &mut commands,
+            &mut cancelled,
+        );
+        // The carrier can't drop the item twice either
+        assert_eq!(cancelled, vec![other, carrier]);
+        let mut updated = vec![];
+        while let Some(command) = commands.pull() {
+            if let EntityCommand::SetComponent { entity_id, .. } = command {
+                updated.push(entity_id);
+            }
+        }
+        assert_eq!(updated, vec![carrier, item, item]);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::capture::save_png;
+use image::RgbaImage;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug)]
+pub struct GoldenTolerance {
+    /// Maximum absolute difference allowed for each channel of a pixel.
+    pub channel: u8,
+    /// Ratio of pixels (0.0 - 1.0) allowed to exceed `channel`.
+    pub pixel_ratio: f32,
+}
+
+impl Default for GoldenTolerance {
+    fn default() -> Self {
+        GoldenTolerance {
+            channel: 2,
+            pixel_ratio: 0.001,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Image(image::ImageError),
+    /// There is no golden image yet; the actual image was written next to where it should be.
+    Missing {
+        golden_path: PathBuf,
+        actual_path: PathBuf,
+    },
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    Mismatch {
+        mismatched_pixels: usize,
+        max_difference: u8,
+        actual_path: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Image(e) => write!(f, "{}", e),
+            GoldenError::Missing {
+                golden_path,
+                actual_path,
+            } => write!(
+                f,
+                "golden image {:?} does not exist, actual image written to {:?}; run with UPDATE_GOLDEN=1 to accept it",
+                golden_path, actual_path
+            ),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "image size {:?} does not match the golden image size {:?}",
+                actual, expected
+            ),
+            GoldenError::Mismatch {
+                mismatched_pixels,
+                max_difference,
+                actual_path,
+            } => write!(
+                f,
+                "{} pixels differ from the golden image (max difference {}), actual image written to {:?}",
+                mismatched_pixels, max_difference, actual_path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<image::ImageError> for GoldenError {
+    fn from(e: image::ImageError) -> Self {
+        GoldenError::Image(e)
+    }
+}
+
+/// Compares `actual` with the PNG at `golden_path`.
+///
+/// If `UPDATE_GOLDEN` is set, `actual` is written as the new golden image instead.
+/// A missing golden image is an error, so that CI can't pass without one.
+/// On failure the rendered image is written next to the golden one as `<name>.actual.png`.
+pub fn assert_golden<P: AsRef<Path>>(
+    actual: &RgbaImage,
+    golden_path: P,
+    tolerance: GoldenTolerance,
+) -> Result<(), GoldenError> {
+    let golden_path = golden_path.as_ref();
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        save_png(actual, golden_path)?;
+        return Ok(());
+    }
+    let actual_path = golden_path.with_extension("actual.png");
+    if !golden_path.exists() {
+        save_png(actual, &actual_path)?;
+        return Err(GoldenError::Missing {
+            golden_path: golden_path.to_path_buf(),
+            actual_path,
+        });
+    }
+
+    let expected = image::open(golden_path)?.to_rgba8();
+    if let Some((mismatched_pixels, max_difference)) = compare(&expected, actual, tolerance)? {
+        save_png(actual, &actual_path)?;
+        return Err(GoldenError::Mismatch {
+            mismatched_pixels,
+            max_difference,
+            actual_path,
+        });
+    }
+    Ok(())
+}
+
+/// The number of pixels differing by more than `tolerance` and the largest difference, or
+/// `None` if few enough pixels differ.
+fn compare(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    tolerance: GoldenTolerance,
+) -> Result<Option<(usize, u8)>, GoldenError> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err(GoldenError::SizeMismatch {
+            expected: expected.dimensions(),
+            actual: actual.dimensions(),
+        });
+    }
+
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    for (a, e) in actual.pixels().zip(expected.pixels()) {
+        let difference =
+            a.0.iter()
+                .zip(e.0.iter())
+                .map(|(a, e)| (*a as i16 - *e as i16).abs() as u8)
+                .max()
+                .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > tolerance.channel {
+            mismatched_pixels += 1;
+        }
+    }
+
+    let total_pixels = (actual.width() * actual.height()) as f32;
+    if mismatched_pixels as f32 > total_pixels * tolerance.pixel_ratio {
+        Ok(Some((mismatched_pixels, max_difference)))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const GRAY: Rgba<u8> = Rgba([100, 100, 100, 255]);
+
+    /// A 10x10 gray image with the first `count` pixels brightened by `difference`.
+    fn image_with_changes(count: u32, difference: u8) -> RgbaImage {
+        let mut image = RgbaImage::from_pixel(10, 10, GRAY);
+        for i in 0..count {
+            let pixel = image.get_pixel_mut(i % 10, i / 10);
+            pixel.0[1] += difference;
+        }
+        image
+    }
+
+    fn tolerance(channel: u8, pixel_ratio: f32) -> GoldenTolerance {
+        GoldenTolerance {
+            channel,
+            pixel_ratio,
+        }
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = image_with_changes(0, 0);
+        assert_eq!(compare(&image, &image, tolerance(0, 0.0f32)).unwrap(), None);
+    }
+
+    #[test]
+    fn channel_differences_within_tolerance_match() {
+        let expected = image_with_changes(0, 0);
+        let actual = image_with_changes(100, 2);
+        assert_eq!(
+            compare(&expected, &actual, tolerance(2, 0.0f32)).unwrap(),
+            None
+        );
+        assert_eq!(
+            compare(&expected, &actual, tolerance(1, 0.0f32)).unwrap(),
+            Some((100, 2))
+        );
+    }
+
+    #[test]
+    fn a_few_differing_pixels_are_allowed() {
+        let expected = image_with_changes(0, 0);
+        let actual = image_with_changes(5, 50);
+        // 5 of 100 pixels differ
+        assert_eq!(
+            compare(&expected, &actual, tolerance(2, 0.05f32)).unwrap(),
+            None
+        );
+        assert_eq!(
+            compare(&expected, &actual, tolerance(2, 0.04f32)).unwrap(),
+            Some((5, 50))
+        );
+    }
+
+    #[test]
+    fn differently_sized_images_never_match() {
+        let expected = RgbaImage::from_pixel(10, 10, GRAY);
+        let actual = RgbaImage::from_pixel(10, 12, GRAY);
+        match compare(&expected, &actual, tolerance(255, 1.0f32)) {
+            Err(GoldenError::SizeMismatch { expected, actual }) => {
+                assert_eq!(expected, (10, 10));
+                assert_eq!(actual, (10, 12));
+            }
+            result => panic!("Expected a size mismatch, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn mismatches_keep_the_actual_image() {
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            return;
+        }
+        let directory =
+            std::env::temp_dir().join(format!("golden-mismatch-{}", std::process::id()));
+        let golden_path = directory.join("golden.png");
+        save_png(&image_with_changes(0, 0), &golden_path).unwrap();
+
+        assert!(assert_golden(
+            &image_with_changes(0, 0),
+            &golden_path,
+            tolerance(0, 0.0f32)
+        )
+        .is_ok());
+        let actual = image_with_changes(1, 9);
+        match assert_golden(&actual, &golden_path, tolerance(0, 0.0f32)) {
+            Err(GoldenError::Mismatch {
+                mismatched_pixels,
+                max_difference,
+                actual_path,
+            }) => {
+                assert_eq!((mismatched_pixels, max_difference), (1, 9));
+                assert_eq!(image::open(&actual_path).unwrap().to_rgba8(), actual);
+            }
+            result => panic!("Expected a mismatch, got {:?}", result),
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn missing_golden_images_fail_and_keep_the_actual_image() {
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            return;
+        }
+        let directory = std::env::temp_dir().join(format!("golden-test-{}", std::process::id()));
+        let golden_path = directory.join("missing.png");
+        let actual = image_with_changes(3, 10);
+
+        match assert_golden(&actual, &golden_path, GoldenTolerance::default()) {
+            Err(GoldenError::Missing {
+                golden_path: missing,
+                actual_path,
+            }) => {
+                assert_eq!(missing, golden_path);
+                assert_eq!(actual_path, directory.join("missing.actual.png"));
+                assert_eq!(image::open(&actual_path).unwrap().to_rgba8(), actual);
+            }
+            result => panic!("Expected a missing golden image, got {:?}", result),
+        }
+        assert!(!golden_path.exists());
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+pub mod golden;
+
+use crate::capture::OffscreenTarget;
+use crate::settings::Backend;
+use image::RgbaImage;
+use std::fmt;
+
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+pub const DEFAULT_FRAME_DELTA: f32 = 1.0f32 / 60.0f32;
+
+pub struct HeadlessContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A scene that can draw itself into an arbitrary texture view, without a window or swapchain.
+///
+/// `update` receives the simulated time step, so output doesn't depend on how fast frames are produced.
+pub trait HeadlessScene {
+    fn update(&mut self, context: &HeadlessContext, delta: f32);
+
+    fn render(&mut self, context: &HeadlessContext, view: &wgpu::TextureView);
+}
+
+pub type HeadlessSceneFactory = fn(context: &HeadlessContext) -> Box<dyn HeadlessScene>;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    AdapterNotFound,
+    FallbackAdapterNotFound,
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::AdapterNotFound => write!(f, "no suitable graphics adapter was found"),
+            HeadlessError::FallbackAdapterNotFound => {
+                write!(f, "no software (CPU) graphics adapter was found")
+            }
+            HeadlessError::RequestDevice(e) => write!(f, "failed to request a device: {:?}", e),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+pub struct HeadlessConfig {
+    pub width: u32,
+    pub height: u32,
+    pub backends: wgpu::BackendBit,
+    pub power_preference: wgpu::PowerPreference,
+    /// Only accept a software adapter (lavapipe, SwiftShader, WARP), e.g. for CI machines
+    /// without a GPU or to get the same output everywhere.
+    pub force_fallback_adapter: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        HeadlessConfig {
+            width: 256,
+            height: 256,
+            backends: backends_from_env().unwrap_or(wgpu::BackendBit::PRIMARY),
+            power_preference: wgpu::PowerPreference::LowPower,
+            force_fallback_adapter: std::env::var("WGPU_FALLBACK_ADAPTER").is_ok(),
+        }
+    }
+}
+
+/// Reads `WGPU_BACKEND` (`vulkan`, `metal`, `dx12`, `dx11` or `gl`) so that CI can pin a backend.
+pub fn backends_from_env() -> Option<wgpu::BackendBit> {
+    let value = std::env::var("WGPU_BACKEND").ok()?;
+    Backend::from_name(&value).map(Backend::bits)
+}
+
+/// Renders scenes into an offscreen texture.
+///
+/// Machines without a GPU can run it on a software adapter; set
+/// `HeadlessConfig::force_fallback_adapter` (or `WGPU_FALLBACK_ADAPTER`) to require one.
+pub struct HeadlessRunner {
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    target: OffscreenTarget,
+}
+
+impl HeadlessRunner {
+    pub fn new(config: HeadlessConfig) -> Result<Self, HeadlessError> {
+        futures::executor::block_on(Self::new_async(config))
+    }
+
+    async fn new_async(config: HeadlessConfig) -> Result<Self, HeadlessError> {
+        let adapter = if config.force_fallback_adapter {
+            request_fallback_adapter(config.backends)
+                .ok_or(HeadlessError::FallbackAdapterNotFound)?
+        } else {
+            // Fall back to any backend before giving up, since CI images often only ship GL
+            match request_adapter(config.backends, config.power_preference).await {
+                Some(adapter) => adapter,
+                None => request_adapter(wgpu::BackendBit::all(), config.power_preference)
+                    .await
+                    .ok_or(HeadlessError::AdapterNotFound)?,
+            }
+        };
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                },
+                None,
+            )
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+        let target = OffscreenTarget::new(&device, config.width, config.height, HEADLESS_FORMAT);
+
+        Ok(HeadlessRunner {
+            adapter,
+            device,
+            queue,
+            target,
+        })
+    }
+
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    pub fn context(&self) -> HeadlessContext {
+        HeadlessContext {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.target.format(),
+            width: self.target.width(),
+            height: self.target.height(),
+        }
+    }
+
+    pub fn create_scene(&self, factory: HeadlessSceneFactory) -> Box<dyn HeadlessScene> {
+        factory(&self.context())
+    }
+
+    /// Advances `scene` by `delta` seconds, renders one frame and reads it back.
+    pub fn render_frame(&self, scene: &mut dyn HeadlessScene, delta: f32) -> RgbaImage {
+        let context = self.context();
+        scene.update(&context, delta);
+        scene.render(&context, self.target.view());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.target.copy_to_buffer(&mut encoder);
+        self.queue.submit(Some(encoder.finish()));
+        self.target.read_image(&self.device)
+    }
+
+    /// Creates a scene, renders `frames` frames of it at `DEFAULT_FRAME_DELTA` and returns the last one.
+    pub fn render_frames(&self, factory: HeadlessSceneFactory, frames: usize) -> RgbaImage {
+        let mut scene = self.create_scene(factory);
+        let context = self.context();
+        for _ in 1..frames {
+            scene.update(&context, DEFAULT_FRAME_DELTA);
+            scene.render(&context, self.target.view());
+        }
+        self.render_frame(scene.as_mut(), DEFAULT_FRAME_DELTA)
+    }
+}
+
+async fn request_adapter(
+    backends: wgpu::BackendBit,
+    power_preference: wgpu::PowerPreference,
+) -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(backends);
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference,
+            compatible_surface: None,
+        })
+        .await
+}
+
+/// The first software adapter of `backends`. wgpu can't be asked for one directly, so the
+/// adapters are enumerated and the ones backed by the CPU are kept.
+fn request_fallback_adapter(backends: wgpu::BackendBit) -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(backends);
+    instance
+        .enumerate_adapters(backends)
+        .find(|adapter| adapter.get_info().device_type == wgpu::DeviceType::Cpu)
+}
@@ -0,0 +1,189 @@
+use crate::input::gamepad::{GamepadAxis, GamepadButton};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tearchan::fs::{file_util, read_bytes_from_file};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+/// Bindings the example ships with, relative to the assets directory.
+pub const INPUT_BINDINGS_FILE: &str = "input_bindings.json";
+
+#[derive(Debug)]
+pub enum InputBindingsError {
+    Load(String),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for InputBindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputBindingsError::Load(message) => {
+                write!(f, "failed to read input bindings: {}", message)
+            }
+            InputBindingsError::Parse(e) => write!(f, "invalid input bindings: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InputBindingsError {}
+
+impl From<serde_json::Error> for InputBindingsError {
+    fn from(e: serde_json::Error) -> Self {
+        InputBindingsError::Parse(e)
+    }
+}
+
+/// A physical input. Serialized as `{ "key": "Space" }`, `{ "mouse": "Left" }`,
+/// `{ "gamepad": "South" }` or `"touch"`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+    /// The button on any connected gamepad
+    Gamepad(GamepadButton),
+    /// Any finger on the screen
+    Touch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseAxis {
+    X,
+    Y,
+    Wheel,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisBinding {
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    Mouse {
+        axis: MouseAxis,
+        scale: f32,
+    },
+    Gamepad {
+        axis: GamepadAxis,
+        scale: f32,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    #[serde(default)]
+    pub actions: HashMap<String, Vec<Binding>>,
+    #[serde(default)]
+    pub axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl InputBindings {
+    pub fn from_slice(bytes: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn to_vec(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+}
+
+/// Path of `INPUT_BINDINGS_FILE` in the assets directory.
+pub fn default_input_bindings_path() -> PathBuf {
+    let mut path = PathBuf::new();
+    path.push(file_util().assets_path());
+    path.push(INPUT_BINDINGS_FILE);
+    path
+}
+
+/// Reads and parses a bindings file through `tearchan::fs`.
+pub async fn load_input_bindings<P: AsRef<Path>>(
+    path: P,
+) -> Result<InputBindings, InputBindingsError> {
+    let path = path.as_ref().to_path_buf();
+    let bytes = read_bytes_from_file(path.clone())
+        .await
+        .map_err(|e| InputBindingsError::Load(format!("{:?}: {:?}", path, e)))?;
+    Ok(InputBindings::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::gamepad::GamepadAxis;
+
+    #[test]
+    fn parses_every_binding_kind() {
+        let bindings = InputBindings::from_slice(
+            br#"{
+                "actions": {
+                    "jump": [{ "key": "Space" }, { "mouse": "Left" }, { "gamepad": "South" }, "touch"]
+                },
+                "axes": {
+                    "move_x": [
+                        { "buttons": { "negative": { "key": "A" }, "positive": { "key": "D" } } },
+                        { "mouse": { "axis": "wheel", "scale": 2.0 } },
+                        { "gamepad": { "axis": "LeftStickX", "scale": -1.0 } }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            bindings.actions["jump"],
+            vec![
+                Binding::Key(VirtualKeyCode::Space),
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButton::South),
+                Binding::Touch,
+            ]
+        );
+        assert_eq!(
+            bindings.axes["move_x"],
+            vec![
+                AxisBinding::Buttons {
+                    negative: Binding::Key(VirtualKeyCode::A),
+                    positive: Binding::Key(VirtualKeyCode::D),
+                },
+                AxisBinding::Mouse {
+                    axis: MouseAxis::Wheel,
+                    scale: 2.0f32,
+                },
+                AxisBinding::Gamepad {
+                    axis: GamepadAxis::LeftStickX,
+                    scale: -1.0f32,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_sections_are_empty() {
+        assert_eq!(
+            InputBindings::from_slice(b"{}").unwrap(),
+            InputBindings::default()
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(
+            InputBindings::from_slice(br#"{ "actions": { "jump": [{ "key": "Nope" }] } }"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn bundled_bindings_parse_and_round_trip() {
+        let bindings =
+            InputBindings::from_slice(include_bytes!("../../assets/input_bindings.json")).unwrap();
+        assert!(bindings.actions.contains_key("jump"));
+        assert!(bindings.axes.contains_key("move_x"));
+        assert_eq!(
+            InputBindings::from_slice(&bindings.to_vec().unwrap()).unwrap(),
+            bindings
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::input::gamepad::{
+    GamepadAxis, GamepadBackend, GamepadButton, GamepadEvent, GamepadEventKind, GamepadId,
+};
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+pub struct GilrsBackend {
+    gilrs: Gilrs,
+    announced: bool,
+}
+
+impl GilrsBackend {
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(GilrsBackend {
+                gilrs,
+                announced: false,
+            }),
+            Err(e) => {
+                log::warn!("Gamepads are unavailable: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl GamepadBackend for GilrsBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        // gilrs doesn't send Connected for pads that were plugged in before it started
+        if !self.announced {
+            self.announced = true;
+            for (id, gamepad) in self.gilrs.gamepads() {
+                events.push(GamepadEvent {
+                    id: GamepadId(id.into()),
+                    kind: GamepadEventKind::Connected {
+                        name: gamepad.name().to_string(),
+                    },
+                });
+            }
+        }
+
+        while let Some(event) = self.gilrs.next_event() {
+            let kind = match event.event {
+                EventType::Connected => GamepadEventKind::Connected {
+                    name: self.gilrs.gamepad(event.id).name().to_string(),
+                },
+                EventType::Disconnected => GamepadEventKind::Disconnected,
+                EventType::ButtonPressed(button, _) => match convert_button(button) {
+                    Some(button) => GamepadEventKind::ButtonPressed(button),
+                    None => continue,
+                },
+                EventType::ButtonReleased(button, _) => match convert_button(button) {
+                    Some(button) => GamepadEventKind::ButtonReleased(button),
+                    None => continue,
+                },
+                // Analog triggers are reported as buttons with a value
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    GamepadEventKind::AxisChanged(GamepadAxis::LeftTrigger, value)
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    GamepadEventKind::AxisChanged(GamepadAxis::RightTrigger, value)
+                }
+                EventType::AxisChanged(axis, value, _) => match convert_axis(axis) {
+                    Some(axis) => GamepadEventKind::AxisChanged(axis, value),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            events.push(GamepadEvent {
+                id: GamepadId(event.id.into()),
+                kind,
+            });
+        }
+    }
+}
+
+fn convert_button(button: Button) -> Option<GamepadButton> {
+    let button = match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+fn convert_axis(axis: Axis) -> Option<GamepadAxis> {
+    let axis = match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    };
+    Some(axis)
+}
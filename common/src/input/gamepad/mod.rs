@@ -0,0 +1,428 @@
+#[cfg(not(any(target_arch = "wasm32", target_os = "android", target_os = "ios")))]
+mod desktop;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GamepadId(pub usize);
+
+/// Buttons named after the standard layout; `South` is A on Xbox and Cross on PlayStation pads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Stick axes are in `-1.0..=1.0` with up and right positive; triggers are in `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    fn stick_pair(self) -> Option<(GamepadAxis, GamepadAxis)> {
+        match self {
+            GamepadAxis::LeftStickX | GamepadAxis::LeftStickY => {
+                Some((GamepadAxis::LeftStickX, GamepadAxis::LeftStickY))
+            }
+            GamepadAxis::RightStickX | GamepadAxis::RightStickY => {
+                Some((GamepadAxis::RightStickX, GamepadAxis::RightStickY))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadEventKind {
+    Connected { name: String },
+    Disconnected,
+    ButtonPressed(GamepadButton),
+    ButtonReleased(GamepadButton),
+    AxisChanged(GamepadAxis, f32),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GamepadEvent {
+    pub id: GamepadId,
+    pub kind: GamepadEventKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeadZone {
+    /// Radial dead zone of each stick. Values past it are rescaled to start from 0.
+    pub stick: f32,
+    pub trigger: f32,
+}
+
+impl Default for DeadZone {
+    fn default() -> Self {
+        DeadZone {
+            stick: 0.15f32,
+            trigger: 0.05f32,
+        }
+    }
+}
+
+impl DeadZone {
+    pub fn apply_stick(&self, x: f32, y: f32) -> (f32, f32) {
+        let length = (x * x + y * y).sqrt();
+        if length <= self.stick {
+            return (0.0f32, 0.0f32);
+        }
+        let scale = ((length - self.stick) / (1.0f32 - self.stick)).min(1.0f32) / length;
+        (x * scale, y * scale)
+    }
+
+    pub fn apply_trigger(&self, value: f32) -> f32 {
+        if value <= self.trigger {
+            return 0.0f32;
+        }
+        ((value - self.trigger) / (1.0f32 - self.trigger)).min(1.0f32)
+    }
+}
+
+/// Source of unfiltered gamepad events for a platform.
+trait GamepadBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>);
+}
+
+struct NoneBackend;
+
+impl GamepadBackend for NoneBackend {
+    fn poll(&mut self, _events: &mut Vec<GamepadEvent>) {}
+}
+
+#[derive(Default)]
+struct GamepadState {
+    name: String,
+    buttons: HashSet<GamepadButton>,
+    raw_axes: HashMap<GamepadAxis, f32>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+/// Connected gamepads and their state.
+///
+/// Native gamepads are read through gilrs on desktop and the Gamepad API on the web.
+/// Other platforms have no backend yet and never report a gamepad.
+pub struct Gamepads {
+    backend: Box<dyn GamepadBackend>,
+    pub dead_zone: DeadZone,
+    gamepads: HashMap<GamepadId, GamepadState>,
+    raw_events: Vec<GamepadEvent>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Gamepads::with_backend(create_backend())
+    }
+
+    fn with_backend(backend: Box<dyn GamepadBackend>) -> Self {
+        Gamepads {
+            backend,
+            dead_zone: DeadZone::default(),
+            gamepads: HashMap::new(),
+            raw_events: vec![],
+        }
+    }
+
+    /// Reads pending events from the platform. Call once per frame; the returned events have
+    /// the dead zone applied and should be handed to the scene along with its `WindowEvent`s.
+    pub fn poll(&mut self) -> Vec<GamepadEvent> {
+        let mut raw_events = std::mem::take(&mut self.raw_events);
+        self.backend.poll(&mut raw_events);
+
+        let mut events = vec![];
+        for event in raw_events.drain(..) {
+            self.process(event, &mut events);
+        }
+        self.raw_events = raw_events;
+        events
+    }
+
+    pub fn ids(&self) -> Vec<GamepadId> {
+        let mut ids = self.gamepads.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    pub fn name(&self, id: GamepadId) -> Option<&str> {
+        self.gamepads.get(&id).map(|state| state.name.as_str())
+    }
+
+    pub fn is_pressed(&self, id: GamepadId, button: GamepadButton) -> bool {
+        self.gamepads
+            .get(&id)
+            .map(|state| state.buttons.contains(&button))
+            .unwrap_or(false)
+    }
+
+    pub fn axis(&self, id: GamepadId, axis: GamepadAxis) -> f32 {
+        self.gamepads
+            .get(&id)
+            .and_then(|state| state.axes.get(&axis).copied())
+            .unwrap_or(0.0f32)
+    }
+
+    fn process(&mut self, event: GamepadEvent, events: &mut Vec<GamepadEvent>) {
+        let id = event.id;
+        match event.kind {
+            GamepadEventKind::Connected { name } => {
+                self.gamepads.insert(
+                    id,
+                    GamepadState {
+                        name: name.clone(),
+                        ..GamepadState::default()
+                    },
+                );
+                events.push(GamepadEvent {
+                    id,
+                    kind: GamepadEventKind::Connected { name },
+                });
+            }
+            GamepadEventKind::Disconnected => {
+                if self.gamepads.remove(&id).is_some() {
+                    events.push(event);
+                }
+            }
+            GamepadEventKind::ButtonPressed(button) => {
+                let state = self.gamepads.entry(id).or_default();
+                if state.buttons.insert(button) {
+                    events.push(event);
+                }
+            }
+            GamepadEventKind::ButtonReleased(button) => {
+                let state = self.gamepads.entry(id).or_default();
+                if state.buttons.remove(&button) {
+                    events.push(event);
+                }
+            }
+            GamepadEventKind::AxisChanged(axis, value) => {
+                let dead_zone = self.dead_zone;
+                let state = self.gamepads.entry(id).or_default();
+                state.raw_axes.insert(axis, value);
+
+                let filtered = match axis.stick_pair() {
+                    Some((x_axis, y_axis)) => {
+                        let x = state.raw_axes.get(&x_axis).copied().unwrap_or(0.0f32);
+                        let y = state.raw_axes.get(&y_axis).copied().unwrap_or(0.0f32);
+                        let (x, y) = dead_zone.apply_stick(x, y);
+                        vec![(x_axis, x), (y_axis, y)]
+                    }
+                    None => vec![(axis, dead_zone.apply_trigger(value))],
+                };
+                for (axis, value) in filtered {
+                    let previous = state.axes.insert(axis, value).unwrap_or(0.0f32);
+                    if (previous - value).abs() > std::f32::EPSILON {
+                        events.push(GamepadEvent {
+                            id,
+                            kind: GamepadEventKind::AxisChanged(axis, value),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Gamepads::new()
+    }
+}
+
+thread_local! {
+    // gilrs opens every device it finds, so the app reads them through a single instance
+    static GAMEPADS: RefCell<Option<Rc<RefCell<Gamepads>>>> = RefCell::new(None);
+}
+
+/// Returns the app-wide `Gamepads`, starting the platform backend the first time.
+///
+/// Scenes get it through `GameContext::gamepads`, so pads stay connected across scene transitions.
+pub(crate) fn shared_gamepads() -> Rc<RefCell<Gamepads>> {
+    GAMEPADS.with(|cell| {
+        Rc::clone(
+            cell.borrow_mut()
+                .get_or_insert_with(|| Rc::new(RefCell::new(Gamepads::new()))),
+        )
+    })
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "android", target_os = "ios")))]
+fn create_backend() -> Box<dyn GamepadBackend> {
+    match desktop::GilrsBackend::new() {
+        Some(backend) => Box::new(backend),
+        None => Box::new(NoneBackend),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn create_backend() -> Box<dyn GamepadBackend> {
+    Box::new(web::WebGamepadBackend::new())
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn create_backend() -> Box<dyn GamepadBackend> {
+    Box::new(NoneBackend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct QueuedBackend(Rc<RefCell<Vec<GamepadEvent>>>);
+
+    impl GamepadBackend for QueuedBackend {
+        fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+            events.append(&mut self.0.borrow_mut());
+        }
+    }
+
+    fn gamepads() -> (Gamepads, Rc<RefCell<Vec<GamepadEvent>>>) {
+        let queue = Rc::new(RefCell::new(vec![]));
+        let gamepads = Gamepads::with_backend(Box::new(QueuedBackend(Rc::clone(&queue))));
+        (gamepads, queue)
+    }
+
+    fn event(kind: GamepadEventKind) -> GamepadEvent {
+        GamepadEvent {
+            id: GamepadId(0),
+            kind,
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-6f32,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn stick_dead_zone_is_radial_and_rescaled() {
+        let dead_zone = DeadZone {
+            stick: 0.2f32,
+            trigger: 0.1f32,
+        };
+        assert_eq!(dead_zone.apply_stick(0.1f32, 0.1f32), (0.0f32, 0.0f32));
+
+        // Halfway between the dead zone and the edge along a diagonal
+        let component = 0.6f32 / 2.0f32.sqrt();
+        let (x, y) = dead_zone.apply_stick(component, component);
+        assert_close(x, 0.5f32 / 2.0f32.sqrt());
+        assert_close(y, 0.5f32 / 2.0f32.sqrt());
+
+        // Corners of a square gate are clamped to the unit circle
+        let (x, y) = dead_zone.apply_stick(1.0f32, 1.0f32);
+        assert_close((x * x + y * y).sqrt(), 1.0f32);
+        assert_close(x, y);
+    }
+
+    #[test]
+    fn trigger_dead_zone_is_rescaled() {
+        let dead_zone = DeadZone {
+            stick: 0.2f32,
+            trigger: 0.1f32,
+        };
+        assert_close(dead_zone.apply_trigger(0.05f32), 0.0f32);
+        assert_close(dead_zone.apply_trigger(0.55f32), 0.5f32);
+        assert_close(dead_zone.apply_trigger(1.0f32), 1.0f32);
+    }
+
+    #[test]
+    fn repeated_presses_and_releases_are_dropped() {
+        let (mut gamepads, queue) = gamepads();
+        let press = event(GamepadEventKind::ButtonPressed(GamepadButton::South));
+        let release = event(GamepadEventKind::ButtonReleased(GamepadButton::South));
+        queue.borrow_mut().extend(vec![
+            event(GamepadEventKind::Connected {
+                name: "Pad".to_string(),
+            }),
+            release.clone(),
+            press.clone(),
+            press.clone(),
+        ]);
+
+        let events = gamepads.poll();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1], press);
+        assert!(gamepads.is_pressed(GamepadId(0), GamepadButton::South));
+
+        queue
+            .borrow_mut()
+            .extend(vec![press.clone(), release.clone(), release.clone()]);
+        assert_eq!(gamepads.poll(), vec![release]);
+        assert!(!gamepads.is_pressed(GamepadId(0), GamepadButton::South));
+    }
+
+    #[test]
+    fn stick_axes_are_filtered_as_a_pair() {
+        let (mut gamepads, queue) = gamepads();
+        gamepads.dead_zone = DeadZone {
+            stick: 0.5f32,
+            trigger: 0.0f32,
+        };
+
+        // On its own, X stays inside the dead zone
+        queue.borrow_mut().push(event(GamepadEventKind::AxisChanged(
+            GamepadAxis::LeftStickX,
+            0.4f32,
+        )));
+        assert!(gamepads.poll().is_empty());
+
+        // Moving Y takes the stick out of it, so X is sent along with Y
+        queue.borrow_mut().push(event(GamepadEventKind::AxisChanged(
+            GamepadAxis::LeftStickY,
+            0.6f32,
+        )));
+        let events = gamepads.poll();
+        let axes = events
+            .iter()
+            .map(|event| match event.kind {
+                GamepadEventKind::AxisChanged(axis, _) => axis,
+                _ => panic!("unexpected event {:?}", event),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(axes, vec![GamepadAxis::LeftStickX, GamepadAxis::LeftStickY]);
+        assert!(gamepads.axis(GamepadId(0), GamepadAxis::LeftStickX) > 0.0f32);
+        assert!(gamepads.axis(GamepadId(0), GamepadAxis::LeftStickY) > 0.0f32);
+
+        // Going back into the dead zone zeroes both axes
+        queue.borrow_mut().push(event(GamepadEventKind::AxisChanged(
+            GamepadAxis::LeftStickY,
+            0.0f32,
+        )));
+        let events = gamepads.poll();
+        assert_eq!(events.len(), 2);
+        assert_close(gamepads.axis(GamepadId(0), GamepadAxis::LeftStickX), 0.0f32);
+        assert_close(gamepads.axis(GamepadId(0), GamepadAxis::LeftStickY), 0.0f32);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::input::gamepad::{
+    GamepadAxis, GamepadBackend, GamepadButton, GamepadEvent, GamepadEventKind, GamepadId,
+};
+use std::collections::HashMap;
+use wasm_bindgen::JsCast;
+
+// Button order of the "standard" mapping: https://w3c.github.io/gamepad/#remapping
+const STANDARD_BUTTONS: [GamepadButton; 17] = [
+    GamepadButton::South,
+    GamepadButton::East,
+    GamepadButton::West,
+    GamepadButton::North,
+    GamepadButton::LeftBumper,
+    GamepadButton::RightBumper,
+    GamepadButton::LeftTrigger,
+    GamepadButton::RightTrigger,
+    GamepadButton::Select,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+    GamepadButton::Mode,
+];
+
+const STANDARD_AXES: [GamepadAxis; 4] = [
+    GamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY,
+    GamepadAxis::RightStickX,
+    GamepadAxis::RightStickY,
+];
+
+#[derive(Default)]
+struct Snapshot {
+    buttons: Vec<bool>,
+    axes: Vec<f32>,
+    triggers: [f32; 2],
+}
+
+/// The Gamepad API has no events for button and axis changes, so every `poll` compares
+/// `navigator.getGamepads()` with the previous snapshot.
+pub struct WebGamepadBackend {
+    snapshots: HashMap<u32, Snapshot>,
+}
+
+impl WebGamepadBackend {
+    pub fn new() -> Self {
+        WebGamepadBackend {
+            snapshots: HashMap::new(),
+        }
+    }
+}
+
+impl GamepadBackend for WebGamepadBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+        let gamepads =
+            match web_sys::window().and_then(|window| window.navigator().get_gamepads().ok()) {
+                Some(gamepads) => gamepads,
+                None => return,
+            };
+
+        let mut connected = vec![];
+        for value in gamepads.iter() {
+            let gamepad = match value.dyn_into::<web_sys::Gamepad>() {
+                Ok(gamepad) if gamepad.connected() => gamepad,
+                _ => continue,
+            };
+            let index = gamepad.index();
+            let id = GamepadId(index as usize);
+            connected.push(index);
+
+            let previous = match self.snapshots.remove(&index) {
+                Some(previous) => previous,
+                None => {
+                    events.push(GamepadEvent {
+                        id,
+                        kind: GamepadEventKind::Connected { name: gamepad.id() },
+                    });
+                    Snapshot::default()
+                }
+            };
+            let snapshot = read_snapshot(&gamepad);
+
+            for (i, button) in STANDARD_BUTTONS.iter().enumerate() {
+                let was_pressed = previous.buttons.get(i).copied().unwrap_or(false);
+                let pressed = snapshot.buttons.get(i).copied().unwrap_or(false);
+                if pressed != was_pressed {
+                    let kind = if pressed {
+                        GamepadEventKind::ButtonPressed(*button)
+                    } else {
+                        GamepadEventKind::ButtonReleased(*button)
+                    };
+                    events.push(GamepadEvent { id, kind });
+                }
+            }
+            for (i, axis) in STANDARD_AXES.iter().enumerate() {
+                let value = snapshot.axes.get(i).copied().unwrap_or(0.0f32);
+                if previous.axes.get(i).copied().unwrap_or(0.0f32) != value {
+                    events.push(GamepadEvent {
+                        id,
+                        kind: GamepadEventKind::AxisChanged(*axis, value),
+                    });
+                }
+            }
+            let triggers = [GamepadAxis::LeftTrigger, GamepadAxis::RightTrigger];
+            for (i, axis) in triggers.iter().enumerate() {
+                if previous.triggers[i] != snapshot.triggers[i] {
+                    events.push(GamepadEvent {
+                        id,
+                        kind: GamepadEventKind::AxisChanged(*axis, snapshot.triggers[i]),
+                    });
+                }
+            }
+
+            self.snapshots.insert(index, snapshot);
+        }
+
+        let disconnected = self
+            .snapshots
+            .keys()
+            .filter(|index| !connected.contains(index))
+            .copied()
+            .collect::<Vec<_>>();
+        for index in disconnected {
+            self.snapshots.remove(&index);
+            events.push(GamepadEvent {
+                id: GamepadId(index as usize),
+                kind: GamepadEventKind::Disconnected,
+            });
+        }
+    }
+}
+
+fn read_snapshot(gamepad: &web_sys::Gamepad) -> Snapshot {
+    let buttons = gamepad
+        .buttons()
+        .iter()
+        .filter_map(|value| value.dyn_into::<web_sys::GamepadButton>().ok())
+        .collect::<Vec<_>>();
+    let trigger = |index: usize| {
+        buttons
+            .get(index)
+            .map(|b| b.value() as f32)
+            .unwrap_or(0.0f32)
+    };
+    let triggers = [trigger(6), trigger(7)];
+
+    let mut axes = gamepad
+        .axes()
+        .iter()
+        .map(|value| value.as_f64().unwrap_or(0.0) as f32)
+        .collect::<Vec<_>>();
+    // The Gamepad API reports down as positive Y
+    for i in [1, 3].iter() {
+        if let Some(value) = axes.get_mut(*i) {
+            *value = -*value;
+        }
+    }
+
+    Snapshot {
+        buttons: buttons.iter().map(|button| button.pressed()).collect(),
+        axes,
+        triggers,
+    }
+}
@@ -0,0 +1,487 @@
+pub mod bindings;
+pub mod gamepad;
+pub mod record;
+
+use crate::input::bindings::{load_input_bindings, AxisBinding, Binding, InputBindings, MouseAxis};
+use crate::input::gamepad::{
+    GamepadAxis, GamepadButton, GamepadEvent, GamepadEventKind, GamepadId,
+};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use winit::dpi::PhysicalPosition;
+use winit::event::{
+    ElementState, KeyboardInput, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode,
+    WindowEvent,
+};
+
+/// Raw keyboard, mouse, touch and gamepad state accumulated from `WindowEvent`s and `GamepadEvent`s.
+///
+/// "Just pressed/released" flags and motion deltas cover everything since the last `end_frame`.
+#[derive(Default)]
+pub struct InputState {
+    keys: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+    mouse_buttons: HashSet<MouseButton>,
+    mouse_buttons_pressed: HashSet<MouseButton>,
+    mouse_buttons_released: HashSet<MouseButton>,
+    cursor: Option<PhysicalPosition<f64>>,
+    cursor_delta: (f32, f32),
+    wheel_delta: f32,
+    touches: HashMap<u64, PhysicalPosition<f64>>,
+    touches_started: usize,
+    touches_ended: usize,
+    gamepad_buttons: HashSet<(GamepadId, GamepadButton)>,
+    gamepad_buttons_pressed: HashSet<GamepadButton>,
+    gamepad_buttons_released: HashSet<GamepadButton>,
+    gamepad_axes: HashMap<(GamepadId, GamepadAxis), f32>,
+}
+
+impl InputState {
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match state {
+                // Key repeat sends Pressed again without a Released in between
+                ElementState::Pressed => {
+                    if self.keys.insert(*keycode) {
+                        self.keys_pressed.insert(*keycode);
+                    }
+                }
+                ElementState::Released => {
+                    if self.keys.remove(keycode) {
+                        self.keys_released.insert(*keycode);
+                    }
+                }
+            },
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.mouse_buttons.insert(*button);
+                    self.mouse_buttons_pressed.insert(*button);
+                }
+                ElementState::Released => {
+                    self.mouse_buttons.remove(button);
+                    self.mouse_buttons_released.insert(*button);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(last) = self.cursor.replace(*position) {
+                    self.cursor_delta.0 += (position.x - last.x) as f32;
+                    self.cursor_delta.1 += (position.y - last.y) as f32;
+                }
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.wheel_delta += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0f32,
+                };
+            }
+            WindowEvent::Touch(touch) => match touch.phase {
+                TouchPhase::Started => {
+                    self.touches.insert(touch.id, touch.location);
+                    self.touches_started += 1;
+                }
+                TouchPhase::Moved => {
+                    self.touches.insert(touch.id, touch.location);
+                }
+                TouchPhase::Ended | TouchPhase::Cancelled => {
+                    if self.touches.remove(&touch.id).is_some() {
+                        self.touches_ended += 1;
+                    }
+                }
+            },
+            WindowEvent::Focused(false) => self.release_all(),
+            _ => {}
+        }
+    }
+
+    pub fn handle_gamepad_event(&mut self, event: &GamepadEvent) {
+        match &event.kind {
+            GamepadEventKind::Connected { .. } => {}
+            GamepadEventKind::Disconnected => {
+                let released = self
+                    .gamepad_buttons
+                    .iter()
+                    .filter(|(id, _)| *id == event.id)
+                    .copied()
+                    .collect::<Vec<_>>();
+                for (id, button) in released {
+                    self.gamepad_buttons.remove(&(id, button));
+                    self.gamepad_buttons_released.insert(button);
+                }
+                self.gamepad_axes.retain(|(id, _), _| *id != event.id);
+            }
+            GamepadEventKind::ButtonPressed(button) => {
+                self.gamepad_buttons.insert((event.id, *button));
+                self.gamepad_buttons_pressed.insert(*button);
+            }
+            GamepadEventKind::ButtonReleased(button) => {
+                self.gamepad_buttons.remove(&(event.id, *button));
+                self.gamepad_buttons_released.insert(*button);
+            }
+            GamepadEventKind::AxisChanged(axis, value) => {
+                self.gamepad_axes.insert((event.id, *axis), *value);
+            }
+        }
+    }
+
+    /// Clears per-frame state. Call at the end of every `Scene::render`.
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.mouse_buttons_pressed.clear();
+        self.mouse_buttons_released.clear();
+        self.cursor_delta = (0.0f32, 0.0f32);
+        self.wheel_delta = 0.0f32;
+        self.touches_started = 0;
+        self.touches_ended = 0;
+        self.gamepad_buttons_pressed.clear();
+        self.gamepad_buttons_released.clear();
+    }
+
+    pub fn is_key_down(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys.contains(&keycode)
+    }
+
+    pub fn is_key_just_pressed(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&keycode)
+    }
+
+    pub fn is_key_just_released(&self, keycode: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&keycode)
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.contains(&button)
+    }
+
+    pub fn cursor(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor
+    }
+
+    pub fn cursor_delta(&self) -> (f32, f32) {
+        self.cursor_delta
+    }
+
+    pub fn wheel_delta(&self) -> f32 {
+        self.wheel_delta
+    }
+
+    pub fn touches(&self) -> &HashMap<u64, PhysicalPosition<f64>> {
+        &self.touches
+    }
+
+    pub fn is_down(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(keycode) => self.keys.contains(keycode),
+            Binding::Mouse(button) => self.mouse_buttons.contains(button),
+            Binding::Gamepad(button) => self
+                .gamepad_buttons
+                .iter()
+                .any(|(_, pressed)| pressed == button),
+            Binding::Touch => !self.touches.is_empty(),
+        }
+    }
+
+    pub fn is_just_pressed(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(keycode) => self.keys_pressed.contains(keycode),
+            Binding::Mouse(button) => self.mouse_buttons_pressed.contains(button),
+            Binding::Gamepad(button) => self.gamepad_buttons_pressed.contains(button),
+            Binding::Touch => self.touches_started > 0,
+        }
+    }
+
+    pub fn is_just_released(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(keycode) => self.keys_released.contains(keycode),
+            Binding::Mouse(button) => self.mouse_buttons_released.contains(button),
+            Binding::Gamepad(button) => self.gamepad_buttons_released.contains(button),
+            Binding::Touch => self.touches_ended > 0,
+        }
+    }
+
+    pub fn axis_value(&self, binding: &AxisBinding) -> f32 {
+        match binding {
+            AxisBinding::Buttons { negative, positive } => {
+                let mut value = 0.0f32;
+                if self.is_down(negative) {
+                    value -= 1.0f32;
+                }
+                if self.is_down(positive) {
+                    value += 1.0f32;
+                }
+                value
+            }
+            AxisBinding::Mouse { axis, scale } => {
+                let value = match axis {
+                    MouseAxis::X => self.cursor_delta.0,
+                    MouseAxis::Y => self.cursor_delta.1,
+                    MouseAxis::Wheel => self.wheel_delta,
+                };
+                value * scale
+            }
+            AxisBinding::Gamepad { axis, scale } => {
+                // The pad pushed furthest wins
+                let value = self
+                    .gamepad_axes
+                    .iter()
+                    .filter(|((_, a), _)| a == axis)
+                    .map(|(_, value)| *value)
+                    .fold(0.0f32, |a, b| if b.abs() > a.abs() { b } else { a });
+                value * scale
+            }
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.keys_released.extend(self.keys.drain());
+        self.mouse_buttons_released
+            .extend(self.mouse_buttons.drain());
+        self.touches_ended += self.touches.len();
+        self.touches.clear();
+    }
+}
+
+/// Maps raw input to the named actions and axes of an `InputBindings`.
+///
+/// Events come in through `handle_event` from `Scene::update`; queries are made during `Scene::render`,
+/// which then calls `end_frame`.
+pub struct InputMap {
+    bindings: InputBindings,
+    state: InputState,
+    // Filled by `load_bindings` and picked up by `end_frame`
+    loaded: Arc<Mutex<Option<InputBindings>>>,
+}
+
+impl InputMap {
+    pub fn new(bindings: InputBindings) -> Self {
+        InputMap {
+            bindings,
+            state: InputState::default(),
+            loaded: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Reads bindings through `tearchan::fs`; they replace the current ones at the next
+    /// `end_frame`. Spawn the returned future, e.g. with `context.spawner().spawn_local`.
+    pub fn load_bindings<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = ()> {
+        let loaded = self.loaded.clone();
+        let path = path.as_ref().to_path_buf();
+        async move {
+            match load_input_bindings(path).await {
+                Ok(bindings) => *loaded.lock().unwrap() = Some(bindings),
+                Err(e) => log::error!("{}", e),
+            }
+        }
+    }
+
+    pub fn bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+
+    pub fn set_bindings(&mut self, bindings: InputBindings) {
+        self.bindings = bindings;
+    }
+
+    pub fn state(&self) -> &InputState {
+        &self.state
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        self.state.handle_event(event);
+    }
+
+    pub fn handle_gamepad_event(&mut self, event: &GamepadEvent) {
+        self.state.handle_gamepad_event(event);
+    }
+
+    pub fn end_frame(&mut self) {
+        self.state.end_frame();
+        if let Some(bindings) = self.loaded.lock().unwrap().take() {
+            self.bindings = bindings;
+        }
+    }
+
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| self.state.is_down(binding))
+    }
+
+    pub fn is_just_pressed(&self, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| self.state.is_just_pressed(binding))
+    }
+
+    pub fn is_just_released(&self, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| self.state.is_just_released(binding))
+    }
+
+    /// Sum of all bindings of `axis`, clamped to `-1.0..=1.0` except for mouse motion.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let bindings = match self.bindings.axes.get(axis) {
+            Some(bindings) => bindings,
+            None => return 0.0f32,
+        };
+        let mut buttons = 0.0f32;
+        let mut motion = 0.0f32;
+        for binding in bindings {
+            match binding {
+                AxisBinding::Buttons { .. } | AxisBinding::Gamepad { .. } => {
+                    buttons += self.state.axis_value(binding)
+                }
+                AxisBinding::Mouse { .. } => motion += self.state.axis_value(binding),
+            }
+        }
+        buttons.max(-1.0f32).min(1.0f32) + motion
+    }
+
+    fn action_bindings(&self, action: &str) -> &[Binding] {
+        self.bindings
+            .actions
+            .get(action)
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::record::RecordedEvent;
+
+    fn bindings() -> InputBindings {
+        InputBindings::from_slice(
+            br#"{
+                "actions": { "jump": [{ "key": "Space" }, { "gamepad": "South" }, "touch"] },
+                "axes": {
+                    "move_x": [
+                        { "buttons": { "negative": { "key": "A" }, "positive": { "key": "D" } } },
+                        { "buttons": { "negative": { "key": "Left" }, "positive": { "key": "Right" } } },
+                        { "gamepad": { "axis": "LeftStickX", "scale": 1.0 } }
+                    ],
+                    "zoom": [{ "mouse": { "axis": "wheel", "scale": 2.0 } }]
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn key(input: &mut InputMap, keycode: VirtualKeyCode, state: ElementState) {
+        let event = RecordedEvent::KeyboardInput {
+            scancode: 0,
+            state,
+            keycode: Some(keycode),
+        };
+        input.handle_event(&event.to_window_event());
+    }
+
+    fn gamepad(input: &mut InputMap, kind: GamepadEventKind) {
+        input.handle_gamepad_event(&GamepadEvent {
+            id: GamepadId(0),
+            kind,
+        });
+    }
+
+    #[test]
+    fn actions_follow_their_keys_across_frames() {
+        let mut input = InputMap::new(bindings());
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        assert!(input.is_pressed("jump"));
+        assert!(input.is_just_pressed("jump"));
+
+        input.end_frame();
+        // Key repeat doesn't count as a new press
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        assert!(input.is_pressed("jump"));
+        assert!(!input.is_just_pressed("jump"));
+
+        key(&mut input, VirtualKeyCode::Space, ElementState::Released);
+        assert!(!input.is_pressed("jump"));
+        assert!(input.is_just_released("jump"));
+        input.end_frame();
+        assert!(!input.is_just_released("jump"));
+    }
+
+    #[test]
+    fn any_binding_triggers_an_action() {
+        let mut input = InputMap::new(bindings());
+        gamepad(
+            &mut input,
+            GamepadEventKind::ButtonPressed(GamepadButton::South),
+        );
+        assert!(input.is_just_pressed("jump"));
+
+        let mut input = InputMap::new(bindings());
+        let touch = RecordedEvent::Touch {
+            id: 1,
+            phase: TouchPhase::Started,
+            x: 0.0,
+            y: 0.0,
+        };
+        input.handle_event(&touch.to_window_event());
+        assert!(input.is_pressed("jump"));
+    }
+
+    #[test]
+    fn unknown_actions_and_axes_are_idle() {
+        let mut input = InputMap::new(bindings());
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        assert!(!input.is_pressed("fire"));
+        assert_eq!(input.axis("move_y"), 0.0f32);
+    }
+
+    #[test]
+    fn button_axes_sum_and_clamp() {
+        let mut input = InputMap::new(bindings());
+        key(&mut input, VirtualKeyCode::D, ElementState::Pressed);
+        assert_eq!(input.axis("move_x"), 1.0f32);
+        key(&mut input, VirtualKeyCode::Right, ElementState::Pressed);
+        assert_eq!(input.axis("move_x"), 1.0f32);
+        key(&mut input, VirtualKeyCode::A, ElementState::Pressed);
+        assert_eq!(input.axis("move_x"), 1.0f32);
+        key(&mut input, VirtualKeyCode::Right, ElementState::Released);
+        assert_eq!(input.axis("move_x"), 0.0f32);
+        gamepad(
+            &mut input,
+            GamepadEventKind::AxisChanged(GamepadAxis::LeftStickX, -0.5f32),
+        );
+        assert_eq!(input.axis("move_x"), -0.5f32);
+    }
+
+    #[test]
+    fn mouse_axes_are_scaled_and_reset_every_frame() {
+        let mut input = InputMap::new(bindings());
+        let wheel = RecordedEvent::MouseWheel {
+            delta: MouseScrollDelta::LineDelta(0.0f32, 3.0f32),
+            phase: TouchPhase::Moved,
+        };
+        input.handle_event(&wheel.to_window_event());
+        assert_eq!(input.axis("zoom"), 6.0f32);
+        input.end_frame();
+        assert_eq!(input.axis("zoom"), 0.0f32);
+    }
+
+    #[test]
+    fn focus_loss_releases_held_keys() {
+        let mut input = InputMap::new(bindings());
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        input.handle_event(&RecordedEvent::Focused(false).to_window_event());
+        assert!(!input.is_pressed("jump"));
+        assert!(input.is_just_released("jump"));
+    }
+}
@@ -0,0 +1,571 @@
+use crate::input::gamepad::GamepadEvent;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{
+    DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, Touch,
+    TouchPhase, VirtualKeyCode, WindowEvent,
+};
+
+/// Version 2 added gamepad events; version 1 recordings are still replayed.
+pub const INPUT_RECORDING_VERSION: u32 = 2;
+
+/// The subset of `WindowEvent` that scenes react to, in a serializable form.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedEvent {
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    Focused(bool),
+    ReceivedCharacter(char),
+    KeyboardInput {
+        scancode: u32,
+        state: ElementState,
+        keycode: Option<VirtualKeyCode>,
+    },
+    ModifiersChanged(ModifiersState),
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorEntered,
+    CursorLeft,
+    MouseWheel {
+        delta: MouseScrollDelta,
+        phase: TouchPhase,
+    },
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        x: f64,
+        y: f64,
+    },
+}
+
+impl RecordedEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        let event = match event {
+            WindowEvent::Resized(size) => RecordedEvent::Resized {
+                width: size.width,
+                height: size.height,
+            },
+            WindowEvent::Focused(focused) => RecordedEvent::Focused(*focused),
+            WindowEvent::ReceivedCharacter(c) => RecordedEvent::ReceivedCharacter(*c),
+            WindowEvent::KeyboardInput { input, .. } => RecordedEvent::KeyboardInput {
+                scancode: input.scancode,
+                state: input.state,
+                keycode: input.virtual_keycode,
+            },
+            WindowEvent::ModifiersChanged(modifiers) => RecordedEvent::ModifiersChanged(*modifiers),
+            WindowEvent::CursorMoved { position, .. } => RecordedEvent::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::CursorEntered { .. } => RecordedEvent::CursorEntered,
+            WindowEvent::CursorLeft { .. } => RecordedEvent::CursorLeft,
+            WindowEvent::MouseWheel { delta, phase, .. } => RecordedEvent::MouseWheel {
+                delta: *delta,
+                phase: *phase,
+            },
+            WindowEvent::MouseInput { state, button, .. } => RecordedEvent::MouseInput {
+                state: *state,
+                button: *button,
+            },
+            WindowEvent::Touch(touch) => RecordedEvent::Touch {
+                id: touch.id,
+                phase: touch.phase,
+                x: touch.location.x,
+                y: touch.location.y,
+            },
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    #[allow(deprecated)]
+    pub fn to_window_event(&self) -> WindowEvent<'static> {
+        // Scenes never compare device ids, so every replayed event uses the same placeholder
+        let device_id = unsafe { DeviceId::dummy() };
+        let modifiers = ModifiersState::empty();
+        match self {
+            RecordedEvent::Resized { width, height } => {
+                WindowEvent::Resized(PhysicalSize::new(*width, *height))
+            }
+            RecordedEvent::Focused(focused) => WindowEvent::Focused(*focused),
+            RecordedEvent::ReceivedCharacter(c) => WindowEvent::ReceivedCharacter(*c),
+            RecordedEvent::KeyboardInput {
+                scancode,
+                state,
+                keycode,
+            } => WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode: *scancode,
+                    state: *state,
+                    virtual_keycode: *keycode,
+                    modifiers,
+                },
+                is_synthetic: false,
+            },
+            RecordedEvent::ModifiersChanged(modifiers) => WindowEvent::ModifiersChanged(*modifiers),
+            RecordedEvent::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(*x, *y),
+                modifiers,
+            },
+            RecordedEvent::CursorEntered => WindowEvent::CursorEntered { device_id },
+            RecordedEvent::CursorLeft => WindowEvent::CursorLeft { device_id },
+            RecordedEvent::MouseWheel { delta, phase } => WindowEvent::MouseWheel {
+                device_id,
+                delta: *delta,
+                phase: *phase,
+                modifiers,
+            },
+            RecordedEvent::MouseInput { state, button } => WindowEvent::MouseInput {
+                device_id,
+                state: *state,
+                button: *button,
+                modifiers,
+            },
+            RecordedEvent::Touch { id, phase, x, y } => WindowEvent::Touch(Touch {
+                device_id,
+                phase: *phase,
+                location: PhysicalPosition::new(*x, *y),
+                force: None,
+                id: *id,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub version: u32,
+    pub fixed_delta: Option<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub index: u64,
+    pub delta: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<RecordedEvent>,
+    /// Delivered after `events`, with dead zones already applied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gamepad_events: Vec<GamepadEvent>,
+}
+
+/// Writes a recording as JSON lines: a `RecordingHeader` followed by one `RecordedFrame` per frame.
+///
+/// Every frame is flushed as soon as it ends, because the event loop exits the process
+/// without dropping the scene.
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    fixed_delta: Option<f32>,
+    frame: RecordedFrame,
+}
+
+impl InputRecorder {
+    pub fn create<P: AsRef<Path>>(path: P, fixed_delta: Option<f32>) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = RecordingHeader {
+            version: INPUT_RECORDING_VERSION,
+            fixed_delta,
+        };
+        serde_json::to_writer(&mut writer, &header)?;
+        writeln!(writer)?;
+        Ok(InputRecorder {
+            writer,
+            fixed_delta,
+            frame: RecordedFrame {
+                index: 0,
+                delta: 0.0f32,
+                events: vec![],
+                gamepad_events: vec![],
+            },
+        })
+    }
+
+    pub fn record_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = RecordedEvent::from_window_event(event) {
+            self.frame.events.push(event);
+        }
+    }
+
+    pub fn record_gamepad_event(&mut self, event: &GamepadEvent) {
+        self.frame.gamepad_events.push(event.clone());
+    }
+
+    /// Stores the events recorded since the previous frame together with the delta used for this one.
+    pub fn end_frame(&mut self, delta: f32) -> std::io::Result<()> {
+        self.frame.delta = delta;
+        serde_json::to_writer(&mut self.writer, &self.frame)?;
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        self.frame.index += 1;
+        self.frame.events.clear();
+        self.frame.gamepad_events.clear();
+        Ok(())
+    }
+}
+
+pub struct InputPlayer {
+    header: RecordingHeader,
+    frames: std::vec::IntoIter<RecordedFrame>,
+}
+
+impl InputPlayer {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header: RecordingHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "the recording is empty",
+                ))
+            }
+        };
+        if header.version == 0 || header.version > INPUT_RECORDING_VERSION {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unsupported recording version {}", header.version),
+            ));
+        }
+
+        let mut frames = vec![];
+        for line in lines {
+            let line = line?;
+            // The last line may be cut off if the recording process was killed
+            match serde_json::from_str::<RecordedFrame>(&line) {
+                Ok(frame) => {
+                    // A skipped or repeated frame would silently desynchronize the replay
+                    let expected = frames.len() as u64;
+                    if frame.index != expected {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("expected frame {}, found frame {}", expected, frame.index),
+                        ));
+                    }
+                    frames.push(frame);
+                }
+                Err(e) => {
+                    log::warn!("Ignoring the rest of the recording: {}", e);
+                    break;
+                }
+            }
+        }
+        Ok(InputPlayer {
+            header,
+            frames: frames.into_iter(),
+        })
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.next()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum InputSessionConfig {
+    Live,
+    Record {
+        path: PathBuf,
+        fixed_delta: Option<f32>,
+    },
+    Replay {
+        path: PathBuf,
+    },
+}
+
+impl Default for InputSessionConfig {
+    fn default() -> Self {
+        InputSessionConfig::Live
+    }
+}
+
+thread_local! {
+    static SESSION_CONFIG: RefCell<InputSessionConfig> = RefCell::new(InputSessionConfig::Live);
+    static SESSION: RefCell<Option<Rc<RefCell<InputSession>>>> = RefCell::new(None);
+}
+
+/// Selects how the app receives input. Call before `launch_app`; the session is created by the
+/// first scene and kept for the rest of the app.
+pub fn configure_input_session(config: InputSessionConfig) {
+    SESSION_CONFIG.with(|session| *session.borrow_mut() = config);
+}
+
+/// Returns the app-wide `InputSession`, creating it from the config given to
+/// `configure_input_session` the first time.
+///
+/// Scenes share it through `GameContext`, so a recording or replay carries on across scene
+/// transitions instead of starting over with each scene.
+pub(crate) fn shared_input_session() -> Rc<RefCell<InputSession>> {
+    SESSION.with(|cell| {
+        Rc::clone(cell.borrow_mut().get_or_insert_with(|| {
+            let session = SESSION_CONFIG.with(|config| InputSession::new(&config.borrow()));
+            Rc::new(RefCell::new(session))
+        }))
+    })
+}
+
+pub struct InputFrame {
+    pub delta: f32,
+    /// Replayed events to handle before the frame is simulated.
+    pub events: Vec<WindowEvent<'static>>,
+    /// Gamepad events to handle after `events`, live or replayed.
+    pub gamepad_events: Vec<GamepadEvent>,
+}
+
+enum InputSessionMode {
+    Live,
+    Recording(InputRecorder),
+    Replaying(InputPlayer),
+}
+
+/// Sits between the event loop and a scene to record or replay its input.
+///
+/// Every event from `Scene::update` goes through `filter_event`, and every `Scene::render`
+/// starts with `begin_frame`, which takes the frame's gamepad events, decides the frame delta
+/// and returns the events to handle.
+pub struct InputSession {
+    mode: InputSessionMode,
+}
+
+impl InputSession {
+    pub fn new(config: &InputSessionConfig) -> Self {
+        let mode = match config {
+            InputSessionConfig::Live => InputSessionMode::Live,
+            InputSessionConfig::Record { path, fixed_delta } => {
+                match InputRecorder::create(path, *fixed_delta) {
+                    Ok(recorder) => {
+                        log::info!("Recording input to {:?}", path);
+                        InputSessionMode::Recording(recorder)
+                    }
+                    Err(e) => {
+                        log::error!("Failed to create {:?}: {}", path, e);
+                        InputSessionMode::Live
+                    }
+                }
+            }
+            InputSessionConfig::Replay { path } => match InputPlayer::open(path) {
+                Ok(player) => {
+                    log::info!("Replaying input from {:?}", path);
+                    InputSessionMode::Replaying(player)
+                }
+                Err(e) => {
+                    log::error!("Failed to open {:?}: {}", path, e);
+                    InputSessionMode::Live
+                }
+            },
+        };
+        InputSession { mode }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self.mode, InputSessionMode::Replaying(_))
+    }
+
+    /// Returns the event if the scene should handle it now.
+    /// While replaying, live input is dropped so it can't interfere with the recording.
+    pub fn filter_event<'a>(&mut self, event: WindowEvent<'a>) -> Option<WindowEvent<'a>> {
+        match &mut self.mode {
+            InputSessionMode::Live => Some(event),
+            InputSessionMode::Recording(recorder) => {
+                recorder.record_event(&event);
+                Some(event)
+            }
+            InputSessionMode::Replaying(_) => match RecordedEvent::from_window_event(&event) {
+                Some(_) => None,
+                None => Some(event),
+            },
+        }
+    }
+
+    /// `gamepad_events` are the live ones polled for this frame; they are dropped while replaying.
+    pub fn begin_frame(
+        &mut self,
+        measured_delta: f32,
+        gamepad_events: Vec<GamepadEvent>,
+    ) -> InputFrame {
+        let frame = match &mut self.mode {
+            InputSessionMode::Live => None,
+            InputSessionMode::Recording(recorder) => {
+                for event in &gamepad_events {
+                    recorder.record_gamepad_event(event);
+                }
+                let delta = recorder.fixed_delta.unwrap_or(measured_delta);
+                if let Err(e) = recorder.end_frame(delta) {
+                    log::error!("Stopped recording input: {}", e);
+                    self.mode = InputSessionMode::Live;
+                }
+                return InputFrame {
+                    delta,
+                    events: vec![],
+                    gamepad_events,
+                };
+            }
+            InputSessionMode::Replaying(player) => player.next_frame(),
+        };
+
+        match frame {
+            Some(frame) => InputFrame {
+                delta: frame.delta,
+                events: frame.events.iter().map(|e| e.to_window_event()).collect(),
+                gamepad_events: frame.gamepad_events,
+            },
+            None => {
+                let gamepad_events = if self.is_replaying() {
+                    log::info!("Replay finished, switching to live input");
+                    self.mode = InputSessionMode::Live;
+                    vec![]
+                } else {
+                    gamepad_events
+                };
+                InputFrame {
+                    delta: measured_delta,
+                    events: vec![],
+                    gamepad_events,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::gamepad::{GamepadButton, GamepadEventKind, GamepadId};
+
+    fn recording_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("input-{}-{}.jsonl", name, std::process::id()))
+    }
+
+    fn key(state: ElementState) -> RecordedEvent {
+        RecordedEvent::KeyboardInput {
+            scancode: 57,
+            state,
+            keycode: Some(VirtualKeyCode::Space),
+        }
+    }
+
+    fn button(kind: GamepadEventKind) -> GamepadEvent {
+        GamepadEvent {
+            id: GamepadId(0),
+            kind,
+        }
+    }
+
+    fn recorded(frame: &InputFrame) -> Vec<RecordedEvent> {
+        frame
+            .events
+            .iter()
+            .filter_map(RecordedEvent::from_window_event)
+            .collect()
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let path = recording_path("round-trip");
+        let pressed = button(GamepadEventKind::ButtonPressed(GamepadButton::South));
+        {
+            let mut session = InputSession::new(&InputSessionConfig::Record {
+                path: path.clone(),
+                fixed_delta: Some(0.5f32),
+            });
+            assert!(session
+                .filter_event(key(ElementState::Pressed).to_window_event())
+                .is_some());
+            let frame = session.begin_frame(0.1f32, vec![pressed.clone()]);
+            assert_eq!(frame.delta, 0.5f32);
+            assert_eq!(frame.gamepad_events, vec![pressed.clone()]);
+
+            session.filter_event(key(ElementState::Released).to_window_event());
+            session.begin_frame(0.2f32, vec![]);
+        }
+
+        let mut session = InputSession::new(&InputSessionConfig::Replay { path: path.clone() });
+        assert!(session.is_replaying());
+        // Live input is dropped while replaying
+        assert!(session
+            .filter_event(key(ElementState::Pressed).to_window_event())
+            .is_none());
+        let live = button(GamepadEventKind::ButtonPressed(GamepadButton::North));
+
+        let frame = session.begin_frame(0.1f32, vec![live.clone()]);
+        assert_eq!(frame.delta, 0.5f32);
+        assert_eq!(recorded(&frame), vec![key(ElementState::Pressed)]);
+        assert_eq!(frame.gamepad_events, vec![pressed]);
+
+        let frame = session.begin_frame(0.1f32, vec![live.clone()]);
+        assert_eq!(recorded(&frame), vec![key(ElementState::Released)]);
+        assert!(frame.gamepad_events.is_empty());
+
+        // The recording ends and live input takes over from the next frame
+        let frame = session.begin_frame(0.1f32, vec![live.clone()]);
+        assert!(!session.is_replaying());
+        assert_eq!(frame.delta, 0.1f32);
+        assert!(frame.gamepad_events.is_empty());
+        let frame = session.begin_frame(0.1f32, vec![live.clone()]);
+        assert_eq!(frame.gamepad_events, vec![live]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_frames_out_of_order() {
+        let path = recording_path("out-of-order");
+        let frame = |index| RecordedFrame {
+            index,
+            delta: 0.1f32,
+            events: vec![],
+            gamepad_events: vec![],
+        };
+        let lines = [
+            serde_json::to_string(&RecordingHeader {
+                version: INPUT_RECORDING_VERSION,
+                fixed_delta: None,
+            })
+            .unwrap(),
+            serde_json::to_string(&frame(0)).unwrap(),
+            serde_json::to_string(&frame(2)).unwrap(),
+        ];
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let error = InputPlayer::open(&path).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn replays_version_1_recordings() {
+        let path = recording_path("version-1");
+        std::fs::write(
+            &path,
+            "{\"version\":1,\"fixed_delta\":null}\n{\"index\":0,\"delta\":0.25}\n{\"index\":1,\"del",
+        )
+        .unwrap();
+
+        let mut player = InputPlayer::open(&path).unwrap();
+        let frame = player.next_frame().unwrap();
+        assert_eq!(frame.delta, 0.25f32);
+        assert!(frame.gamepad_events.is_empty());
+        // The cut off last frame is ignored
+        assert!(player.next_frame().is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,317 @@
+use std::any::{type_name, TypeId};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+/// Data a job reads and writes, so jobs that touch the same data can be kept apart.
+#[derive(Clone, Debug, Default)]
+pub struct JobAccess {
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+}
+
+impl JobAccess {
+    pub fn new() -> Self {
+        JobAccess::default()
+    }
+
+    pub fn read<T: 'static>(mut self) -> Self {
+        self.reads.push(resource::<T>(None));
+        self
+    }
+
+    pub fn write<T: 'static>(mut self) -> Self {
+        self.writes.push(resource::<T>(None));
+        self
+    }
+
+    /// Writes `value` only, e.g. one of several command buffers of the same type, so jobs
+    /// writing other values of `T` aren't kept apart from this one.
+    pub fn write_one<T: 'static>(mut self, value: &T) -> Self {
+        self.writes
+            .push(resource::<T>(Some(value as *const T as usize)));
+        self
+    }
+
+    /// Two jobs conflict when one writes something the other reads or writes.
+    pub fn conflicts_with(&self, other: &JobAccess) -> bool {
+        let writes = |a: &JobAccess, b: &JobAccess| {
+            a.writes.iter().any(|write| {
+                b.reads.iter().any(|other| overlaps(write, other))
+                    || b.writes.iter().any(|other| overlaps(write, other))
+            })
+        };
+        writes(self, other) || writes(other, self)
+    }
+}
+
+/// A type of data with its name for debugging, and the address of one value of it if the
+/// access is limited to that value.
+type Resource = (TypeId, &'static str, Option<usize>);
+
+fn resource<T: 'static>(address: Option<usize>) -> Resource {
+    (TypeId::of::<T>(), type_name::<T>(), address)
+}
+
+fn overlaps(a: &Resource, b: &Resource) -> bool {
+    a.0 == b.0
+        && match (a.2, b.2) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        }
+}
+
+/// A system run by `JobWorker::barrier`.
+///
+/// The closure may borrow from the caller. Its access list must cover everything it touches
+/// through shared locks such as `Mutex` or `RwLock`, since that's what keeps writers apart.
+pub struct SystemJob<'a> {
+    name: &'static str,
+    access: JobAccess,
+    run: Box<dyn FnOnce() + Send + 'a>,
+}
+
+impl<'a> SystemJob<'a> {
+    pub fn new<F>(name: &'static str, access: JobAccess, run: F) -> Self
+    where
+        F: FnOnce() + Send + 'a,
+    {
+        SystemJob {
+            name,
+            access,
+            run: Box::new(run),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn access(&self) -> &JobAccess {
+        &self.access
+    }
+}
+
+/// Runs systems in parallel on a thread pool, or one after another on wasm and when
+/// created with `JobWorker::sequential`.
+#[derive(Clone)]
+pub struct JobWorker {
+    #[cfg(not(target_arch = "wasm32"))]
+    pool: Option<Arc<rayon::ThreadPool>>,
+    parallel: bool,
+}
+
+impl JobWorker {
+    /// Uses the global rayon pool where threads are available.
+    pub fn new() -> Self {
+        JobWorker {
+            #[cfg(not(target_arch = "wasm32"))]
+            pool: None,
+            parallel: cfg!(not(target_arch = "wasm32")),
+        }
+    }
+
+    /// Uses a dedicated pool of `threads` threads. Falls back to sequential on wasm.
+    pub fn with_threads(threads: usize) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => JobWorker {
+                    pool: Some(Arc::new(pool)),
+                    parallel: true,
+                },
+                Err(e) => {
+                    log::warn!("Running jobs sequentially: {}", e);
+                    JobWorker::sequential()
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = threads;
+            JobWorker::sequential()
+        }
+    }
+
+    /// Runs every job on the calling thread, in the order given.
+    pub fn sequential() -> Self {
+        JobWorker {
+            #[cfg(not(target_arch = "wasm32"))]
+            pool: None,
+            parallel: false,
+        }
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Runs `jobs` and returns once all of them have finished.
+    ///
+    /// Jobs whose access doesn't conflict run concurrently. A job that conflicts with an
+    /// earlier one in the list waits until that one is done, so conflicting writers run in
+    /// list order, the same order they would run in sequentially.
+    pub fn barrier(&self, jobs: Vec<SystemJob>) {
+        if !self.parallel {
+            for job in jobs {
+                (job.run)();
+            }
+            return;
+        }
+        for wave in schedule_waves(jobs) {
+            self.run_wave(wave);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_wave(&self, wave: Vec<SystemJob>) {
+        if wave.len() == 1 {
+            for job in wave {
+                (job.run)();
+            }
+            return;
+        }
+        let run = move || {
+            rayon::scope(|scope| {
+                for job in wave {
+                    scope.spawn(move |_| (job.run)());
+                }
+            })
+        };
+        match &self.pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn run_wave(&self, wave: Vec<SystemJob>) {
+        for job in wave {
+            (job.run)();
+        }
+    }
+}
+
+impl Default for JobWorker {
+    fn default() -> Self {
+        JobWorker::new()
+    }
+}
+
+/// Splits jobs into waves that can each run in parallel. Every job lands in the wave after
+/// the last one holding a job it conflicts with.
+fn schedule_waves(jobs: Vec<SystemJob>) -> Vec<Vec<SystemJob>> {
+    let mut waves: Vec<Vec<SystemJob>> = vec![];
+    for job in jobs {
+        let last_conflict = waves.iter().rposition(|wave| {
+            wave.iter()
+                .any(|other| other.access.conflicts_with(&job.access))
+        });
+        let index = last_conflict.map(|i| i + 1).unwrap_or(0);
+        if index == waves.len() {
+            waves.push(vec![]);
+        }
+        waves[index].push(job);
+    }
+    waves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier, Mutex};
+
+    struct Positions;
+    struct Angles;
+
+    fn job(name: &'static str, access: JobAccess) -> SystemJob<'static> {
+        SystemJob::new(name, access, || {})
+    }
+
+    fn wave_names(jobs: Vec<SystemJob>) -> Vec<Vec<&'static str>> {
+        schedule_waves(jobs)
+            .iter()
+            .map(|wave| wave.iter().map(|job| job.name()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn conflicting_writes_go_to_separate_waves() {
+        let waves = wave_names(vec![
+            job("read_positions", JobAccess::new().read::<Positions>()),
+            job(
+                "read_both",
+                JobAccess::new().read::<Positions>().read::<Angles>(),
+            ),
+            job("write_positions", JobAccess::new().write::<Positions>()),
+            job("write_angles", JobAccess::new().write::<Angles>()),
+            job(
+                "write_positions_again",
+                JobAccess::new().write::<Positions>(),
+            ),
+            job("read_nothing", JobAccess::new()),
+        ]);
+        assert_eq!(
+            waves,
+            vec![
+                vec!["read_positions", "read_both", "read_nothing"],
+                vec!["write_positions", "write_angles"],
+                vec!["write_positions_again"],
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_to_different_values_of_a_type_run_together() {
+        let (first, second) = (vec![0u32], vec![0u32]);
+        let waves = wave_names(vec![
+            job("first", JobAccess::new().write_one(&first)),
+            job("second", JobAccess::new().write_one(&second)),
+            job("first_again", JobAccess::new().write_one(&first)),
+            job("every_vec", JobAccess::new().read::<Vec<u32>>()),
+        ]);
+        assert_eq!(
+            waves,
+            vec![
+                vec!["first", "second"],
+                vec!["first_again"],
+                vec!["every_vec"]
+            ]
+        );
+    }
+
+    #[test]
+    fn read_only_jobs_run_concurrently() {
+        let worker = JobWorker::with_threads(2);
+        if !worker.is_parallel() {
+            return;
+        }
+        // Each job waits for the other, so this only returns if they run at the same time
+        let barrier = Arc::new(Barrier::new(2));
+        let jobs = (0..2)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                SystemJob::new("wait", JobAccess::new().read::<Positions>(), move || {
+                    barrier.wait();
+                })
+            })
+            .collect();
+        worker.barrier(jobs);
+    }
+
+    #[test]
+    fn conflicting_jobs_run_in_list_order() {
+        for worker in [JobWorker::with_threads(4), JobWorker::sequential()].iter() {
+            let order = Mutex::new(vec![]);
+            let jobs = (0..8)
+                .map(|i| {
+                    let order = &order;
+                    SystemJob::new("push", JobAccess::new().write::<Positions>(), move || {
+                        order.lock().unwrap().push(i)
+                    })
+                })
+                .collect();
+            worker.barrier(jobs);
+            assert_eq!(order.into_inner().unwrap(), (0..8).collect::<Vec<_>>());
+        }
+    }
+}
@@ -1,13 +1,40 @@
 pub mod action;
+pub mod args;
+pub mod audio;
+pub mod camera;
+pub mod capture;
+pub mod debug_ui;
+pub mod ecs;
+pub mod game;
+pub mod headless;
+pub mod input;
+pub mod job;
+pub mod lockstep;
+pub mod map;
+pub mod net;
+pub mod prefs;
+pub mod render;
+pub mod save;
 pub mod scene;
+pub mod settings;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod simulation;
+pub mod text;
+pub mod time;
+pub mod ui;
 
-use crate::scene::hello_world_scene::HelloWorldScene;
+use crate::settings::{configure_settings, load_settings_or_default, Settings};
 use tearchan::engine::Engine;
-use tearchan::engine_config::EngineStartupConfig;
 
+/// Launches with the bundled and user settings, falling back to the defaults if they are broken.
 pub fn launch_app() {
-    let startup_config =
-        EngineStartupConfig::new_with_title("tearchan example", HelloWorldScene::factory());
+    launch_app_with_settings(load_settings_or_default());
+}
+
+pub fn launch_app_with_settings(settings: Settings) {
+    settings.apply_backend();
+    let startup_config = settings.startup_config();
+    configure_settings(settings);
     let engine = Engine::new(startup_config);
     engine.run();
 }
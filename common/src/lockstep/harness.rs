@@ -0,0 +1,268 @@
+use crate::action::character::CustomActionCreatorState;
+use crate::action::Tick;
+use crate::ecs::EntityId;
+use crate::game::component::{CharacterStatus, Position};
+use crate::game::GameState;
+use crate::job::JobWorker;
+use crate::lockstep::rng::Rng;
+use crate::lockstep::{LockstepInput, LockstepSimulation};
+use crate::map::{Ground, TilePosition};
+
+/// A reproducible world and command stream for checking determinism.
+#[derive(Clone, Debug)]
+pub struct HarnessConfig {
+    pub seed: u64,
+    pub width: u32,
+    pub height: u32,
+    pub characters: usize,
+    pub items: usize,
+    pub ticks: u64,
+    pub tick_duration: Tick,
+    /// Chance per tick that a random character gets a new command.
+    pub input_rate: f32,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        HarnessConfig {
+            seed: 1,
+            width: 32,
+            height: 32,
+            characters: 64,
+            items: 32,
+            ticks: 600,
+            tick_duration: crate::lockstep::DEFAULT_TICK_DURATION,
+            input_rate: 0.5f32,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Desync {
+    pub tick: u64,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl std::fmt::Display for Desync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "State diverged at tick {}: {:016x} != {:016x}",
+            self.tick, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for Desync {}
+
+/// Builds the world described by `config`: scattered walls, characters and items.
+pub fn build_state(config: &HarnessConfig) -> (GameState, Vec<EntityId>, Vec<EntityId>) {
+    let mut rng = Rng::new(config.seed);
+    let mut ground = Ground::new(config.width, config.height);
+    for _ in 0..(config.width * config.height / 10) {
+        let tile = random_tile(&mut rng, config);
+        ground.tiles.set(&tile, crate::map::GroundKind::Wall);
+    }
+
+    let mut state = GameState::new(ground);
+    let characters = (0..config.characters)
+        .map(|_| {
+            let position = random_position(&mut rng, config);
+            let status = CharacterStatus {
+                speed: 1.0f32 + rng.next_f32() * 2.0f32,
+                carrying: None,
+            };
+            state.spawn_character(position, status)
+        })
+        .collect();
+    let items = (0..config.items)
+        .map(|_| {
+            let position = random_position(&mut rng, config);
+            state.spawn_item(position)
+        })
+        .collect();
+    (state, characters, items)
+}
+
+/// Picks a random command for this tick, or `None`. Reads character positions from the
+/// simulation, so it stays deterministic as long as the simulation does.
+pub fn random_input(
+    rng: &mut Rng,
+    config: &HarnessConfig,
+    simulation: &LockstepSimulation,
+    characters: &[EntityId],
+    items: &[EntityId],
+) -> Option<LockstepInput> {
+    if characters.is_empty() || rng.next_f32() >= config.input_rate {
+        return None;
+    }
+    let entity_id = characters[rng.range(0, characters.len() as i32) as usize];
+    let destination = random_position(rng, config);
+    let command = if !items.is_empty() && rng.next_f32() < 0.5f32 {
+        CustomActionCreatorState::BringItemToDestination {
+            item: items[rng.range(0, items.len() as i32) as usize],
+            destination,
+        }
+    } else {
+        CustomActionCreatorState::MoveToDestination {
+            start: *simulation.state().positions.get(&entity_id)?,
+            end: destination,
+        }
+    };
+    Some(LockstepInput {
+        tick: simulation.tick(),
+        entity_id,
+        command,
+    })
+}
+
+/// Runs the harness world with `job_worker` and returns the hash after every tick.
+pub fn run(config: &HarnessConfig, job_worker: JobWorker) -> Vec<u64> {
+    let (mut state, characters, items) = build_state(config);
+    state.job_worker = job_worker;
+
+    // Offset so inputs don't mirror the world layout
+    let mut input_rng = Rng::new(config.seed ^ 0x5eed);
+    let mut simulation = LockstepSimulation::new(state, config.seed, config.tick_duration);
+    (0..config.ticks)
+        .map(|_| {
+            if let Some(input) =
+                random_input(&mut input_rng, config, &simulation, &characters, &items)
+            {
+                simulation.schedule(input);
+            }
+            simulation.step()
+        })
+        .collect()
+}
+
+/// Returns the first tick where two hash streams differ.
+pub fn compare(expected: &[u64], actual: &[u64]) -> Result<(), Desync> {
+    for (tick, (expected, actual)) in expected.iter().zip(actual.iter()).enumerate() {
+        if expected != actual {
+            return Err(Desync {
+                tick: tick as u64,
+                expected: *expected,
+                actual: *actual,
+            });
+        }
+    }
+    if expected.len() != actual.len() {
+        return Err(Desync {
+            tick: expected.len().min(actual.len()) as u64,
+            expected: 0,
+            actual: 0,
+        });
+    }
+    Ok(())
+}
+
+/// Runs the same world and inputs twice on one thread and once on a thread pool, and
+/// checks that every tick hashes the same.
+pub fn verify_determinism(config: &HarnessConfig) -> Result<(), Desync> {
+    let expected = run(config, JobWorker::sequential());
+    compare(&expected, &run(config, JobWorker::sequential()))?;
+    compare(&expected, &run(config, JobWorker::with_threads(4)))
+}
+
+fn random_tile(rng: &mut Rng, config: &HarnessConfig) -> TilePosition {
+    TilePosition::new(
+        rng.range(0, config.width as i32),
+        rng.range(0, config.height as i32),
+    )
+}
+
+fn random_position(rng: &mut Rng, config: &HarnessConfig) -> Position {
+    random_tile(rng, config).to_position()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_config() -> HarnessConfig {
+        HarnessConfig {
+            width: 12,
+            height: 12,
+            characters: 8,
+            items: 4,
+            ticks: 80,
+            ..HarnessConfig::default()
+        }
+    }
+
+    /// Like `run`, but also gives the first character an extra order at `perturbed_tick`.
+    fn run_perturbed(config: &HarnessConfig, perturbed_tick: u64) -> Vec<u64> {
+        let (state, characters, items) = build_state(config);
+        let mut input_rng = Rng::new(config.seed ^ 0x5eed);
+        let mut simulation = LockstepSimulation::new(state, config.seed, config.tick_duration);
+        (0..config.ticks)
+            .map(|tick| {
+                if let Some(input) =
+                    random_input(&mut input_rng, config, &simulation, &characters, &items)
+                {
+                    simulation.schedule(input);
+                }
+                if tick == perturbed_tick {
+                    simulation.schedule(LockstepInput {
+                        tick,
+                        entity_id: characters[0],
+                        command: CustomActionCreatorState::BringItemToDestination {
+                            item: items[0],
+                            destination: Position::new(0.0f32, 0.0f32),
+                        },
+                    });
+                }
+                simulation.step()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn small_worlds_are_deterministic() {
+        assert_eq!(verify_determinism(&small_config()), Ok(()));
+        let config = HarnessConfig {
+            seed: 7,
+            input_rate: 1.0f32,
+            ..small_config()
+        };
+        assert_eq!(verify_determinism(&config), Ok(()));
+    }
+
+    #[test]
+    fn seeds_change_the_world() {
+        let config = small_config();
+        let other = HarnessConfig {
+            seed: 2,
+            ..small_config()
+        };
+        let hashes = run(&config, JobWorker::sequential());
+        assert_eq!(hashes.len(), config.ticks as usize);
+        assert!(compare(&hashes, &run(&other, JobWorker::sequential())).is_err());
+    }
+
+    #[test]
+    fn perturbed_inputs_desync_at_their_tick() {
+        let config = small_config();
+        let expected = run(&config, JobWorker::sequential());
+        let actual = run_perturbed(&config, 30);
+        let desync = compare(&expected, &actual).unwrap_err();
+        assert_eq!(desync.tick, 30);
+        assert_eq!(desync.expected, expected[30]);
+        assert_eq!(desync.actual, actual[30]);
+    }
+
+    #[test]
+    fn shorter_streams_desync_where_they_end() {
+        let hashes = run(&small_config(), JobWorker::sequential());
+        assert_eq!(
+            compare(&hashes, &hashes[..10]),
+            Err(Desync {
+                tick: 10,
+                expected: 0,
+                actual: 0,
+            })
+        );
+    }
+}
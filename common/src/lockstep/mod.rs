@@ -0,0 +1,122 @@
+pub mod harness;
+pub mod hash;
+pub mod rng;
+
+use crate::action::character::CustomActionCreatorState;
+use crate::action::Tick;
+use crate::ecs::EntityId;
+use crate::game::GameState;
+use crate::lockstep::hash::StateHasher;
+use crate::lockstep::rng::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Simulation time per lockstep tick, 20 ticks per second.
+pub const DEFAULT_TICK_DURATION: Tick = 50;
+
+/// A command for one entity, applied at the start of `tick`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LockstepInput {
+    pub tick: u64,
+    pub entity_id: EntityId,
+    pub command: CustomActionCreatorState,
+}
+
+/// Advances a `GameState` in fixed ticks so that every peer given the same inputs ends up
+/// with the same state.
+///
+/// Inputs for a tick are applied in entity order regardless of the order they arrived in,
+/// all randomness comes from the seeded `rng`, and every tick yields a hash of the state
+/// that peers can compare to detect a desync.
+pub struct LockstepSimulation {
+    state: GameState,
+    rng: Rng,
+    tick: u64,
+    tick_duration: Tick,
+    inputs: BTreeMap<u64, Vec<LockstepInput>>,
+    last_hash: u64,
+}
+
+impl LockstepSimulation {
+    pub fn new(state: GameState, seed: u64, tick_duration: Tick) -> Self {
+        let mut simulation = LockstepSimulation {
+            state,
+            rng: Rng::new(seed),
+            tick: 0,
+            tick_duration,
+            inputs: BTreeMap::new(),
+            last_hash: 0,
+        };
+        simulation.last_hash = simulation.hash();
+        simulation
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn rng_mut(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    /// The next tick to run.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn tick_duration(&self) -> Tick {
+        self.tick_duration
+    }
+
+    pub fn last_hash(&self) -> u64 {
+        self.last_hash
+    }
+
+    /// Queues `input`. Inputs for ticks that already ran are applied on the next tick and
+    /// logged, since peers that applied them on time will have diverged.
+    pub fn schedule(&mut self, mut input: LockstepInput) {
+        if input.tick < self.tick {
+            log::warn!(
+                "Input for tick {} arrived at tick {}",
+                input.tick,
+                self.tick
+            );
+            input.tick = self.tick;
+        }
+        self.inputs.entry(input.tick).or_default().push(input);
+    }
+
+    /// Runs one tick and returns the hash of the resulting state.
+    pub fn step(&mut self) -> u64 {
+        if let Some(mut inputs) = self.inputs.remove(&self.tick) {
+            // Stable, so commands for the same entity keep the order they were issued in
+            inputs.sort_by_key(|input| input.entity_id);
+            for input in inputs {
+                self.state.order(input.entity_id, input.command);
+            }
+        }
+        self.state.update(self.tick_duration);
+        self.tick += 1;
+        self.last_hash = self.hash();
+        self.last_hash
+    }
+
+    /// Hash of everything that affects future ticks.
+    pub fn hash(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        hasher.write_u64(self.tick);
+        hasher.write_serialized(&self.rng);
+        hash_game_state(&mut hasher, &self.state);
+        hasher.finish()
+    }
+}
+
+pub fn hash_game_state(hasher: &mut StateHasher, state: &GameState) {
+    hasher.write_u64(state.entity_manager.next_id());
+    hasher.write_serialized(&state.entities);
+    hasher.write_serialized(&state.positions);
+    hasher.write_serialized(&state.angles);
+    hasher.write_serialized(&state.character_status);
+    hasher.write_serialized(&state.items);
+    hasher.write_serialized(&state.actions);
+}
@@ -0,0 +1,270 @@
+pub mod path;
+
+use crate::game::component::Position;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub struct TilePosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl TilePosition {
+    pub fn new(x: i32, y: i32) -> Self {
+        TilePosition { x, y }
+    }
+
+    /// The tile containing `position`. Tile centers sit on whole coordinates.
+    pub fn from_position(position: &Position) -> Self {
+        TilePosition {
+            x: position.x.round() as i32,
+            y: position.y.round() as i32,
+        }
+    }
+
+    pub fn to_position(&self) -> Position {
+        Position::new(self.x as f32, self.y as f32)
+    }
+}
+
+/// A rectangle of tiles stored row by row.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridMap<T> {
+    width: u32,
+    height: u32,
+    tiles: Vec<T>,
+}
+
+impl<T: Clone> GridMap<T> {
+    pub fn new(width: u32, height: u32, fill: T) -> Self {
+        GridMap {
+            width,
+            height,
+            tiles: vec![fill; width as usize * height as usize],
+        }
+    }
+}
+
+impl<T> GridMap<T> {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn contains(&self, position: &TilePosition) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && (position.x as u32) < self.width
+            && (position.y as u32) < self.height
+    }
+
+    pub fn get(&self, position: &TilePosition) -> Option<&T> {
+        let index = self.index(position)?;
+        self.tiles.get(index)
+    }
+
+    pub fn set(&mut self, position: &TilePosition, tile: T) {
+        if let Some(index) = self.index(position) {
+            self.tiles[index] = tile;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TilePosition, &T)> {
+        let width = self.width as usize;
+        self.tiles.iter().enumerate().map(move |(index, tile)| {
+            let position = TilePosition::new((index % width) as i32, (index / width) as i32);
+            (position, tile)
+        })
+    }
+
+    fn index(&self, position: &TilePosition) -> Option<usize> {
+        if self.contains(position) {
+            Some(position.y as usize * self.width as usize + position.x as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroundKind {
+    Plain,
+    Rough,
+    Water,
+    Wall,
+}
+
+impl GroundKind {
+    /// How many times longer crossing this tile takes than plain ground; `None` if impassable.
+    pub fn movement_cost(self) -> Option<f32> {
+        match self {
+            GroundKind::Plain => Some(1.0f32),
+            GroundKind::Rough => Some(2.0f32),
+            GroundKind::Water => Some(4.0f32),
+            GroundKind::Wall => None,
+        }
+    }
+
+    fn from_char(c: char) -> Option<GroundKind> {
+        match c {
+            '.' => Some(GroundKind::Plain),
+            ',' => Some(GroundKind::Rough),
+            '~' => Some(GroundKind::Water),
+            '#' => Some(GroundKind::Wall),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseGroundError {
+    Empty,
+    RaggedRow {
+        row: usize,
+    },
+    UnknownTile {
+        row: usize,
+        column: usize,
+        tile: char,
+    },
+}
+
+impl std::fmt::Display for ParseGroundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseGroundError::Empty => write!(f, "The map has no tiles"),
+            ParseGroundError::RaggedRow { row } => {
+                write!(f, "Row {} has a different width than the first row", row)
+            }
+            ParseGroundError::UnknownTile { row, column, tile } => {
+                write!(
+                    f,
+                    "Unknown tile {:?} at row {}, column {}",
+                    tile, row, column
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseGroundError {}
+
+/// The ground type of every tile on the map.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Ground {
+    pub tiles: GridMap<GroundKind>,
+}
+
+impl Ground {
+    pub fn new(width: u32, height: u32) -> Self {
+        Ground {
+            tiles: GridMap::new(width, height, GroundKind::Plain),
+        }
+    }
+
+    /// Parses a map drawn with one character per tile, the first line being `y = 0`:
+    /// `.` plain, `,` rough, `~` water and `#` wall.
+    pub fn parse(text: &str) -> Result<Self, ParseGroundError> {
+        let rows = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let width = match rows.first() {
+            Some(row) => row.chars().count(),
+            None => return Err(ParseGroundError::Empty),
+        };
+
+        let mut ground = Ground::new(width as u32, rows.len() as u32);
+        for (row, line) in rows.iter().enumerate() {
+            if line.chars().count() != width {
+                return Err(ParseGroundError::RaggedRow { row });
+            }
+            for (column, tile) in line.chars().enumerate() {
+                let kind = GroundKind::from_char(tile).ok_or(ParseGroundError::UnknownTile {
+                    row,
+                    column,
+                    tile,
+                })?;
+                ground
+                    .tiles
+                    .set(&TilePosition::new(column as i32, row as i32), kind);
+            }
+        }
+        Ok(ground)
+    }
+
+    pub fn kind(&self, position: &TilePosition) -> Option<GroundKind> {
+        self.tiles.get(position).copied()
+    }
+
+    /// Movement cost of the tile, or `None` if it is impassable or off the map.
+    pub fn movement_cost(&self, position: &TilePosition) -> Option<f32> {
+        self.kind(position)?.movement_cost()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rows_top_down() {
+        let ground = Ground::parse(
+            "
+            .,~
+            #..
+            ",
+        )
+        .unwrap();
+        assert_eq!(ground.tiles.width(), 3);
+        assert_eq!(ground.tiles.height(), 2);
+        assert_eq!(
+            ground.kind(&TilePosition::new(2, 0)),
+            Some(GroundKind::Water)
+        );
+        assert_eq!(
+            ground.kind(&TilePosition::new(0, 1)),
+            Some(GroundKind::Wall)
+        );
+        assert_eq!(ground.movement_cost(&TilePosition::new(1, 0)), Some(2.0f32));
+        assert_eq!(ground.movement_cost(&TilePosition::new(0, 1)), None);
+        assert_eq!(ground.movement_cost(&TilePosition::new(3, 0)), None);
+    }
+
+    #[test]
+    fn rejects_malformed_maps() {
+        assert_eq!(Ground::parse("\n  \n"), Err(ParseGroundError::Empty));
+        assert_eq!(
+            Ground::parse("...\n..\n"),
+            Err(ParseGroundError::RaggedRow { row: 1 })
+        );
+        assert_eq!(
+            Ground::parse("..\n.x\n"),
+            Err(ParseGroundError::UnknownTile {
+                row: 1,
+                column: 1,
+                tile: 'x',
+            })
+        );
+    }
+
+    #[test]
+    fn grid_maps_ignore_positions_off_the_map() {
+        let mut map = GridMap::new(2, 2, 0);
+        map.set(&TilePosition::new(-1, 0), 1);
+        map.set(&TilePosition::new(2, 1), 1);
+        map.set(&TilePosition::new(1, 1), 2);
+        assert_eq!(map.get(&TilePosition::new(0, -1)), None);
+        assert_eq!(
+            map.iter().map(|(_, tile)| *tile).collect::<Vec<_>>(),
+            vec![0, 0, 0, 2]
+        );
+    }
+}
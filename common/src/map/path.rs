@@ -0,0 +1,383 @@
+use crate::action::Tick;
+use crate::game::component::CharacterStatus;
+use crate::map::{GridMap, Ground, TilePosition};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+const ORTHOGONAL_NEIGHBORS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL_NEIGHBORS: [(i32, i32); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+
+/// One step of a path between neighboring tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PathSegment {
+    pub start: TilePosition,
+    pub end: TilePosition,
+}
+
+impl PathSegment {
+    pub fn length(&self) -> f32 {
+        let dx = (self.end.x - self.start.x) as f32;
+        let dy = (self.end.y - self.start.y) as f32;
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+/// Finds paths over a snapshot of the ground's movement costs.
+///
+/// Call `update_tile` or `rebuild` when the ground changes.
+pub struct MapPath {
+    costs: GridMap<Option<f32>>,
+    min_cost: f32,
+    /// Allows moving diagonally between tiles, but never across the corner of an impassable tile.
+    pub allow_diagonal: bool,
+}
+
+impl MapPath {
+    pub fn new(ground: &Ground) -> Self {
+        let mut map_path = MapPath {
+            costs: GridMap::new(ground.tiles.width(), ground.tiles.height(), None),
+            min_cost: 1.0f32,
+            allow_diagonal: true,
+        };
+        map_path.rebuild(ground);
+        map_path
+    }
+
+    pub fn rebuild(&mut self, ground: &Ground) {
+        self.costs = GridMap::new(ground.tiles.width(), ground.tiles.height(), None);
+        for (position, kind) in ground.tiles.iter() {
+            self.costs.set(&position, kind.movement_cost());
+        }
+        self.update_min_cost();
+    }
+
+    pub fn update_tile(&mut self, ground: &Ground, position: &TilePosition) {
+        self.costs.set(position, ground.movement_cost(position));
+        self.update_min_cost();
+    }
+
+    pub fn is_passable(&self, position: &TilePosition) -> bool {
+        self.cost(position).is_some()
+    }
+
+    /// Returns the cheapest path from `start` to `end` as steps between neighboring tiles,
+    /// an empty path if they are the same tile, or `None` if `end` can't be reached.
+    pub fn search_paths(
+        &self,
+        start: &TilePosition,
+        end: &TilePosition,
+    ) -> Option<Vec<PathSegment>> {
+        if !self.is_passable(start) || !self.is_passable(end) {
+            return None;
+        }
+        if start == end {
+            return Some(vec![]);
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<TilePosition, TilePosition> = HashMap::new();
+        let mut costs: HashMap<TilePosition, f32> = HashMap::new();
+        costs.insert(*start, 0.0f32);
+        open.push(OpenNode {
+            estimate: self.heuristic(start, end),
+            cost: 0.0f32,
+            position: *start,
+        });
+
+        while let Some(node) = open.pop() {
+            if node.position == *end {
+                return Some(self.reconstruct(&came_from, end));
+            }
+            if node.cost > costs[&node.position] {
+                // A cheaper way here was found after this node was queued
+                continue;
+            }
+            for (neighbor, step_cost) in self.neighbors(&node.position) {
+                let cost = node.cost + step_cost;
+                if costs.get(&neighbor).map(|c| cost < *c).unwrap_or(true) {
+                    costs.insert(neighbor, cost);
+                    came_from.insert(neighbor, node.position);
+                    open.push(OpenNode {
+                        estimate: cost + self.heuristic(&neighbor, end),
+                        cost,
+                        position: neighbor,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn cost(&self, position: &TilePosition) -> Option<f32> {
+        self.costs.get(position).copied().flatten()
+    }
+
+    /// Half of a step is spent on each of the two tiles.
+    fn step_cost(&self, from: &TilePosition, to: &TilePosition, length: f32) -> Option<f32> {
+        Some((self.cost(from)? + self.cost(to)?) * 0.5f32 * length)
+    }
+
+    fn neighbors(&self, position: &TilePosition) -> Vec<(TilePosition, f32)> {
+        let mut neighbors = vec![];
+        for (dx, dy) in ORTHOGONAL_NEIGHBORS.iter() {
+            let next = TilePosition::new(position.x + dx, position.y + dy);
+            if let Some(cost) = self.step_cost(position, &next, 1.0f32) {
+                neighbors.push((next, cost));
+            }
+        }
+        if self.allow_diagonal {
+            for (dx, dy) in DIAGONAL_NEIGHBORS.iter() {
+                let next = TilePosition::new(position.x + dx, position.y + dy);
+                let is_corner_free = self
+                    .is_passable(&TilePosition::new(position.x + dx, position.y))
+                    && self.is_passable(&TilePosition::new(position.x, position.y + dy));
+                if !is_corner_free {
+                    continue;
+                }
+                if let Some(cost) = self.step_cost(position, &next, std::f32::consts::SQRT_2) {
+                    neighbors.push((next, cost));
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Octile (or Manhattan) distance at the cheapest cost, so it never overestimates.
+    fn heuristic(&self, from: &TilePosition, to: &TilePosition) -> f32 {
+        let dx = (to.x - from.x).abs() as f32;
+        let dy = (to.y - from.y).abs() as f32;
+        let distance = if self.allow_diagonal {
+            dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0f32) * dx.min(dy)
+        } else {
+            dx + dy
+        };
+        distance * self.min_cost
+    }
+
+    fn reconstruct(
+        &self,
+        came_from: &HashMap<TilePosition, TilePosition>,
+        end: &TilePosition,
+    ) -> Vec<PathSegment> {
+        let mut segments = vec![];
+        let mut current = *end;
+        while let Some(previous) = came_from.get(&current) {
+            segments.push(PathSegment {
+                start: *previous,
+                end: current,
+            });
+            current = *previous;
+        }
+        segments.reverse();
+        segments
+    }
+
+    fn update_min_cost(&mut self) {
+        self.min_cost = self
+            .costs
+            .iter()
+            .filter_map(|(_, cost)| *cost)
+            .fold(None, |min: Option<f32>, cost| {
+                Some(min.map(|min| min.min(cost)).unwrap_or(cost))
+            })
+            .unwrap_or(1.0f32);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct OpenNode {
+    estimate: f32,
+    cost: f32,
+    position: TilePosition,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    // Reversed so `BinaryHeap` pops the lowest estimate first; ties go to the node
+    // furthest along, then to tile order so searches are deterministic
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                self.cost
+                    .partial_cmp(&other.cost)
+                    .unwrap_or(Ordering::Equal)
+            })
+            .then_with(|| other.position.cmp(&self.position))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Time a character with `status` takes to walk `segment`, or `None` if it can't.
+pub fn calc_movement_time(
+    status: &CharacterStatus,
+    ground: &Ground,
+    segment: &PathSegment,
+) -> Option<Tick> {
+    if status.speed <= 0.0f32 {
+        return None;
+    }
+    let cost =
+        (ground.movement_cost(&segment.start)? + ground.movement_cost(&segment.end)?) * 0.5f32;
+    let seconds = segment.length() * cost / status.speed;
+    Some((seconds * 1000.0f32).round() as Tick)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_path(text: &str) -> MapPath {
+        MapPath::new(&Ground::parse(text).unwrap())
+    }
+
+    fn tiles(segments: &[PathSegment]) -> Vec<(i32, i32)> {
+        let mut tiles = segments
+            .iter()
+            .map(|segment| (segment.start.x, segment.start.y))
+            .collect::<Vec<_>>();
+        if let Some(last) = segments.last() {
+            tiles.push((last.end.x, last.end.y));
+        }
+        tiles
+    }
+
+    #[test]
+    fn walks_straight_across_open_ground() {
+        let path = map_path(
+            "
+            .....
+            .....
+            ",
+        );
+        let segments = path
+            .search_paths(&TilePosition::new(0, 0), &TilePosition::new(4, 0))
+            .unwrap();
+        assert_eq!(
+            tiles(&segments),
+            vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]
+        );
+        assert_eq!(
+            path.search_paths(&TilePosition::new(1, 1), &TilePosition::new(1, 1)),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn detours_around_walls_and_costly_ground() {
+        let mut path = map_path(
+            "
+            .#.
+            .#.
+            ...
+            ",
+        );
+        path.allow_diagonal = false;
+        let segments = path
+            .search_paths(&TilePosition::new(0, 0), &TilePosition::new(2, 0))
+            .unwrap();
+        assert_eq!(
+            tiles(&segments),
+            vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)]
+        );
+
+        // Going around two plain tiles is cheaper than wading through the water
+        let mut path = map_path(
+            "
+            .~.
+            ...
+            ",
+        );
+        path.allow_diagonal = false;
+        let segments = path
+            .search_paths(&TilePosition::new(0, 0), &TilePosition::new(2, 0))
+            .unwrap();
+        assert_eq!(
+            tiles(&segments),
+            vec![(0, 0), (0, 1), (1, 1), (2, 1), (2, 0)]
+        );
+    }
+
+    #[test]
+    fn never_cuts_the_corner_of_a_wall() {
+        let path = map_path(
+            "
+            ..
+            #.
+            ",
+        );
+        let segments = path
+            .search_paths(&TilePosition::new(0, 0), &TilePosition::new(1, 1))
+            .unwrap();
+        assert_eq!(tiles(&segments), vec![(0, 0), (1, 0), (1, 1)]);
+
+        let path = map_path(
+            "
+            ...
+            ...
+            ",
+        );
+        let segments = path
+            .search_paths(&TilePosition::new(0, 0), &TilePosition::new(1, 1))
+            .unwrap();
+        assert_eq!(tiles(&segments), vec![(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn unreachable_goals_have_no_path() {
+        let mut path = map_path(
+            "
+            ..#..
+            ..#..
+            ",
+        );
+        assert_eq!(
+            path.search_paths(&TilePosition::new(0, 0), &TilePosition::new(4, 1)),
+            None
+        );
+        assert_eq!(
+            path.search_paths(&TilePosition::new(0, 0), &TilePosition::new(2, 0)),
+            None
+        );
+        assert_eq!(
+            path.search_paths(&TilePosition::new(0, 0), &TilePosition::new(9, 0)),
+            None
+        );
+
+        let mut ground = Ground::parse("..#..\n..#..\n").unwrap();
+        ground
+            .tiles
+            .set(&TilePosition::new(2, 1), crate::map::GroundKind::Plain);
+        path.update_tile(&ground, &TilePosition::new(2, 1));
+        assert!(path
+            .search_paths(&TilePosition::new(0, 0), &TilePosition::new(4, 1))
+            .is_some());
+    }
+
+    #[test]
+    fn movement_time_scales_with_ground_cost() {
+        let ground = Ground::parse(".,").unwrap();
+        let status = CharacterStatus {
+            speed: 2.0f32,
+            carrying: None,
+        };
+        let segment = PathSegment {
+            start: TilePosition::new(0, 0),
+            end: TilePosition::new(1, 0),
+        };
+        assert_eq!(calc_movement_time(&status, &ground, &segment), Some(750));
+        let stopped = CharacterStatus {
+            speed: 0.0f32,
+            carrying: None,
+        };
+        assert_eq!(calc_movement_time(&stopped, &ground, &segment), None);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::map::Ground;
+use crate::net::protocol::{
+    decode, encode, ClientId, ClientMessage, PlayerCommand, Replica, ServerMessage,
+    PROTOCOL_VERSION,
+};
+use crate::net::socket::Socket;
+use crate::net::NetError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientEvent {
+    /// The server sent the full state; `NetClient::replica` is available from now on.
+    Joined {
+        client_id: ClientId,
+    },
+    Updated,
+    Rejected {
+        reason: String,
+    },
+    /// The server is about to close the connection.
+    Kicked {
+        reason: String,
+    },
+}
+
+/// Sends player commands to a server and mirrors the state it broadcasts.
+///
+/// Call `poll` once per frame; it never blocks.
+pub struct NetClient {
+    socket: Socket,
+    client_id: Option<ClientId>,
+    replica: Option<Replica>,
+    ground: Option<Ground>,
+}
+
+impl NetClient {
+    pub fn connect(url: &str, name: &str) -> Result<Self, NetError> {
+        let mut socket = Socket::connect(url)?;
+        socket.send(encode(&ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+        })?)?;
+        Ok(NetClient {
+            socket,
+            client_id: None,
+            replica: None,
+            ground: None,
+        })
+    }
+
+    pub fn client_id(&self) -> Option<ClientId> {
+        self.client_id
+    }
+
+    /// The server's state as of the last poll, once joined.
+    pub fn replica(&self) -> Option<&Replica> {
+        self.replica.as_ref()
+    }
+
+    pub fn ground(&self) -> Option<&Ground> {
+        self.ground.as_ref()
+    }
+
+    pub fn send_command(&mut self, command: PlayerCommand) -> Result<(), NetError> {
+        self.socket
+            .send(encode(&ClientMessage::Command { command })?)
+    }
+
+    /// Applies everything the server sent since the last poll.
+    pub fn poll(&mut self) -> Result<Vec<ClientEvent>, NetError> {
+        let mut events = vec![];
+        loop {
+            let text = match self.socket.receive() {
+                Ok(Some(text)) => text,
+                Ok(None) => break,
+                // Hand out what arrived first; the next poll reports the error
+                Err(_) if !events.is_empty() => break,
+                Err(e) => return Err(e),
+            };
+            match decode(&text)? {
+                ServerMessage::Welcome {
+                    version,
+                    client_id,
+                    tick,
+                    snapshot,
+                } => {
+                    if version != PROTOCOL_VERSION {
+                        self.socket.close();
+                        return Err(NetError::Version {
+                            found: version,
+                            supported: PROTOCOL_VERSION,
+                        });
+                    }
+                    self.client_id = Some(client_id);
+                    self.replica = Some(Replica::from_snapshot(&snapshot, tick));
+                    self.ground = Some(snapshot.ground);
+                    events.push(ClientEvent::Joined { client_id });
+                }
+                ServerMessage::Delta { delta } => match self.replica.as_mut() {
+                    Some(replica) => {
+                        delta.apply(replica);
+                        events.push(ClientEvent::Updated);
+                    }
+                    None => log::warn!("Received a delta before joining"),
+                },
+                ServerMessage::Rejected { reason } => events.push(ClientEvent::Rejected { reason }),
+                ServerMessage::Error { reason } => events.push(ClientEvent::Kicked { reason }),
+            }
+        }
+        Ok(events)
+    }
+
+    pub fn disconnect(&mut self) {
+        self.socket.close();
+    }
+}
@@ -0,0 +1,49 @@
+pub mod client;
+pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+pub mod socket;
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The WebSocket handshake or transport failed.
+    Socket(String),
+    /// The peer spoke a different protocol version.
+    Version {
+        found: u32,
+        supported: u32,
+    },
+    Closed,
+}
+
+impl std::fmt::Display for NetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "Network error: {}", e),
+            NetError::Json(e) => write!(f, "Invalid message: {}", e),
+            NetError::Socket(e) => write!(f, "WebSocket error: {}", e),
+            NetError::Version { found, supported } => write!(
+                f,
+                "Protocol version {} doesn't match the supported version {}",
+                found, supported
+            ),
+            NetError::Closed => write!(f, "Connection closed"),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+impl From<std::io::Error> for NetError {
+    fn from(e: std::io::Error) -> Self {
+        NetError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for NetError {
+    fn from(e: serde_json::Error) -> Self {
+        NetError::Json(e)
+    }
+}
@@ -0,0 +1,231 @@
+use crate::action::character::CustomActionCreatorState;
+use crate::action::Tick;
+use crate::ecs::{ComponentGroup, EntityId};
+use crate::game::component::{Angle, CharacterStatus, Item, Position};
+use crate::game::GameState;
+use crate::map::TilePosition;
+use crate::net::NetError;
+use crate::save::SaveData;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Bumped whenever a message changes shape; peers with another version are turned away.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+pub type ClientId = u64;
+
+/// What a player asks the server to do. The server decides whether and how it happens.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayerCommand {
+    MoveTo {
+        entity_id: EntityId,
+        destination: TilePosition,
+    },
+    BringItem {
+        entity_id: EntityId,
+        item: EntityId,
+        destination: TilePosition,
+    },
+}
+
+impl PlayerCommand {
+    pub fn entity_id(&self) -> EntityId {
+        match self {
+            PlayerCommand::MoveTo { entity_id, .. } => *entity_id,
+            PlayerCommand::BringItem { entity_id, .. } => *entity_id,
+        }
+    }
+
+    /// The action creator for this command, or `None` if it refers to something that
+    /// doesn't exist.
+    pub fn to_creator(&self, state: &GameState) -> Option<CustomActionCreatorState> {
+        if !state.character_status.contains(&self.entity_id()) {
+            return None;
+        }
+        match self {
+            PlayerCommand::MoveTo {
+                entity_id,
+                destination,
+            } => Some(CustomActionCreatorState::MoveToDestination {
+                start: *state.positions.get(entity_id)?,
+                end: destination.to_position(),
+            }),
+            PlayerCommand::BringItem {
+                item, destination, ..
+            } => {
+                if !state.items.contains(item) {
+                    return None;
+                }
+                Some(CustomActionCreatorState::BringItemToDestination {
+                    item: *item,
+                    destination: destination.to_position(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello { version: u32, name: String },
+    Command { command: PlayerCommand },
+}
+
+/// Adjacently tagged: an internally tagged enum buffers its fields, which turns the entity id
+/// keys of the snapshot's component maps into strings that no longer parse.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "body", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The full state, sent once after `Hello`. Deltas follow from this point on.
+    Welcome {
+        version: u32,
+        client_id: ClientId,
+        tick: Tick,
+        snapshot: Box<SaveData>,
+    },
+    Delta {
+        delta: Box<StateDelta>,
+    },
+    /// A command was refused; the connection stays open.
+    Rejected {
+        reason: String,
+    },
+    /// The server is closing the connection.
+    Error {
+        reason: String,
+    },
+}
+
+/// Components that were set or removed since the last delta.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ComponentDelta<T> {
+    pub changed: Vec<(EntityId, T)>,
+    pub removed: Vec<EntityId>,
+}
+
+impl<T> ComponentDelta<T>
+where
+    T: Clone + PartialEq,
+{
+    pub fn diff(old: &ComponentGroup<T>, new: &ComponentGroup<T>) -> Self {
+        let changed = new
+            .iter()
+            .filter(|(entity_id, component)| old.get(entity_id) != Some(component))
+            .map(|(entity_id, component)| (*entity_id, component.clone()))
+            .collect();
+        let removed = old
+            .entities()
+            .filter(|entity_id| !new.contains(entity_id))
+            .copied()
+            .collect();
+        ComponentDelta { changed, removed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn apply(&self, group: &mut ComponentGroup<T>) {
+        for entity_id in self.removed.iter() {
+            group.remove(entity_id);
+        }
+        for (entity_id, component) in self.changed.iter() {
+            group.insert(*entity_id, component.clone());
+        }
+    }
+}
+
+/// Everything a client needs to mirror the server's state from one tick to the next.
+/// Plans aren't sent; clients only show where things are.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateDelta {
+    pub tick: Tick,
+    pub created: Vec<EntityId>,
+    pub destroyed: Vec<EntityId>,
+    pub positions: ComponentDelta<Position>,
+    pub angles: ComponentDelta<Angle>,
+    pub character_status: ComponentDelta<CharacterStatus>,
+    pub items: ComponentDelta<Item>,
+}
+
+impl StateDelta {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.destroyed.is_empty()
+            && self.positions.is_empty()
+            && self.angles.is_empty()
+            && self.character_status.is_empty()
+            && self.items.is_empty()
+    }
+
+    pub fn apply(&self, replica: &mut Replica) {
+        replica.tick = self.tick;
+        for entity_id in self.destroyed.iter() {
+            replica.entities.remove(entity_id);
+        }
+        replica.entities.extend(self.created.iter().copied());
+        self.positions.apply(&mut replica.positions);
+        self.angles.apply(&mut replica.angles);
+        self.character_status.apply(&mut replica.character_status);
+        self.items.apply(&mut replica.items);
+    }
+}
+
+/// The replicated part of a `GameState`, kept by clients and by the server to diff against.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replica {
+    pub tick: Tick,
+    pub entities: BTreeSet<EntityId>,
+    pub positions: ComponentGroup<Position>,
+    pub angles: ComponentGroup<Angle>,
+    pub character_status: ComponentGroup<CharacterStatus>,
+    pub items: ComponentGroup<Item>,
+}
+
+impl Replica {
+    pub fn from_state(state: &GameState) -> Self {
+        Replica {
+            tick: state.tick(),
+            entities: state.entities.clone(),
+            positions: state.positions.clone(),
+            angles: state.angles.clone(),
+            character_status: state.character_status.clone(),
+            items: state.items.clone(),
+        }
+    }
+
+    pub fn from_snapshot(snapshot: &SaveData, tick: Tick) -> Self {
+        Replica {
+            tick,
+            entities: snapshot.entities.clone(),
+            positions: snapshot.positions.clone(),
+            angles: snapshot.angles.clone(),
+            character_status: snapshot.character_status.clone(),
+            items: snapshot.items.clone(),
+        }
+    }
+
+    /// What changed between this replica and `state`.
+    pub fn diff(&self, state: &GameState) -> StateDelta {
+        StateDelta {
+            tick: state.tick(),
+            created: state.entities.difference(&self.entities).copied().collect(),
+            destroyed: self.entities.difference(&state.entities).copied().collect(),
+            positions: ComponentDelta::diff(&self.positions, &state.positions),
+            angles: ComponentDelta::diff(&self.angles, &state.angles),
+            character_status: ComponentDelta::diff(&self.character_status, &state.character_status),
+            items: ComponentDelta::diff(&self.items, &state.items),
+        }
+    }
+}
+
+pub fn encode<T: Serialize>(message: &T) -> Result<String, NetError> {
+    Ok(serde_json::to_string(message)?)
+}
+
+pub fn decode<T: DeserializeOwned>(text: &str) -> Result<T, NetError> {
+    Ok(serde_json::from_str(text)?)
+}
@@ -0,0 +1,317 @@
+use crate::action::Tick;
+use crate::game::GameState;
+use crate::net::protocol::{
+    decode, encode, ClientId, ClientMessage, Replica, ServerMessage, PROTOCOL_VERSION,
+};
+use crate::net::socket::Socket;
+use crate::net::NetError;
+use crate::save::SaveData;
+use std::collections::BTreeMap;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// How often the accept thread checks whether the server stopped
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(10);
+
+// How long a new connection may take to complete the WebSocket handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Connection {
+    socket: Socket,
+    name: Option<String>,
+}
+
+/// The authoritative game: applies player commands, runs `GameState` at a fixed tick and
+/// broadcasts what changed to every joined client.
+pub struct Server {
+    state: GameState,
+    replica: Replica,
+    tick_duration: Tick,
+    local_addr: SocketAddr,
+    accepted: Receiver<Socket>,
+    connections: BTreeMap<ClientId, Connection>,
+    next_client_id: ClientId,
+    running: Arc<AtomicBool>,
+}
+
+impl Server {
+    /// Listens on `address`, e.g. `127.0.0.1:9000`. Port 0 picks a free port.
+    pub fn bind(address: &str, state: GameState, tick_duration: Tick) -> Result<Self, NetError> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let running = Arc::new(AtomicBool::new(true));
+
+        let (sender, accepted) = channel();
+        let accept_running = Arc::clone(&running);
+        std::thread::Builder::new()
+            .name("server-accept".to_string())
+            .spawn(move || {
+                while accept_running.load(Ordering::Relaxed) {
+                    let stream = match listener.accept() {
+                        Ok((stream, _)) => stream,
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                            std::thread::sleep(ACCEPT_INTERVAL);
+                            continue;
+                        }
+                        Err(e) => {
+                            log::warn!("Failed to accept a connection: {}", e);
+                            continue;
+                        }
+                    };
+                    // Handshakes run on their own threads so a client that connects and
+                    // then stays silent only holds up itself
+                    let sender = sender.clone();
+                    let spawned = std::thread::Builder::new()
+                        .name("server-handshake".to_string())
+                        .spawn(move || match handshake(stream) {
+                            Ok(socket) => {
+                                let _ = sender.send(socket);
+                            }
+                            Err(e) => log::warn!("Failed to open a WebSocket: {}", e),
+                        });
+                    if let Err(e) = spawned {
+                        log::warn!("Failed to start a handshake: {}", e);
+                    }
+                }
+            })?;
+
+        Ok(Server {
+            replica: Replica::from_state(&state),
+            state,
+            tick_duration,
+            local_addr,
+            accepted,
+            connections: BTreeMap::new(),
+            next_client_id: 1,
+            running,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.local_addr)
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Runs one tick: handles new connections and messages, updates the game and
+    /// broadcasts the changes.
+    pub fn step(&mut self) {
+        while let Ok(socket) = self.accepted.try_recv() {
+            let client_id = self.next_client_id;
+            self.next_client_id += 1;
+            self.connections
+                .insert(client_id, Connection { socket, name: None });
+        }
+
+        // Clients are served in id order so commands arriving in the same tick always
+        // apply in the same order
+        let mut disconnected = vec![];
+        for (client_id, connection) in self.connections.iter_mut() {
+            if let Err(e) = receive(*client_id, connection, &mut self.state, &self.replica) {
+                match e {
+                    NetError::Closed => {}
+                    e => log::warn!("Dropping client {}: {}", client_id, e),
+                }
+                disconnected.push(*client_id);
+            }
+        }
+        for client_id in disconnected {
+            if let Some(mut connection) = self.connections.remove(&client_id) {
+                connection.socket.close();
+                log::info!(
+                    "Client {} ({}) left",
+                    client_id,
+                    connection.name.as_deref().unwrap_or("unnamed")
+                );
+            }
+        }
+
+        self.state.update(self.tick_duration);
+
+        let delta = self.replica.diff(&self.state);
+        if delta.is_empty() {
+            return;
+        }
+        delta.apply(&mut self.replica);
+        let text = match encode(&ServerMessage::Delta {
+            delta: Box::new(delta),
+        }) {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to encode a delta: {}", e);
+                return;
+            }
+        };
+        for connection in self.connections.values_mut() {
+            if connection.name.is_some() {
+                let _ = connection.socket.send(text.clone());
+            }
+        }
+    }
+
+    /// Steps every `tick_duration` until `running` is cleared. Ticks that fall behind are
+    /// dropped rather than caught up, so the game slows down instead of stalling.
+    pub fn run(&mut self, running: &AtomicBool) {
+        let interval = Duration::from_millis(self.tick_duration);
+        let mut next = Instant::now();
+        while running.load(Ordering::Relaxed) {
+            self.step();
+            next += interval;
+            let now = Instant::now();
+            if next > now {
+                std::thread::sleep(next - now);
+            } else {
+                next = now;
+            }
+        }
+    }
+
+    /// Runs the server on its own thread, e.g. to play against it from the same process.
+    pub fn spawn(self) -> Result<ServerHandle, NetError> {
+        let url = self.url();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+        let mut server = self;
+        let thread = std::thread::Builder::new()
+            .name("server".to_string())
+            .spawn(move || server.run(&thread_running))?;
+        Ok(ServerHandle {
+            url,
+            running,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn handshake(stream: TcpStream) -> Result<Socket, NetError> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let websocket = tungstenite::accept(stream).map_err(|e| NetError::Socket(e.to_string()))?;
+    Socket::spawn(websocket)
+}
+
+fn receive(
+    client_id: ClientId,
+    connection: &mut Connection,
+    state: &mut GameState,
+    replica: &Replica,
+) -> Result<(), NetError> {
+    while let Some(text) = connection.socket.receive()? {
+        let message = match decode::<ClientMessage>(&text) {
+            Ok(message) => message,
+            Err(e) => {
+                reply(
+                    connection,
+                    ServerMessage::Rejected {
+                        reason: e.to_string(),
+                    },
+                )?;
+                continue;
+            }
+        };
+        match message {
+            ClientMessage::Hello { version, name } => {
+                if version != PROTOCOL_VERSION {
+                    reply(
+                        connection,
+                        ServerMessage::Error {
+                            reason: format!(
+                                "Protocol version {} isn't supported, use {}",
+                                version, PROTOCOL_VERSION
+                            ),
+                        },
+                    )?;
+                    return Err(NetError::Version {
+                        found: version,
+                        supported: PROTOCOL_VERSION,
+                    });
+                }
+                log::info!("Client {} ({}) joined", client_id, name);
+                connection.name = Some(name);
+                // Matches the replica the next delta is diffed against
+                reply(
+                    connection,
+                    ServerMessage::Welcome {
+                        version: PROTOCOL_VERSION,
+                        client_id,
+                        tick: replica.tick,
+                        snapshot: Box::new(SaveData::from_state(state)),
+                    },
+                )?;
+            }
+            ClientMessage::Command { command } => {
+                if connection.name.is_none() {
+                    reply(
+                        connection,
+                        ServerMessage::Rejected {
+                            reason: "Say hello first".to_string(),
+                        },
+                    )?;
+                    continue;
+                }
+                match command.to_creator(state) {
+                    // Queued after whatever the entity is already doing
+                    Some(creator) => state.order(command.entity_id(), creator),
+                    None => reply(
+                        connection,
+                        ServerMessage::Rejected {
+                            reason: format!("Invalid command {:?}", command),
+                        },
+                    )?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn reply(connection: &mut Connection, message: ServerMessage) -> Result<(), NetError> {
+    connection.socket.send(encode(&message)?)
+}
+
+/// A server running on a background thread. Dropping the handle stops it.
+pub struct ServerHandle {
+    url: String,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,240 @@
+use crate::net::NetError;
+#[cfg(target_arch = "wasm32")]
+use std::cell::{Cell, RefCell};
+#[cfg(target_arch = "wasm32")]
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::TcpStream;
+#[cfg(target_arch = "wasm32")]
+use std::rc::Rc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use tungstenite::{Message, WebSocket};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{closure::Closure, JsCast};
+#[cfg(target_arch = "wasm32")]
+use web_sys::{CloseEvent, MessageEvent};
+
+// How long the native pump thread blocks on a read before checking for outgoing messages
+#[cfg(not(target_arch = "wasm32"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// Reads to wait for the peer to acknowledge a close before giving up on it
+#[cfg(not(target_arch = "wasm32"))]
+const CLOSE_TIMEOUT_POLLS: u32 = 200;
+
+#[cfg(not(target_arch = "wasm32"))]
+enum Outgoing {
+    Text(String),
+    Close,
+}
+
+/// A text WebSocket that never blocks the caller.
+///
+/// On native platforms a thread drives a `tungstenite` socket; on the web the browser does.
+/// Messages sent before the connection opens are queued.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Socket {
+    outgoing: Sender<Outgoing>,
+    incoming: Receiver<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Socket {
+    /// Connects to a `ws://` url. Blocks until the handshake is done.
+    pub fn connect(url: &str) -> Result<Socket, NetError> {
+        let (websocket, _) =
+            tungstenite::connect(url).map_err(|e| NetError::Socket(e.to_string()))?;
+        Socket::spawn(websocket)
+    }
+
+    /// Takes over a socket whose handshake is done, e.g. one accepted by a server.
+    pub fn spawn(websocket: WebSocket<TcpStream>) -> Result<Socket, NetError> {
+        websocket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+        let (outgoing_sender, outgoing_receiver) = channel();
+        let (incoming_sender, incoming_receiver) = channel();
+        std::thread::Builder::new()
+            .name("websocket".to_string())
+            .spawn(move || pump(websocket, outgoing_receiver, incoming_sender))?;
+        Ok(Socket {
+            outgoing: outgoing_sender,
+            incoming: incoming_receiver,
+        })
+    }
+
+    pub fn send(&mut self, text: String) -> Result<(), NetError> {
+        self.outgoing
+            .send(Outgoing::Text(text))
+            .map_err(|_| NetError::Closed)
+    }
+
+    /// The next received message, if any. Fails once the connection is closed and every
+    /// message has been read.
+    pub fn receive(&mut self) -> Result<Option<String>, NetError> {
+        match self.incoming.try_recv() {
+            Ok(text) => Ok(Some(text)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(NetError::Closed),
+        }
+    }
+
+    pub fn close(&mut self) {
+        let _ = self.outgoing.send(Outgoing::Close);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn pump(
+    mut websocket: WebSocket<TcpStream>,
+    outgoing: Receiver<Outgoing>,
+    incoming: Sender<String>,
+) {
+    let mut closing_polls = None;
+    loop {
+        match websocket.read_message() {
+            Ok(Message::Text(text)) => {
+                if incoming.send(text).is_err() {
+                    // Nobody is listening any more
+                    closing_polls = closing_polls.or(Some(0));
+                    let _ = websocket.close(None);
+                }
+            }
+            // Pings are answered and closes acknowledged by tungstenite itself
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                if let Some(polls) = closing_polls.as_mut() {
+                    *polls += 1;
+                    if *polls > CLOSE_TIMEOUT_POLLS {
+                        break;
+                    }
+                }
+            }
+            Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => {
+                break;
+            }
+            Err(e) => {
+                log::warn!("WebSocket failed: {}", e);
+                break;
+            }
+        }
+
+        if closing_polls.is_some() {
+            continue;
+        }
+        loop {
+            match outgoing.try_recv() {
+                Ok(Outgoing::Text(text)) => {
+                    if let Err(e) = websocket.write_message(Message::Text(text)) {
+                        log::warn!("Failed to send a message: {}", e);
+                        return;
+                    }
+                }
+                Ok(Outgoing::Close) | Err(TryRecvError::Disconnected) => {
+                    closing_polls = Some(0);
+                    let _ = websocket.close(None);
+                    break;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct Socket {
+    websocket: web_sys::WebSocket,
+    incoming: Rc<RefCell<VecDeque<String>>>,
+    closed: Rc<Cell<bool>>,
+    queued: Vec<String>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Socket {
+    /// Starts connecting to a `ws://` or `wss://` url.
+    pub fn connect(url: &str) -> Result<Socket, NetError> {
+        let websocket =
+            web_sys::WebSocket::new(url).map_err(|e| NetError::Socket(format!("{:?}", e)))?;
+        let incoming = Rc::new(RefCell::new(VecDeque::new()));
+        let closed = Rc::new(Cell::new(false));
+
+        let on_message = {
+            let incoming = Rc::clone(&incoming);
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                if let Some(text) = event.data().as_string() {
+                    incoming.borrow_mut().push_back(text);
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+        let on_close = {
+            let closed = Rc::clone(&closed);
+            Closure::wrap(Box::new(move |_: CloseEvent| {
+                closed.set(true);
+            }) as Box<dyn FnMut(CloseEvent)>)
+        };
+        websocket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        websocket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Socket {
+            websocket,
+            incoming,
+            closed,
+            queued: vec![],
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    pub fn send(&mut self, text: String) -> Result<(), NetError> {
+        self.queued.push(text);
+        self.flush()
+    }
+
+    pub fn receive(&mut self) -> Result<Option<String>, NetError> {
+        // Messages that arrived before the close are still handed out
+        let flushed = self.flush();
+        if let Some(text) = self.incoming.borrow_mut().pop_front() {
+            return Ok(Some(text));
+        }
+        flushed?;
+        if self.closed.get() {
+            return Err(NetError::Closed);
+        }
+        Ok(None)
+    }
+
+    pub fn close(&mut self) {
+        let _ = self.websocket.close();
+    }
+
+    fn flush(&mut self) -> Result<(), NetError> {
+        match self.websocket.ready_state() {
+            web_sys::WebSocket::CONNECTING => Ok(()),
+            web_sys::WebSocket::OPEN => {
+                for text in self.queued.drain(..) {
+                    self.websocket
+                        .send_with_str(&text)
+                        .map_err(|e| NetError::Socket(format!("{:?}", e)))?;
+                }
+                Ok(())
+            }
+            _ => Err(NetError::Closed),
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.websocket.set_onmessage(None);
+        self.websocket.set_onclose(None);
+        let _ = self.websocket.close();
+    }
+}
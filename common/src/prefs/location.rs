@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+/// Directory name used under the platform's config directory.
+pub const APP_DIRECTORY: &str = "tearchan-example";
+
+thread_local! {
+    static DIRECTORY_OVERRIDE: RefCell<Option<PathBuf>> = RefCell::new(None);
+}
+
+/// Stores preferences in `directory` instead of the platform default. Android has to call
+/// this with the activity's internal data path, since only the activity knows it.
+pub fn configure_preferences_directory(directory: PathBuf) {
+    DIRECTORY_OVERRIDE.with(|current| *current.borrow_mut() = Some(directory));
+}
+
+/// Where preferences are kept on this platform, or `None` if it can't be found out.
+pub fn preferences_directory() -> Option<PathBuf> {
+    if let Some(directory) = DIRECTORY_OVERRIDE.with(|current| current.borrow().clone()) {
+        return Some(directory);
+    }
+    platform_directory()
+}
+
+#[cfg(target_os = "ios")]
+fn platform_directory() -> Option<PathBuf> {
+    // HOME is the app's sandbox container; Documents is backed up with the app
+    Some(env_path("HOME")?.join("Documents"))
+}
+
+#[cfg(target_os = "android")]
+fn platform_directory() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn platform_directory() -> Option<PathBuf> {
+    Some(
+        env_path("HOME")?
+            .join("Library")
+            .join("Application Support")
+            .join(APP_DIRECTORY),
+    )
+}
+
+#[cfg(target_os = "windows")]
+fn platform_directory() -> Option<PathBuf> {
+    Some(env_path("APPDATA")?.join(APP_DIRECTORY))
+}
+
+#[cfg(not(any(
+    target_os = "ios",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "windows",
+    target_arch = "wasm32"
+)))]
+fn platform_directory() -> Option<PathBuf> {
+    let config = env_path("XDG_CONFIG_HOME").or_else(|| Some(env_path("HOME")?.join(".config")))?;
+    Some(config.join(APP_DIRECTORY))
+}
+
+// The web keeps preferences in localStorage, not in a directory
+#[cfg(target_arch = "wasm32")]
+fn platform_directory() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+fn env_path(name: &str) -> Option<PathBuf> {
+    // Relative or empty values are ignored, as the XDG spec asks
+    let path = PathBuf::from(std::env::var_os(name)?);
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,467 @@
+pub mod location;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::prefs::location::preferences_directory;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::save::storage::FileStorage;
+#[cfg(target_arch = "wasm32")]
+use crate::save::storage::LocalStorage;
+use crate::save::storage::Storage;
+use crate::save::SaveError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+/// Name the preferences are stored under, i.e. `preferences.json` in a directory.
+pub const PREFERENCES_NAME: &str = "preferences";
+
+#[derive(Debug)]
+pub enum PreferencesError {
+    Json(serde_json::Error),
+    Storage(SaveError),
+    /// The stored value doesn't have the requested type.
+    Type {
+        key: String,
+        error: serde_json::Error,
+    },
+    /// The platform has no known place for preferences.
+    NoLocation,
+}
+
+impl std::fmt::Display for PreferencesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreferencesError::Json(e) => write!(f, "Invalid preferences: {}", e),
+            PreferencesError::Storage(e) => write!(f, "{}", e),
+            PreferencesError::Type { key, error } => {
+                write!(f, "Preference {:?} has another type: {}", key, error)
+            }
+            PreferencesError::NoLocation => write!(f, "No place to store preferences"),
+        }
+    }
+}
+
+impl std::error::Error for PreferencesError {}
+
+impl From<serde_json::Error> for PreferencesError {
+    fn from(e: serde_json::Error) -> Self {
+        PreferencesError::Json(e)
+    }
+}
+
+impl From<SaveError> for PreferencesError {
+    fn from(e: SaveError) -> Self {
+        PreferencesError::Storage(e)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreferenceChange {
+    pub key: String,
+    /// `None` when the key was added.
+    pub old: Option<Value>,
+    /// `None` when the key was removed.
+    pub new: Option<Value>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(u64);
+
+type Listener = Box<dyn FnMut(&PreferenceChange)>;
+
+/// Typed key-value preferences, written through to storage on every change.
+///
+/// Values are stored as JSON, so anything serializable can be kept; `get` fails if the
+/// stored value doesn't deserialize as the requested type.
+pub struct Preferences {
+    storage: Box<dyn Storage>,
+    values: BTreeMap<String, Value>,
+    listeners: BTreeMap<SubscriptionId, Listener>,
+    next_subscription: u64,
+    /// Removed on drop; set by `temporary`.
+    #[cfg(not(target_arch = "wasm32"))]
+    temporary_directory: Option<PathBuf>,
+}
+
+impl Preferences {
+    /// Opens the preferences in the platform's usual place: a JSON file in the config
+    /// directory on desktop, the app's documents on iOS, internal storage on Android and
+    /// `localStorage` on the web.
+    pub fn open() -> Result<Self, PreferencesError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let directory = preferences_directory().ok_or(PreferencesError::NoLocation)?;
+            Preferences::with_storage(Box::new(FileStorage::new(directory)))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            Preferences::with_storage(Box::new(LocalStorage::new("")))
+        }
+    }
+
+    /// Opens fresh, empty preferences in a new directory under the system temp directory,
+    /// e.g. for tests. The directory is removed when the preferences are dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn temporary() -> Result<Self, PreferencesError> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let directory = std::env::temp_dir().join(format!(
+            "preferences-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut preferences = Preferences::with_storage(Box::new(FileStorage::new(&directory)))?;
+        preferences.temporary_directory = Some(directory);
+        Ok(preferences)
+    }
+
+    pub fn with_storage(storage: Box<dyn Storage>) -> Result<Self, PreferencesError> {
+        let values = read_values(storage.as_ref())?;
+        Ok(Preferences {
+            storage,
+            values,
+            listeners: BTreeMap::new(),
+            next_subscription: 1,
+            #[cfg(not(target_arch = "wasm32"))]
+            temporary_directory: None,
+        })
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, PreferencesError> {
+        match self.values.get(key) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map(Some)
+                .map_err(|error| PreferencesError::Type {
+                    key: key.to_string(),
+                    error,
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// The stored value, or `default` if it is missing or has another type.
+    pub fn get_or<T: DeserializeOwned>(&self, key: &str, default: T) -> T {
+        match self.get(key) {
+            Ok(Some(value)) => value,
+            Ok(None) => default,
+            Err(e) => {
+                log::warn!("{}", e);
+                default
+            }
+        }
+    }
+
+    /// Stores `value` and notifies subscribers if it changed. Nothing changes if it can't be
+    /// written to storage.
+    pub fn set<T: Serialize>(&mut self, key: &str, value: &T) -> Result<(), PreferencesError> {
+        let value = serde_json::to_value(value)?;
+        if self.values.get(key) == Some(&value) {
+            return Ok(());
+        }
+        let old = self.values.insert(key.to_string(), value.clone());
+        if let Err(e) = self.save() {
+            match &old {
+                Some(old) => self.values.insert(key.to_string(), old.clone()),
+                None => self.values.remove(key),
+            };
+            return Err(e);
+        }
+        self.notify(&PreferenceChange {
+            key: key.to_string(),
+            old,
+            new: Some(value),
+        });
+        Ok(())
+    }
+
+    /// Returns whether the key existed. The key stays if the removal can't be written to
+    /// storage.
+    pub fn remove(&mut self, key: &str) -> Result<bool, PreferencesError> {
+        let old = match self.values.remove(key) {
+            Some(old) => old,
+            None => return Ok(false),
+        };
+        if let Err(e) = self.save() {
+            self.values.insert(key.to_string(), old);
+            return Err(e);
+        }
+        self.notify(&PreferenceChange {
+            key: key.to_string(),
+            old: Some(old),
+            new: None,
+        });
+        Ok(true)
+    }
+
+    /// Calls `listener` after every change, including those found by `reload`.
+    pub fn subscribe<F>(&mut self, listener: F) -> SubscriptionId
+    where
+        F: FnMut(&PreferenceChange) + 'static,
+    {
+        let id = SubscriptionId(self.next_subscription);
+        self.next_subscription += 1;
+        self.listeners.insert(id, Box::new(listener));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        self.listeners.remove(&id);
+    }
+
+    /// Re-reads storage, e.g. after another process or tab changed it, and notifies
+    /// subscribers of every key that differs.
+    pub fn reload(&mut self) -> Result<(), PreferencesError> {
+        let values = read_values(self.storage.as_ref())?;
+        let old = std::mem::replace(&mut self.values, values);
+        let mut changes = vec![];
+        for (key, old_value) in old.iter() {
+            let new_value = self.values.get(key);
+            if new_value != Some(old_value) {
+                changes.push(PreferenceChange {
+                    key: key.clone(),
+                    old: Some(old_value.clone()),
+                    new: new_value.cloned(),
+                });
+            }
+        }
+        for (key, new_value) in self.values.iter() {
+            if !old.contains_key(key) {
+                changes.push(PreferenceChange {
+                    key: key.clone(),
+                    old: None,
+                    new: Some(new_value.clone()),
+                });
+            }
+        }
+        for change in changes.iter() {
+            self.notify(change);
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), PreferencesError> {
+        let bytes = serde_json::to_vec_pretty(&self.values)?;
+        self.storage.write(PREFERENCES_NAME, &bytes)?;
+        Ok(())
+    }
+
+    fn notify(&mut self, change: &PreferenceChange) {
+        for listener in self.listeners.values_mut() {
+            listener(change);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for Preferences {
+    fn drop(&mut self) {
+        if let Some(directory) = self.temporary_directory.take() {
+            if let Err(e) = std::fs::remove_dir_all(&directory) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to remove {}: {}", directory.display(), e);
+                }
+            }
+        }
+    }
+}
+
+fn read_values(storage: &dyn Storage) -> Result<BTreeMap<String, Value>, PreferencesError> {
+    match storage.read(PREFERENCES_NAME)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    /// Keeps the preferences in memory and fails writes on request.
+    #[derive(Clone, Default)]
+    struct FlakyStorage {
+        bytes: Rc<RefCell<Option<Vec<u8>>>>,
+        failing: Rc<Cell<bool>>,
+    }
+
+    impl Storage for FlakyStorage {
+        fn read(&self, _name: &str) -> Result<Option<Vec<u8>>, SaveError> {
+            Ok(self.bytes.borrow().clone())
+        }
+
+        fn write(&self, _name: &str, bytes: &[u8]) -> Result<(), SaveError> {
+            if self.failing.get() {
+                return Err(SaveError::Storage("disk full".to_string()));
+            }
+            *self.bytes.borrow_mut() = Some(bytes.to_vec());
+            Ok(())
+        }
+
+        fn remove(&self, _name: &str) -> Result<(), SaveError> {
+            *self.bytes.borrow_mut() = None;
+            Ok(())
+        }
+    }
+
+    fn record_changes(preferences: &mut Preferences) -> Rc<RefCell<Vec<PreferenceChange>>> {
+        let changes = Rc::new(RefCell::new(vec![]));
+        let recorded = Rc::clone(&changes);
+        preferences.subscribe(move |change| recorded.borrow_mut().push(change.clone()));
+        changes
+    }
+
+    #[test]
+    fn gets_what_was_set() {
+        let mut preferences = Preferences::temporary().unwrap();
+        preferences.set("volume", &0.5f32).unwrap();
+        preferences.set("name", &"player").unwrap();
+        preferences.set("size", &(640u32, 480u32)).unwrap();
+
+        assert_eq!(preferences.get::<f32>("volume").unwrap(), Some(0.5f32));
+        assert_eq!(
+            preferences.get::<String>("name").unwrap(),
+            Some("player".to_string())
+        );
+        assert_eq!(
+            preferences.get::<(u32, u32)>("size").unwrap(),
+            Some((640, 480))
+        );
+        assert_eq!(preferences.get::<f32>("missing").unwrap(), None);
+        assert_eq!(
+            preferences.keys().cloned().collect::<Vec<_>>(),
+            vec!["name", "size", "volume"]
+        );
+    }
+
+    #[test]
+    fn reports_values_of_another_type() {
+        let mut preferences = Preferences::temporary().unwrap();
+        preferences.set("name", &"player").unwrap();
+        match preferences.get::<u32>("name") {
+            Err(PreferencesError::Type { key, .. }) => assert_eq!(key, "name"),
+            result => panic!("Expected a type error, got {:?}", result),
+        }
+        assert_eq!(preferences.get_or("name", 7u32), 7);
+        assert_eq!(preferences.get_or("missing", 7u32), 7);
+    }
+
+    #[test]
+    fn persists_across_reopening() {
+        let mut preferences = Preferences::temporary().unwrap();
+        preferences.set("volume", &0.25f32).unwrap();
+        preferences.set("muted", &true).unwrap();
+        assert!(preferences.remove("muted").unwrap());
+        assert!(!preferences.remove("muted").unwrap());
+
+        let directory = preferences.temporary_directory.clone().unwrap();
+        let reopened = Preferences::with_storage(Box::new(FileStorage::new(directory))).unwrap();
+        assert_eq!(reopened.get::<f32>("volume").unwrap(), Some(0.25f32));
+        assert!(!reopened.contains("muted"));
+    }
+
+    #[test]
+    fn notifies_subscribers_of_changes() {
+        let storage = FlakyStorage::default();
+        let mut preferences = Preferences::with_storage(Box::new(storage.clone())).unwrap();
+        let changes = record_changes(&mut preferences);
+
+        preferences.set("volume", &1).unwrap();
+        preferences.set("volume", &1).unwrap();
+        preferences.set("volume", &2).unwrap();
+        preferences.remove("volume").unwrap();
+        assert_eq!(
+            *changes.borrow(),
+            vec![
+                PreferenceChange {
+                    key: "volume".to_string(),
+                    old: None,
+                    new: Some(1.into()),
+                },
+                PreferenceChange {
+                    key: "volume".to_string(),
+                    old: Some(1.into()),
+                    new: Some(2.into()),
+                },
+                PreferenceChange {
+                    key: "volume".to_string(),
+                    old: Some(2.into()),
+                    new: None,
+                },
+            ]
+        );
+
+        // Another process rewrites the file
+        preferences.set("kept", &true).unwrap();
+        preferences.set("dropped", &true).unwrap();
+        changes.borrow_mut().clear();
+        *storage.bytes.borrow_mut() = Some(br#"{"kept": true, "added": 3}"#.to_vec());
+        preferences.reload().unwrap();
+        assert_eq!(
+            *changes.borrow(),
+            vec![
+                PreferenceChange {
+                    key: "dropped".to_string(),
+                    old: Some(true.into()),
+                    new: None,
+                },
+                PreferenceChange {
+                    key: "added".to_string(),
+                    old: None,
+                    new: Some(3.into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_writes_change_nothing() {
+        let storage = FlakyStorage::default();
+        let mut preferences = Preferences::with_storage(Box::new(storage.clone())).unwrap();
+        preferences.set("volume", &1).unwrap();
+        let changes = record_changes(&mut preferences);
+
+        storage.failing.set(true);
+        assert!(preferences.set("volume", &2).is_err());
+        assert!(preferences.set("name", &"player").is_err());
+        assert!(preferences.remove("volume").is_err());
+        assert_eq!(preferences.get::<i32>("volume").unwrap(), Some(1));
+        assert!(!preferences.contains("name"));
+        assert!(changes.borrow().is_empty());
+
+        storage.failing.set(false);
+        preferences.set("volume", &2).unwrap();
+        assert_eq!(changes.borrow().len(), 1);
+    }
+
+    #[test]
+    fn temporary_preferences_clean_up_after_themselves() {
+        let mut preferences = Preferences::temporary().unwrap();
+        preferences.set("volume", &1).unwrap();
+        let directory = preferences.temporary_directory.clone().unwrap();
+        assert!(directory.join("preferences.json").is_file());
+
+        let other = Preferences::temporary().unwrap();
+        assert_ne!(other.temporary_directory.as_ref(), Some(&directory));
+
+        drop(preferences);
+        assert!(!directory.exists());
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Smallest buffer a batch allocates, in elements.
+const MIN_CAPACITY: u32 = 64;
+
+/// Hands out slot indices, reusing freed ones before growing.
+#[derive(Clone, Debug, Default)]
+pub struct SlotAllocator {
+    free: Vec<u32>,
+    len: u32,
+}
+
+impl SlotAllocator {
+    pub fn new() -> Self {
+        SlotAllocator::default()
+    }
+
+    pub fn allocate(&mut self) -> u32 {
+        match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.len += 1;
+                self.len - 1
+            }
+        }
+    }
+
+    pub fn free(&mut self, slot: u32) {
+        self.free.push(slot);
+    }
+
+    /// One past the highest slot ever allocated.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn in_use(&self) -> u32 {
+        self.len - self.free.len() as u32
+    }
+}
+
+/// A persistent GPU buffer holding one element per key, e.g. one instance per entity.
+///
+/// Elements are diffed against a CPU copy, so `flush` only uploads the slots that changed.
+/// Removed keys leave their slot holding `V::default()` until it is reused, so `V::default()`
+/// must draw nothing (a zero-sized instance, for example).
+pub struct RenderBatch<K, V> {
+    label: &'static str,
+    usage: wgpu::BufferUsage,
+    slots: BTreeMap<K, u32>,
+    allocator: SlotAllocator,
+    elements: Vec<V>,
+    dirty: BTreeSet<u32>,
+    buffer: Option<wgpu::Buffer>,
+    capacity: u32,
+}
+
+impl<K, V> RenderBatch<K, V>
+where
+    K: Ord + Copy,
+    V: bytemuck::Pod + PartialEq + Default,
+{
+    pub fn new(label: &'static str, usage: wgpu::BufferUsage) -> Self {
+        RenderBatch {
+            label,
+            usage: usage | wgpu::BufferUsage::COPY_DST,
+            slots: BTreeMap::new(),
+            allocator: SlotAllocator::new(),
+            elements: vec![],
+            dirty: BTreeSet::new(),
+            buffer: None,
+            capacity: 0,
+        }
+    }
+
+    /// Stores `value` for `key`, returning whether anything changed.
+    pub fn set(&mut self, key: K, value: V) -> bool {
+        let slot = match self.slots.get(&key) {
+            Some(slot) => *slot,
+            None => {
+                let slot = self.allocator.allocate();
+                self.slots.insert(key, slot);
+                if slot as usize >= self.elements.len() {
+                    self.elements.resize(slot as usize + 1, V::default());
+                }
+                slot
+            }
+        };
+        if self.elements[slot as usize] == value {
+            return false;
+        }
+        self.elements[slot as usize] = value;
+        self.dirty.insert(slot);
+        true
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some(slot) = self.slots.remove(key) {
+            self.elements[slot as usize] = V::default();
+            self.dirty.insert(slot);
+            self.allocator.free(slot);
+        }
+    }
+
+    /// Makes the batch hold exactly `values`: new and changed keys are written and keys that
+    /// are missing are removed.
+    pub fn sync<I>(&mut self, values: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut seen = BTreeSet::new();
+        for (key, value) in values {
+            seen.insert(key);
+            self.set(key, value);
+        }
+        let removed = self
+            .slots
+            .keys()
+            .filter(|key| !seen.contains(key))
+            .copied()
+            .collect::<Vec<_>>();
+        for key in removed.iter() {
+            self.remove(key);
+        }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.slots.contains_key(key)
+    }
+
+    /// Number of slots to draw, including empty ones left by removed keys.
+    pub fn len(&self) -> u32 {
+        self.allocator.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocator.is_empty()
+    }
+
+    /// Slots waiting for the next `flush`.
+    pub fn dirty_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> {
+        self.buffer.as_ref()
+    }
+
+    /// Uploads changed slots, merging neighbors into one write. The buffer is recreated with
+    /// twice the room when the batch outgrows it.
+    pub fn flush(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let len = self.allocator.len();
+        if self.buffer.is_none() || len > self.capacity {
+            let capacity = len.next_power_of_two().max(MIN_CAPACITY);
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(self.label),
+                size: capacity as u64 * std::mem::size_of::<V>() as u64,
+                usage: self.usage,
+                mapped_at_creation: false,
+            });
+            if !self.elements.is_empty() {
+                queue.write_buffer(&buffer, 0, bytemuck::cast_slice(&self.elements));
+            }
+            self.buffer = Some(buffer);
+            self.capacity = capacity;
+            self.dirty.clear();
+            return;
+        }
+
+        let buffer = match &self.buffer {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let element_size = std::mem::size_of::<V>() as u64;
+        let mut dirty = self.dirty.iter().copied().peekable();
+        while let Some(start) = dirty.next() {
+            let mut end = start + 1;
+            while dirty.peek() == Some(&end) {
+                dirty.next();
+                end += 1;
+            }
+            queue.write_buffer(
+                buffer,
+                start as u64 * element_size,
+                bytemuck::cast_slice(&self.elements[start as usize..end as usize]),
+            );
+        }
+        self.dirty.clear();
+    }
+}
+
+/// One quad drawn by `shaders/batch.vert`, lying on the ground plane.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BatchInstance {
+    /// Map coordinates; `y` runs along world `z`.
+    pub position: [f32; 2],
+    pub angle: f32,
+    /// Half the side of the quad. Zero hides the instance.
+    pub size: f32,
+    pub texcoord: [f32; 2],
+}
+
+unsafe impl bytemuck::Zeroable for BatchInstance {}
+unsafe impl bytemuck::Pod for BatchInstance {}
+
+pub const BATCH_INSTANCE_ATTRIBUTES: [wgpu::VertexAttributeDescriptor; 4] = [
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float2,
+        offset: 0,
+        shader_location: 1,
+    },
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float,
+        offset: 8,
+        shader_location: 2,
+    },
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float,
+        offset: 12,
+        shader_location: 3,
+    },
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float2,
+        offset: 16,
+        shader_location: 4,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch() -> RenderBatch<u32, u32> {
+        RenderBatch::new("test", wgpu::BufferUsage::VERTEX)
+    }
+
+    fn dirty(batch: &RenderBatch<u32, u32>) -> Vec<u32> {
+        batch.dirty.iter().copied().collect()
+    }
+
+    #[test]
+    fn allocator_reuses_freed_slots() {
+        let mut allocator = SlotAllocator::new();
+        assert!(allocator.is_empty());
+        assert_eq!(
+            (0..3).map(|_| allocator.allocate()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        allocator.free(1);
+        assert_eq!((allocator.len(), allocator.in_use()), (3, 2));
+
+        assert_eq!(allocator.allocate(), 1);
+        assert_eq!(allocator.allocate(), 3);
+        assert_eq!((allocator.len(), allocator.in_use()), (4, 4));
+    }
+
+    #[test]
+    fn set_only_marks_changed_slots() {
+        let mut batch = batch();
+        assert!(batch.set(10, 1));
+        assert!(batch.set(20, 2));
+        assert_eq!(dirty(&batch), vec![0, 1]);
+
+        batch.dirty.clear();
+        assert!(!batch.set(10, 1));
+        assert!(batch.set(20, 3));
+        assert_eq!(dirty(&batch), vec![1]);
+        assert_eq!(batch.elements, vec![1, 3]);
+    }
+
+    #[test]
+    fn removed_slots_are_cleared_and_reused() {
+        let mut batch = batch();
+        batch.set(10, 1);
+        batch.set(20, 2);
+        batch.set(30, 3);
+        batch.dirty.clear();
+
+        batch.remove(&20);
+        batch.remove(&40);
+        assert!(!batch.contains(&20));
+        assert_eq!(dirty(&batch), vec![1]);
+        assert_eq!(batch.elements, vec![1, 0, 3]);
+        // Removed slots are still drawn, empty, until they are reused
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch.allocator.in_use(), 2);
+
+        batch.set(40, 4);
+        assert_eq!(batch.slots.get(&40), Some(&1));
+        assert_eq!(batch.elements, vec![1, 4, 3]);
+        assert_eq!(batch.len(), 3);
+    }
+
+    #[test]
+    fn sync_removes_missing_keys() {
+        let mut batch = batch();
+        batch.sync(vec![(10, 1), (20, 2), (30, 3)]);
+        batch.dirty.clear();
+
+        batch.sync(vec![(10, 1), (30, 5)]);
+        assert!(batch.contains(&10));
+        assert!(!batch.contains(&20));
+        assert!(batch.contains(&30));
+        assert_eq!(dirty(&batch), vec![1, 2]);
+        assert_eq!(batch.elements, vec![1, 0, 5]);
+        assert_eq!(batch.allocator.in_use(), 2);
+
+        batch.sync(vec![]);
+        assert_eq!(batch.allocator.in_use(), 0);
+        assert_eq!(batch.elements, vec![0, 0, 0]);
+        assert!(!batch.is_empty());
+    }
+}
@@ -0,0 +1,433 @@
+/// Smallest vertex buffer the renderer allocates, in vertices.
+const MIN_VERTEX_CAPACITY: u64 = 1024;
+
+/// A screen-space rectangle in pixels, from the top left corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+}
+
+/// One corner of a quad drawn by `shaders/quad.vert`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QuadVertex {
+    /// Pixels from the top left corner of the screen.
+    pub position: [f32; 2],
+    pub texcoord: [f32; 2],
+    /// Multiplied with the texel, so a white texel draws a solid color.
+    pub color: [f32; 4],
+}
+
+unsafe impl bytemuck::Zeroable for QuadVertex {}
+unsafe impl bytemuck::Pod for QuadVertex {}
+
+pub const QUAD_VERTEX_ATTRIBUTES: [wgpu::VertexAttributeDescriptor; 3] = [
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float2,
+        offset: 0,
+        shader_location: 0,
+    },
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float2,
+        offset: 8,
+        shader_location: 1,
+    },
+    wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float4,
+        offset: 16,
+        shader_location: 2,
+    },
+];
+
+/// Quads to draw with one texture, in order.
+#[derive(Clone, Debug, Default)]
+pub struct QuadList {
+    vertices: Vec<QuadVertex>,
+}
+
+impl QuadList {
+    pub fn new() -> Self {
+        QuadList::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[QuadVertex] {
+        &self.vertices
+    }
+
+    /// Draws the `texcoords` part of the texture stretched over `rect`.
+    pub fn push(&mut self, rect: Rect, texcoords: Rect, color: [f32; 4]) {
+        let corner = |x: f32, y: f32, u: f32, v: f32| QuadVertex {
+            position: [x, y],
+            texcoord: [u, v],
+            color,
+        };
+        let top_left = corner(rect.x, rect.y, texcoords.x, texcoords.y);
+        let top_right = corner(rect.right(), rect.y, texcoords.right(), texcoords.y);
+        let bottom_left = corner(rect.x, rect.bottom(), texcoords.x, texcoords.bottom());
+        let bottom_right = corner(
+            rect.right(),
+            rect.bottom(),
+            texcoords.right(),
+            texcoords.bottom(),
+        );
+        self.vertices.extend_from_slice(&[
+            top_left,
+            bottom_left,
+            bottom_right,
+            top_left,
+            bottom_right,
+            top_right,
+        ]);
+    }
+
+    /// Moves the quads of `other` into this list, after the ones already here.
+    pub fn append(&mut self, other: &mut QuadList) {
+        self.vertices.append(&mut other.vertices);
+    }
+
+    /// Inserts `other` before the quads already here, e.g. to put a background behind
+    /// contents that were laid out first.
+    pub fn prepend(&mut self, other: &QuadList) {
+        self.vertices.splice(0..0, other.vertices.iter().copied());
+    }
+}
+
+/// An RGBA texture quads can be drawn with. Its texels can be rewritten, e.g. to add glyphs
+/// to an atlas.
+pub struct QuadTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+}
+
+impl QuadTexture {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Replaces a `width` by `height` block of texels at `x`, `y` with tightly packed `rgba`.
+    pub fn write(&self, queue: &wgpu::Queue, x: u32, y: u32, width: u32, height: u32, rgba: &[u8]) {
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            rgba,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * width,
+                rows_per_image: 0,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        );
+    }
+
+    /// Texture coordinates of the texel block at `x`, `y`.
+    pub fn texcoords(&self, x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect::new(
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            width as f32 / self.width as f32,
+            height as f32 / self.height as f32,
+        )
+    }
+}
+
+/// Draws textured, colored quads in screen space over whatever is already in the frame.
+/// Used for overlays, text and menus.
+pub struct QuadRenderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    vertex_buffer: Option<wgpu::Buffer>,
+    vertex_capacity: u64,
+}
+
+impl QuadRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Quad"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(8),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Quad Uniform Buffer"),
+            size: 8,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!(
+            "../../../target/shaders/quad.vert.spv"
+        ));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!(
+            "../../../target/shaders/quad.frag.spv"
+        ));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Quad"),
+            layout: Some(&pipeline_layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format,
+                color_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: None,
+                vertex_buffers: &[wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<QuadVertex>() as u64,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &QUAD_VERTEX_ATTRIBUTES,
+                }],
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        QuadRenderer {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            vertex_buffer: None,
+            vertex_capacity: 0,
+        }
+    }
+
+    /// Creates a transparent texture. `filter` is `Nearest` for pixel art and bitmap fonts,
+    /// `Linear` for anything drawn at fractional sizes.
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        width: u32,
+        height: u32,
+        filter: wgpu::FilterMode,
+    ) -> QuadTexture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some(label),
+        });
+        QuadTexture {
+            texture,
+            bind_group,
+            width,
+            height,
+        }
+    }
+
+    /// Draws each list with its texture, in order, over the contents of `view`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        screen_size: (u32, u32),
+        layers: &[(&QuadTexture, &QuadList)],
+    ) {
+        let vertex_count = layers
+            .iter()
+            .map(|(_, list)| list.vertices.len() as u64)
+            .sum::<u64>();
+        if vertex_count == 0 {
+            return;
+        }
+
+        let vertex_size = std::mem::size_of::<QuadVertex>() as u64;
+        if self.vertex_buffer.is_none() || vertex_count > self.vertex_capacity {
+            let capacity = vertex_count.next_power_of_two().max(MIN_VERTEX_CAPACITY);
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Quad Vertex Buffer"),
+                size: capacity * vertex_size,
+                usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            }));
+            self.vertex_capacity = capacity;
+        }
+        let vertex_buffer = match &self.vertex_buffer {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        let mut offset = 0u64;
+        for (_, list) in layers.iter().filter(|(_, list)| !list.is_empty()) {
+            queue.write_buffer(
+                vertex_buffer,
+                offset * vertex_size,
+                bytemuck::cast_slice(&list.vertices),
+            );
+            offset += list.vertices.len() as u64;
+        }
+        let (width, height) = screen_size;
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[width as f32, height as f32]),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Quads"),
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            let mut start = 0u32;
+            for (texture, list) in layers.iter().filter(|(_, list)| !list.is_empty()) {
+                let end = start + list.vertices.len() as u32;
+                rpass.set_bind_group(0, &texture.bind_group, &[]);
+                rpass.draw(start..end, 0..1);
+                start = end;
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,229 @@
+pub mod storage;
+
+use crate::action::character::{CustomActionCreatorState, CustomActionState};
+use crate::action::ActionScheduler;
+use crate::ecs::{ComponentGroup, EntityId, EntityManager};
+use crate::game::component::{Angle, CharacterStatus, Item, Position};
+use crate::game::GameState;
+use crate::map::path::MapPath;
+use crate::map::Ground;
+use crate::save::storage::Storage;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Bumped whenever what a save holds changes shape.
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Json(serde_json::Error),
+    Io(std::io::Error),
+    /// The save was written by a newer build.
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+    NotFound(String),
+    Storage(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Json(e) => write!(f, "Invalid save data: {}", e),
+            SaveError::Io(e) => write!(f, "Failed to access the save: {}", e),
+            SaveError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Save version {} is newer than the supported version {}",
+                found, supported
+            ),
+            SaveError::NotFound(name) => write!(f, "No save named {:?}", name),
+            SaveError::Storage(e) => write!(f, "Storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Json(e)
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// What a save file holds: the version it was written with and the saved `data`.
+#[derive(Serialize, Deserialize)]
+struct SaveFile<T> {
+    version: u32,
+    data: T,
+}
+
+/// Everything needed to rebuild a `GameState`. The path finder is rebuilt from the ground.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub next_entity_id: EntityId,
+    pub entities: BTreeSet<EntityId>,
+    pub positions: ComponentGroup<Position>,
+    pub angles: ComponentGroup<Angle>,
+    pub character_status: ComponentGroup<CharacterStatus>,
+    pub items: ComponentGroup<Item>,
+    pub ground: Ground,
+    pub actions: ActionScheduler<CustomActionState, CustomActionCreatorState>,
+}
+
+impl SaveData {
+    pub fn from_state(state: &GameState) -> Self {
+        SaveData {
+            next_entity_id: state.entity_manager.next_id(),
+            entities: state.entities.clone(),
+            positions: state.positions.clone(),
+            angles: state.angles.clone(),
+            character_status: state.character_status.clone(),
+            items: state.items.clone(),
+            ground: state.ground.clone(),
+            actions: state.actions.clone(),
+        }
+    }
+
+    pub fn into_state(self) -> GameState {
+        let mut state = GameState::new(self.ground);
+        state.entity_manager = EntityManager::starting_at(self.next_entity_id);
+        state.entities = self.entities;
+        state.positions = self.positions;
+        state.angles = self.angles;
+        state.character_status = self.character_status;
+        state.items = self.items;
+        state.map_path = MapPath::new(&state.ground);
+        state.actions = self.actions;
+        state
+    }
+}
+
+/// Encodes `data`, e.g. a `SaveData` or a game's own save built around one.
+pub fn encode_save<T: Serialize>(data: &T) -> Result<Vec<u8>, SaveError> {
+    Ok(serde_json::to_vec(&SaveFile {
+        version: SAVE_VERSION,
+        data,
+    })?)
+}
+
+/// Reads a save written by `encode_save`, rejecting saves from newer builds.
+pub fn decode_save<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SaveError> {
+    let header: SaveHeader = serde_json::from_slice(bytes)?;
+    if header.version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion {
+            found: header.version,
+            supported: SAVE_VERSION,
+        });
+    }
+    let file: SaveFile<T> = serde_json::from_slice(bytes)?;
+    Ok(file.data)
+}
+
+pub fn save_game_state(state: &GameState) -> Result<Vec<u8>, SaveError> {
+    encode_save(&SaveData::from_state(state))
+}
+
+pub fn load_game_state(bytes: &[u8]) -> Result<GameState, SaveError> {
+    Ok(decode_save::<SaveData>(bytes)?.into_state())
+}
+
+/// Writes `data` to `storage` under `name`, replacing any previous save.
+pub fn save_to<T: Serialize>(storage: &dyn Storage, name: &str, data: &T) -> Result<(), SaveError> {
+    storage.write(name, &encode_save(data)?)
+}
+
+pub fn load_from<T: DeserializeOwned>(storage: &dyn Storage, name: &str) -> Result<T, SaveError> {
+    match storage.read(name)? {
+        Some(bytes) => decode_save(&bytes),
+        None => Err(SaveError::NotFound(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::demo::create_demo_world;
+
+    fn busy_state() -> GameState {
+        let mut world = create_demo_world();
+        for _ in 0..40 {
+            world.update(100);
+        }
+        world.state
+    }
+
+    #[test]
+    fn round_trips_game_states() {
+        let state = busy_state();
+        let data = SaveData::from_state(&state);
+        let bytes = save_game_state(&state).unwrap();
+        assert_eq!(decode_save::<SaveData>(&bytes).unwrap(), data);
+
+        let mut loaded = load_game_state(&bytes).unwrap();
+        assert_eq!(SaveData::from_state(&loaded), data);
+        assert_eq!(
+            loaded.entity_manager.next_id(),
+            state.entity_manager.next_id()
+        );
+        assert_eq!(
+            loaded.map_path.allow_diagonal,
+            state.map_path.allow_diagonal
+        );
+
+        // The loaded world carries on exactly like the original
+        let mut state = state;
+        for _ in 0..20 {
+            state.update(100);
+            loaded.update(100);
+        }
+        assert_eq!(SaveData::from_state(&loaded), SaveData::from_state(&state));
+    }
+
+    #[test]
+    fn rejects_saves_from_newer_builds() {
+        let bytes = save_game_state(&busy_state()).unwrap();
+        let mut value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        value["version"] = (SAVE_VERSION + 1).into();
+        let bytes = serde_json::to_vec(&value).unwrap();
+        match decode_save::<SaveData>(&bytes) {
+            Err(SaveError::UnsupportedVersion { found, supported }) => {
+                assert_eq!(found, SAVE_VERSION + 1);
+                assert_eq!(supported, SAVE_VERSION);
+            }
+            result => panic!("Expected UnsupportedVersion, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn reports_missing_and_corrupt_saves() {
+        let directory = std::env::temp_dir().join(format!("save-test-{}", std::process::id()));
+        let storage = crate::save::storage::FileStorage::new(&directory);
+        assert!(matches!(
+            load_from::<SaveData>(&storage, "missing"),
+            Err(SaveError::NotFound(_))
+        ));
+        storage.write("corrupt", b"{\"version\": 1").unwrap();
+        assert!(matches!(
+            load_from::<SaveData>(&storage, "corrupt"),
+            Err(SaveError::Json(_))
+        ));
+
+        let data = SaveData::from_state(&busy_state());
+        save_to(&storage, "slot", &data).unwrap();
+        assert_eq!(load_from::<SaveData>(&storage, "slot").unwrap(), data);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
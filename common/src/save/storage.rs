@@ -0,0 +1,130 @@
+use crate::save::SaveError;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use tearchan::fs::file_util;
+
+/// Where saves are kept: files on native platforms, `localStorage` on the web.
+pub trait Storage {
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>, SaveError>;
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<(), SaveError>;
+    fn remove(&self, name: &str) -> Result<(), SaveError>;
+}
+
+/// Stores each save as `<name>.json` in a directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    directory: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        FileStorage {
+            directory: directory.into(),
+        }
+    }
+
+    /// The `saves` directory next to the assets directory.
+    pub fn beside_assets() -> Self {
+        let mut directory = PathBuf::new();
+        directory.push(file_util().assets_path());
+        directory.pop();
+        directory.push("saves");
+        FileStorage::new(directory)
+    }
+
+    pub fn directory(&self) -> &PathBuf {
+        &self.directory
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.directory.join(format!("{}.json", name))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>, SaveError> {
+        match std::fs::read(self.path(name)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<(), SaveError> {
+        std::fs::create_dir_all(&self.directory)?;
+        // Write next to the old save and swap, so a crash never leaves a half-written file
+        let path = self.path(name);
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, bytes)?;
+        std::fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> Result<(), SaveError> {
+        match std::fs::remove_file(self.path(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Stores each save as a string under `<prefix><name>` in `window.localStorage`.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage {
+    prefix: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    pub fn new(prefix: &str) -> Self {
+        LocalStorage {
+            prefix: prefix.to_string(),
+        }
+    }
+
+    fn storage(&self) -> Result<web_sys::Storage, SaveError> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| SaveError::Storage("localStorage is unavailable".to_string()))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, name: &str) -> Result<Option<Vec<u8>>, SaveError> {
+        let value = self
+            .storage()?
+            .get_item(&format!("{}{}", self.prefix, name))
+            .map_err(|e| SaveError::Storage(format!("{:?}", e)))?;
+        Ok(value.map(String::into_bytes))
+    }
+
+    fn write(&self, name: &str, bytes: &[u8]) -> Result<(), SaveError> {
+        // Saves are JSON, so they are always valid UTF-8
+        let value = String::from_utf8_lossy(bytes);
+        self.storage()?
+            .set_item(&format!("{}{}", self.prefix, name), &value)
+            .map_err(|e| SaveError::Storage(format!("{:?}", e)))
+    }
+
+    fn remove(&self, name: &str) -> Result<(), SaveError> {
+        self.storage()?
+            .remove_item(&format!("{}{}", self.prefix, name))
+            .map_err(|e| SaveError::Storage(format!("{:?}", e)))
+    }
+}
+
+/// The storage saves go to on this platform.
+pub fn default_storage() -> Box<dyn Storage> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        Box::new(FileStorage::beside_assets())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        Box::new(LocalStorage::new("saves/"))
+    }
+}
@@ -0,0 +1,841 @@
+use crate::action::Tick;
+use crate::audio::crossfade_to_scene_music;
+use crate::camera::{CameraController, CameraControllerConfig};
+use crate::capture::screenshot::{ScreenshotCapture, SCREENSHOT_DIRECTORY};
+use crate::ecs::{ComponentGroup, EntityId};
+use crate::game::component::{Angle, CharacterStatus, Item, Position};
+use crate::game::demo::{create_demo_world, DemoWorld};
+use crate::game::GameState;
+use crate::headless::{HeadlessContext, HeadlessScene, HeadlessSceneFactory};
+use crate::input::gamepad::{GamepadButton, GamepadEventKind};
+use crate::map::{Ground, GroundKind};
+use crate::render::batch::{BatchInstance, RenderBatch, BATCH_INSTANCE_ATTRIBUTES};
+use crate::save::storage::{default_storage, Storage};
+use crate::save::{load_from, save_to};
+use crate::scene::context::{GameContext, GameEvent, GameScene, GameSceneHost};
+use crate::settings::{current_settings, initial_scale_factor};
+use crate::text::font::{load_default_fonts, Fonts};
+use crate::time::FixedTimestep;
+use crate::ui::layout::{Anchor, CrossAlign, Justify, Size};
+use crate::ui::render::UiRenderer;
+use crate::ui::theme::{default_theme_path, Theme};
+use crate::ui::widget::Widget;
+use crate::ui::{Ui, UiEvent, WidgetId};
+use nalgebra_glm::vec3;
+use tearchan::scene::context::SceneRenderContext;
+use tearchan::scene::factory::SceneFactory;
+use tearchan::scene::SceneControlFlow;
+use tearchan_gfx::camera::Camera3D;
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+const QUICK_SAVE_KEY: VirtualKeyCode = VirtualKeyCode::F5;
+const QUICK_LOAD_KEY: VirtualKeyCode = VirtualKeyCode::F9;
+const QUICK_SAVE_NAME: &str = "quicksave";
+const MENU_KEY: VirtualKeyCode = VirtualKeyCode::Escape;
+const MENU_BUTTON: GamepadButton = GamepadButton::Start;
+const MUSIC: &str = "music/characters.wav";
+
+// Save names and how the pause menu lists them
+const SAVE_SLOTS: [(&str, &str); 4] = [
+    (QUICK_SAVE_NAME, "Quick save"),
+    ("slot1", "Slot 1"),
+    ("slot2", "Slot 2"),
+    ("slot3", "Slot 3"),
+];
+const MAX_GAME_SPEED: f32 = 4.0f32;
+
+// The simulation runs at 50 Hz
+const GAME_TICK: Tick = 20;
+
+// Colors of the palette texture, addressed by texcoord
+const PALETTE: [[u8; 4]; 8] = [
+    [120, 180, 90, 255],  // plain
+    [160, 140, 90, 255],  // rough
+    [60, 110, 200, 255],  // water
+    [70, 70, 70, 255],    // wall
+    [230, 80, 60, 255],   // character
+    [250, 210, 60, 255],  // item
+    [255, 255, 255, 255], // character front
+    [0, 0, 0, 255],
+];
+const PALETTE_CHARACTER: usize = 4;
+const PALETTE_ITEM: usize = 5;
+const PALETTE_CHARACTER_FRONT: usize = 6;
+
+/// Characters carrying items around a small map, driven by `GameState`.
+pub struct CharacterScene {
+    world: DemoWorld,
+    renderer: CharacterRenderer,
+    timestep: FixedTimestep,
+    storage: Box<dyn Storage>,
+    ui: Ui,
+    ui_renderer: UiRenderer,
+    menu: PauseMenu,
+    game_speed: f32,
+    screenshot: ScreenshotCapture,
+}
+
+/// Widgets of the menu shown while the game is paused.
+struct PauseMenu {
+    panel: WidgetId,
+    resume: WidgetId,
+    speed: WidgetId,
+    slots: WidgetId,
+    save: WidgetId,
+    load: WidgetId,
+    status: WidgetId,
+}
+
+impl PauseMenu {
+    fn build(ui: &mut Ui) -> Self {
+        let root = ui.root();
+        ui.add(
+            root,
+            Widget::label("Esc: menu   F5/F9: quick save/load")
+                .anchored(Anchor::TopLeft, [16.0f32, 12.0f32]),
+        );
+        let panel = ui.add(
+            root,
+            Widget::column()
+                .with_background()
+                .with_align(CrossAlign::Stretch)
+                .anchored(Anchor::Center, [0.0f32, 0.0f32]),
+        );
+        ui.add(panel, Widget::label("Paused"));
+        let resume = ui.add(panel, Widget::button("Resume"));
+        ui.add(panel, Widget::label("Game speed"));
+        let speed = ui.add(
+            panel,
+            Widget::slider(1.0f32, 0.25f32..=MAX_GAME_SPEED).with_step(0.25f32),
+        );
+        let names = SAVE_SLOTS
+            .iter()
+            .map(|(_, label)| label.to_string())
+            .collect();
+        let slots = ui.add(panel, Widget::list(names, 3).with_selected(0));
+        let buttons = ui.add(panel, Widget::row().with_justify(Justify::SpaceBetween));
+        let save = ui.add(
+            buttons,
+            Widget::button("Save").with_width(Size::Fill(1.0f32)),
+        );
+        let load = ui.add(
+            buttons,
+            Widget::button("Load").with_width(Size::Fill(1.0f32)),
+        );
+        let status = ui.add(panel, Widget::label(""));
+        ui.set_visible(panel, false);
+
+        PauseMenu {
+            panel,
+            resume,
+            speed,
+            slots,
+            save,
+            load,
+            status,
+        }
+    }
+}
+
+impl CharacterScene {
+    pub fn factory() -> SceneFactory {
+        |context, _| {
+            let world = create_demo_world();
+            let renderer = CharacterRenderer::new(
+                context.gfx().device,
+                context.gfx().queue,
+                context.gfx().swapchain_desc.format,
+                context.gfx().swapchain_desc.width,
+                context.gfx().swapchain_desc.height,
+                current_settings().msaa,
+                &world.state.ground,
+            );
+
+            let fonts = Fonts::new();
+            context
+                .spawner()
+                .spawn_local(load_default_fonts(fonts.clone()));
+            let ui_renderer = UiRenderer::new(
+                context.gfx().device,
+                context.gfx().queue,
+                context.gfx().swapchain_desc.format,
+                fonts,
+            );
+            let mut ui = Ui::new(Theme::default());
+            ui.set_scale_factor(initial_scale_factor(context.gfx().swapchain_desc.width) as f32);
+            context
+                .spawner()
+                .spawn_local(ui.load_theme(default_theme_path()));
+            let menu = PauseMenu::build(&mut ui);
+
+            let game = GameContext::configured();
+            context
+                .spawner()
+                .spawn_local(crossfade_to_scene_music(game.audio().clone(), MUSIC));
+
+            Box::new(GameSceneHost::new(
+                game,
+                CharacterScene {
+                    world,
+                    renderer,
+                    timestep: FixedTimestep::new(GAME_TICK as f32 / 1000.0f32),
+                    storage: default_storage(),
+                    ui,
+                    ui_renderer,
+                    menu,
+                    game_speed: 1.0f32,
+                    screenshot: ScreenshotCapture::new(SCREENSHOT_DIRECTORY),
+                },
+            ))
+        }
+    }
+
+    /// The world without the menu, for recording.
+    pub fn headless_factory() -> HeadlessSceneFactory {
+        |context| {
+            let world = create_demo_world();
+            let renderer = CharacterRenderer::new(
+                context.device,
+                context.queue,
+                context.format,
+                context.width,
+                context.height,
+                current_settings().msaa,
+                &world.state.ground,
+            );
+            Box::new(HeadlessCharacterScene {
+                world,
+                renderer,
+                timestep: FixedTimestep::new(GAME_TICK as f32 / 1000.0f32),
+            })
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.ui.is_visible(self.menu.panel)
+    }
+
+    fn selected_slot(&self) -> &'static str {
+        let index = self.ui.selected(self.menu.slots).unwrap_or(0);
+        SAVE_SLOTS[index.min(SAVE_SLOTS.len() - 1)].0
+    }
+
+    /// Returns a status line for the pause menu.
+    fn save(&mut self, name: &str) -> String {
+        match save_to(self.storage.as_ref(), name, &self.world.to_save_data()) {
+            Ok(()) => {
+                log::info!("Saved the game to {}", name);
+                format!("Saved to {}", name)
+            }
+            Err(e) => {
+                log::error!("Failed to save the game: {}", e);
+                format!("Failed to save: {}", e)
+            }
+        }
+    }
+
+    /// Replaces the simulated world, e.g. with a loaded one, and redraws its map, since saves
+    /// carry their own.
+    fn set_world(&mut self, world: DemoWorld) {
+        self.world = world;
+        self.renderer.set_ground(&self.world.state.ground);
+    }
+
+    /// Returns a status line for the pause menu.
+    fn load(&mut self, name: &str) -> String {
+        match load_from(self.storage.as_ref(), name) {
+            Ok(data) => {
+                self.set_world(DemoWorld::from_save_data(data));
+                format!("Loaded {}", name)
+            }
+            Err(e) => {
+                log::error!("Failed to load the game: {}", e);
+                format!("Failed to load: {}", e)
+            }
+        }
+    }
+
+    /// Draws the world and the menu over `view`.
+    fn draw_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        screen_size: (u32, u32),
+    ) {
+        self.renderer.draw(device, queue, view);
+        self.ui_renderer
+            .draw(&self.ui, device, queue, view, screen_size);
+    }
+
+    fn toggle_menu(&mut self) {
+        let paused = self.is_paused();
+        self.ui.set_visible(self.menu.panel, !paused);
+        self.ui.set_text(self.menu.status, "");
+    }
+
+    fn handle_ui_events(&mut self) {
+        for event in self.ui.drain_events() {
+            match event {
+                UiEvent::Clicked(id) if id == self.menu.resume => {
+                    self.ui.set_visible(self.menu.panel, false);
+                }
+                UiEvent::Clicked(id) if id == self.menu.save => {
+                    let status = self.save(self.selected_slot());
+                    self.ui.set_text(self.menu.status, &status);
+                }
+                UiEvent::Clicked(id) if id == self.menu.load => {
+                    let status = self.load(self.selected_slot());
+                    self.ui.set_text(self.menu.status, &status);
+                }
+                UiEvent::ValueChanged { id, value } if id == self.menu.speed => {
+                    self.game_speed = value;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl GameScene for CharacterScene {
+    fn update(&mut self, _game: &mut GameContext, event: GameEvent) -> SceneControlFlow {
+        let event = match event {
+            GameEvent::Window(event) => event,
+            GameEvent::Gamepad(event) => {
+                if event.kind == GamepadEventKind::ButtonPressed(MENU_BUTTON) {
+                    self.toggle_menu();
+                }
+                return SceneControlFlow::None;
+            }
+        };
+        if self.screenshot.handle_event(&event)
+            || self.ui.handle_event(&event)
+            || self.renderer.handle_event(&event)
+        {
+            return SceneControlFlow::None;
+        }
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            if key == QUICK_SAVE_KEY {
+                self.save(QUICK_SAVE_NAME);
+            } else if key == QUICK_LOAD_KEY {
+                self.load(QUICK_SAVE_NAME);
+            } else if key == MENU_KEY {
+                self.toggle_menu();
+            }
+        }
+        SceneControlFlow::None
+    }
+
+    fn render(
+        &mut self,
+        context: &mut SceneRenderContext,
+        game: &mut GameContext,
+    ) -> SceneControlFlow {
+        let frame = context.gfx_rendering().frame();
+        let queue = context.gfx().queue;
+        let device = context.gfx().device;
+
+        self.handle_ui_events();
+        let time = game.time();
+        let delta = if self.is_paused() {
+            0.0f32
+        } else {
+            time.delta * self.game_speed
+        };
+        for _ in 0..self.timestep.advance(delta) {
+            self.world.update(GAME_TICK);
+        }
+
+        let (width, height) = (
+            context.gfx().swapchain_desc.width,
+            context.gfx().swapchain_desc.height,
+        );
+        self.renderer.resize(device, width, height);
+        // The camera keeps moving while the game is paused
+        self.renderer.update_camera(queue, time.delta);
+        self.renderer.update(&self.world.state);
+        self.ui
+            .layout(width as f32, height as f32, self.ui_renderer.fonts());
+        self.draw_frame(device, queue, &frame.view, (width, height));
+
+        let format = context.gfx().swapchain_desc.format;
+        if let Some(target) = self.screenshot.begin(device, width, height, format) {
+            self.draw_frame(device, queue, target.view(), (width, height));
+            match self.screenshot.save(device, queue, target) {
+                Ok(path) => log::info!("Saved screenshot to {:?}", path),
+                Err(e) => log::error!("Failed to save screenshot: {}", e),
+            }
+        }
+        SceneControlFlow::None
+    }
+}
+
+struct HeadlessCharacterScene {
+    world: DemoWorld,
+    renderer: CharacterRenderer,
+    timestep: FixedTimestep,
+}
+
+impl HeadlessScene for HeadlessCharacterScene {
+    fn update(&mut self, _context: &HeadlessContext, delta: f32) {
+        for _ in 0..self.timestep.advance(delta) {
+            self.world.update(GAME_TICK);
+        }
+    }
+
+    fn render(&mut self, context: &HeadlessContext, view: &wgpu::TextureView) {
+        self.renderer.update(&self.world.state);
+        self.renderer.draw(context.device, context.queue, view);
+    }
+}
+
+/// Draws the map and its entities as instanced quads.
+///
+/// Each kind of quad lives in a persistent `RenderBatch`, so only entities that moved are
+/// uploaded each frame.
+pub struct CharacterRenderer {
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    camera: Camera3D,
+    camera_controller: CameraController,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    size: (u32, u32),
+    // Rendered into and resolved to the frame when multisampling
+    multisampled_view: Option<wgpu::TextureView>,
+    pipeline: wgpu::RenderPipeline,
+    quad_buffer: wgpu::Buffer,
+    ground: RenderBatch<u64, BatchInstance>,
+    items: RenderBatch<EntityId, BatchInstance>,
+    characters: RenderBatch<EntityId, BatchInstance>,
+    fronts: RenderBatch<EntityId, BatchInstance>,
+}
+
+impl CharacterRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        ground: &Ground,
+    ) -> Self {
+        let aspect = width as f32 / height.max(1) as f32;
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let texels = PALETTE.iter().flatten().copied().collect::<Vec<u8>>();
+        let texture_extent = wgpu::Extent3d {
+            width: PALETTE.len() as u32,
+            height: 1,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Palette"),
+            size: texture_extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &texels,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * PALETTE.len() as u32,
+                rows_per_image: 0,
+            },
+            texture_extent,
+        );
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // Look down at the middle of the map from the south
+        let center_x = (ground.tiles.width() as f32 - 1.0f32) * 0.5f32;
+        let center_z = (ground.tiles.height() as f32 - 1.0f32) * 0.5f32;
+        let mut camera = Camera3D::default_with_aspect(aspect);
+        camera.position = vec3(center_x, 10.0f32, center_z + 7.0f32);
+        camera.target_position = vec3(center_x, 0.0f32, center_z);
+        camera.up = vec3(0.0f32, 1.0f32, 0.0f32);
+        camera.update();
+        let camera_controller = CameraController::new(
+            CameraControllerConfig::default(),
+            camera.position,
+            camera.target_position,
+        );
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(camera.combine().as_slice()),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: None,
+        });
+
+        let vertex_state = wgpu::VertexStateDescriptor {
+            index_format: None,
+            vertex_buffers: &[
+                wgpu::VertexBufferDescriptor {
+                    stride: 3 * std::mem::size_of::<f32>() as u64,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float3,
+                        offset: 0,
+                        shader_location: 0,
+                    }],
+                },
+                wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<BatchInstance>() as u64,
+                    step_mode: wgpu::InputStepMode::Instance,
+                    attributes: &BATCH_INSTANCE_ATTRIBUTES,
+                },
+            ],
+        };
+
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!(
+            "../../../target/shaders/batch.vert.spv"
+        ));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!(
+            "../../../target/shaders/simple.frag.spv"
+        ));
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state,
+            sample_count,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        // Two triangles spanning -1..1 on the ground plane, scaled by each instance
+        #[rustfmt::skip]
+        let quad = [
+            -1.0f32, 0.0, -1.0,
+            1.0, 0.0, -1.0,
+            1.0, 0.0, 1.0,
+            -1.0, 0.0, -1.0,
+            1.0, 0.0, 1.0,
+            -1.0, 0.0, 1.0,
+        ];
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Quad Buffer"),
+            contents: bytemuck::cast_slice(&quad),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+
+        let mut ground_batch = RenderBatch::new("Ground Batch", wgpu::BufferUsage::VERTEX);
+        ground_batch_system(ground, &mut ground_batch);
+
+        CharacterRenderer {
+            bind_group,
+            uniform_buffer,
+            camera,
+            camera_controller,
+            format,
+            sample_count,
+            size: (width, height),
+            multisampled_view: create_multisampled_view(
+                device,
+                format,
+                width,
+                height,
+                sample_count,
+            ),
+            pipeline,
+            quad_buffer,
+            ground: ground_batch,
+            items: RenderBatch::new("Item Batch", wgpu::BufferUsage::VERTEX),
+            characters: RenderBatch::new("Character Batch", wgpu::BufferUsage::VERTEX),
+            fronts: RenderBatch::new("Character Front Batch", wgpu::BufferUsage::VERTEX),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        self.camera_controller.handle_event(event)
+    }
+
+    /// Replaces the tiles drawn, e.g. after loading a save.
+    pub fn set_ground(&mut self, ground: &Ground) {
+        ground_batch_system(ground, &mut self.ground);
+    }
+
+    /// Matches the frame size; call before `draw` whenever the window may have been resized.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if self.size == (width, height) {
+            return;
+        }
+        self.size = (width, height);
+        self.multisampled_view =
+            create_multisampled_view(device, self.format, width, height, self.sample_count);
+
+        let mut camera = Camera3D::default_with_aspect(width as f32 / height.max(1) as f32);
+        camera.position = self.camera.position;
+        camera.target_position = self.camera.target_position;
+        camera.up = self.camera.up;
+        camera.update();
+        self.camera = camera;
+    }
+
+    pub fn update_camera(&mut self, queue: &wgpu::Queue, delta: f32) {
+        self.camera_controller.update(delta, &mut self.camera);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(self.camera.combine().as_slice()),
+        );
+    }
+
+    /// Diffs the game state against the batches.
+    pub fn update(&mut self, state: &GameState) {
+        item_batch_system(&state.positions, &state.items, &mut self.items);
+        character_batch_system(
+            &state.positions,
+            &state.angles,
+            &state.character_status,
+            &mut self.characters,
+            &mut self.fronts,
+        );
+    }
+
+    pub fn draw(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
+        // Later batches are drawn over earlier ones
+        let mut batches = [
+            &mut self.ground,
+            &mut self.items,
+            &mut self.characters,
+            &mut self.fronts,
+        ];
+        for batch in batches.iter_mut() {
+            batch.flush(device, queue);
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: self.multisampled_view.as_ref().unwrap_or(view),
+                    resolve_target: self.multisampled_view.as_ref().map(|_| view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+            for batch in batches.iter() {
+                if let Some(buffer) = batch.buffer() {
+                    rpass.set_vertex_buffer(1, buffer.slice(..));
+                    rpass.draw(0..6, 0..batch.len());
+                }
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+/// The texture multisampled frames are drawn into, or `None` without multisampling.
+fn create_multisampled_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Multisampled Frame"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsage::RENDER_ATTACHMENT,
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+fn ground_batch_system(ground: &Ground, batch: &mut RenderBatch<u64, BatchInstance>) {
+    let map_width = ground.tiles.width() as u64;
+    batch.sync(ground.tiles.iter().map(|(tile, kind)| {
+        let color = match kind {
+            GroundKind::Plain => 0,
+            GroundKind::Rough => 1,
+            GroundKind::Water => 2,
+            GroundKind::Wall => 3,
+        };
+        let key = tile.y as u64 * map_width + tile.x as u64;
+        (key, instance(&tile.to_position(), 0.5f32, 0.0f32, color))
+    }));
+}
+
+fn item_batch_system(
+    positions: &ComponentGroup<Position>,
+    items: &ComponentGroup<Item>,
+    batch: &mut RenderBatch<EntityId, BatchInstance>,
+) {
+    batch.sync(items.iter().filter_map(|(entity_id, _)| {
+        let position = positions.get(entity_id)?;
+        Some((*entity_id, instance(position, 0.2f32, 0.0f32, PALETTE_ITEM)))
+    }));
+}
+
+fn character_batch_system(
+    positions: &ComponentGroup<Position>,
+    angles: &ComponentGroup<Angle>,
+    character_status: &ComponentGroup<CharacterStatus>,
+    batch: &mut RenderBatch<EntityId, BatchInstance>,
+    front_batch: &mut RenderBatch<EntityId, BatchInstance>,
+) {
+    let characters = character_status
+        .iter()
+        .filter_map(|(entity_id, _)| {
+            let position = positions.get(entity_id)?;
+            let angle = angles.get(entity_id).map(|a| a.0).unwrap_or(0.0f32);
+            Some((*entity_id, *position, angle))
+        })
+        .collect::<Vec<_>>();
+
+    batch.sync(characters.iter().map(|(entity_id, position, angle)| {
+        (
+            *entity_id,
+            instance(position, 0.3f32, *angle, PALETTE_CHARACTER),
+        )
+    }));
+    // A small marker shows which way the character faces
+    front_batch.sync(characters.iter().map(|(entity_id, position, angle)| {
+        let front = Position::new(
+            position.x + angle.cos() * 0.2f32,
+            position.y + angle.sin() * 0.2f32,
+        );
+        (
+            *entity_id,
+            instance(&front, 0.08f32, *angle, PALETTE_CHARACTER_FRONT),
+        )
+    }));
+}
+
+fn instance(center: &Position, size: f32, angle: f32, color: usize) -> BatchInstance {
+    BatchInstance {
+        position: [center.x, center.y],
+        angle,
+        size,
+        texcoord: [(color as f32 + 0.5f32) / PALETTE.len() as f32, 0.5f32],
+    }
+}
@@ -0,0 +1,149 @@
+use crate::audio::{shared_audio, Audio};
+use crate::input::gamepad::{shared_gamepads, GamepadEvent, Gamepads};
+use crate::input::record::{shared_input_session, InputSession};
+use crate::time::{FrameClock, FrameTime};
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+use tearchan::scene::context::{SceneContext, SceneRenderContext};
+use tearchan::scene::{Scene, SceneControlFlow};
+use winit::event::WindowEvent;
+
+/// Input handed to `GameScene::update`.
+#[derive(Debug)]
+pub enum GameEvent<'a> {
+    Window(WindowEvent<'a>),
+    /// Dead zones are already applied.
+    Gamepad(GamepadEvent),
+}
+
+/// What the game shares with its scenes on top of tearchan's contexts: frame timing, gamepads,
+/// audio and input recording.
+pub struct GameContext {
+    clock: FrameClock,
+    measured_delta: f32,
+    input_session: Rc<RefCell<InputSession>>,
+    gamepads: Rc<RefCell<Gamepads>>,
+    audio: Audio,
+}
+
+impl GameContext {
+    pub fn new(
+        input_session: Rc<RefCell<InputSession>>,
+        gamepads: Rc<RefCell<Gamepads>>,
+        audio: Audio,
+    ) -> Self {
+        GameContext {
+            clock: FrameClock::new(),
+            measured_delta: 0.0f32,
+            input_session,
+            gamepads,
+            audio,
+        }
+    }
+
+    /// Uses the app-wide input session, gamepads and audio output, opened by the first scene
+    /// that asks for them. The input session follows `configure_input_session`.
+    pub fn configured() -> Self {
+        GameContext::new(shared_input_session(), shared_gamepads(), shared_audio())
+    }
+
+    /// Timing of the frame being rendered. While recording with a fixed delta or replaying,
+    /// `delta` is the recorded one rather than the wall-clock time.
+    pub fn time(&self) -> FrameTime {
+        self.clock.time()
+    }
+
+    /// Wall-clock seconds since the previous frame, e.g. for frame rate displays.
+    pub fn measured_delta(&self) -> f32 {
+        self.measured_delta
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.input_session.borrow().is_replaying()
+    }
+
+    /// The audio output. It outlives scenes, so music can carry on across transitions.
+    pub fn audio(&self) -> &Audio {
+        &self.audio
+    }
+
+    /// Connected gamepads and their current state.
+    pub fn gamepads(&self) -> Ref<Gamepads> {
+        self.gamepads.borrow()
+    }
+
+    /// Starts a frame and returns the input that arrived outside of `Scene::update`.
+    fn begin_frame(&mut self) -> Vec<GameEvent<'static>> {
+        self.measured_delta = self.clock.measure();
+        // Live gamepads are left alone during a replay; the recorded events take their place
+        let mut input_session = self.input_session.borrow_mut();
+        let gamepad_events = if input_session.is_replaying() {
+            vec![]
+        } else {
+            self.gamepads.borrow_mut().poll()
+        };
+        let frame = input_session.begin_frame(self.measured_delta, gamepad_events);
+        self.clock.advance(frame.delta);
+        self.audio.update();
+        let window_events = frame.events.into_iter().map(GameEvent::Window);
+        let gamepad_events = frame.gamepad_events.into_iter().map(GameEvent::Gamepad);
+        window_events.chain(gamepad_events).collect()
+    }
+}
+
+/// A scene driven through a `GameContext`. Wrap it in a `GameSceneHost` to hand it to the engine.
+pub trait GameScene {
+    /// Handles an input event. Gamepad and replayed events arrive here too, right before `render`.
+    fn update(&mut self, game: &mut GameContext, event: GameEvent) -> SceneControlFlow;
+
+    fn render(
+        &mut self,
+        context: &mut SceneRenderContext,
+        game: &mut GameContext,
+    ) -> SceneControlFlow;
+}
+
+/// Runs a `GameScene` as a tearchan `Scene`, timing its frames and recording or replaying
+/// its input through `game`.
+///
+/// ```ignore
+/// let game = GameContext::configured();
+/// let scene = MyScene::new(game.audio().clone());
+/// Box::new(GameSceneHost::new(game, scene))
+/// ```
+pub struct GameSceneHost<S> {
+    scene: S,
+    game: GameContext,
+}
+
+impl<S: GameScene> GameSceneHost<S> {
+    pub fn new(game: GameContext, scene: S) -> Self {
+        GameSceneHost { scene, game }
+    }
+}
+
+impl<S: GameScene> Scene for GameSceneHost<S> {
+    fn update(&mut self, _context: &mut SceneContext, event: WindowEvent) -> SceneControlFlow {
+        let event = self.game.input_session.borrow_mut().filter_event(event);
+        match event {
+            Some(event) => self.scene.update(&mut self.game, GameEvent::Window(event)),
+            None => SceneControlFlow::None,
+        }
+    }
+
+    fn render(&mut self, context: &mut SceneRenderContext) -> SceneControlFlow {
+        // A transition asked for by a replayed or gamepad event wins over the render's
+        let mut flow = SceneControlFlow::None;
+        for event in self.game.begin_frame() {
+            let event_flow = self.scene.update(&mut self.game, event);
+            if let SceneControlFlow::None = flow {
+                flow = event_flow;
+            }
+        }
+        let render_flow = self.scene.render(context, &mut self.game);
+        match flow {
+            SceneControlFlow::None => render_flow,
+            flow => flow,
+        }
+    }
+}
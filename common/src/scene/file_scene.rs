@@ -1,9 +1,9 @@
+use crate::scene::context::{GameContext, GameEvent, GameScene, GameSceneHost};
 use std::path::PathBuf;
 use tearchan::fs::{file_util, read_bytes_from_file};
-use tearchan::scene::context::{SceneContext, SceneRenderContext};
+use tearchan::scene::context::SceneRenderContext;
 use tearchan::scene::factory::SceneFactory;
-use tearchan::scene::{Scene, SceneControlFlow};
-use winit::event::WindowEvent;
+use tearchan::scene::SceneControlFlow;
 
 pub struct FileScene {}
 
@@ -11,17 +11,21 @@ impl FileScene {
     pub fn factory() -> SceneFactory {
         |context, _| {
             context.spawner().spawn_local(read_bytes());
-            Box::new(FileScene {})
+            Box::new(GameSceneHost::new(GameContext::configured(), FileScene {}))
         }
     }
 }
 
-impl Scene for FileScene {
-    fn update(&mut self, _context: &mut SceneContext, _event: WindowEvent) -> SceneControlFlow {
+impl GameScene for FileScene {
+    fn update(&mut self, _game: &mut GameContext, _event: GameEvent) -> SceneControlFlow {
         SceneControlFlow::None
     }
 
-    fn render(&mut self, _context: &mut SceneRenderContext) -> SceneControlFlow {
+    fn render(
+        &mut self,
+        _context: &mut SceneRenderContext,
+        _game: &mut GameContext,
+    ) -> SceneControlFlow {
         SceneControlFlow::None
     }
 }
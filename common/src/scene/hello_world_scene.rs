@@ -1,7 +1,22 @@
+use crate::audio::spatial::{EmitterOptions, SpatialAudio};
+use crate::audio::{crossfade_to_scene_music, Audio};
+use crate::camera::{CameraController, CameraControllerConfig};
+use crate::capture::screenshot::{ScreenshotCapture, SCREENSHOT_DIRECTORY};
+use crate::debug_ui::DebugUi;
+use crate::headless::{HeadlessContext, HeadlessScene, HeadlessSceneFactory};
+use crate::input::bindings::{default_input_bindings_path, InputBindings};
+use crate::input::InputMap;
+use crate::scene::context::{GameContext, GameEvent, GameScene, GameSceneHost};
+use crate::settings::initial_scale_factor;
+use crate::text::font::{load_default_fonts, Fonts};
+use crate::text::layout::{Align, TextStyle};
+use crate::text::TextRenderer;
 use nalgebra_glm::vec3;
-use tearchan::scene::context::{SceneContext, SceneRenderContext};
+use std::path::PathBuf;
+use tearchan::fs::file_util;
+use tearchan::scene::context::SceneRenderContext;
 use tearchan::scene::factory::SceneFactory;
-use tearchan::scene::{Scene, SceneControlFlow};
+use tearchan::scene::SceneControlFlow;
 use tearchan_gfx::camera::Camera3D;
 use tearchan_util::math::rect::rect2;
 use tearchan_util::mesh::square::{
@@ -10,7 +25,21 @@ use tearchan_util::mesh::square::{
 use wgpu::util::DeviceExt;
 use winit::event::WindowEvent;
 
-pub struct HelloWorldScene {
+// Radians per second the camera auto-orbits while idle, 0.01 per frame at 60 fps
+const ROTATION_SPEED: f32 = 0.6f32;
+
+// Center of the square, where its sounds come from
+const SQUARE_CENTER: [f32; 3] = [0.5f32, 0.5f32, 0.0f32];
+
+const CAPTION: &str = "Hello, world!";
+
+const CLEAR_COLOR: [f32; 3] = [0.1f32, 0.2f32, 0.3f32];
+const MUSIC: &str = "music/hello_world.wav";
+
+// Range of the rotation speed knob in the debug overlay, in radians per second
+const MAX_ROTATION_SPEED: f32 = 3.0f32;
+
+pub struct HelloWorldRenderer {
     index_count: usize,
     index_format: wgpu::IndexFormat,
     index_buffer: wgpu::Buffer,
@@ -20,273 +49,291 @@ pub struct HelloWorldScene {
     uniform_buffer: wgpu::Buffer,
     pipeline: wgpu::RenderPipeline,
     camera: Camera3D,
-    angle: f32,
+    camera_controller: CameraController,
+    clear_color: [f32; 3],
 }
 
-impl HelloWorldScene {
-    pub fn factory() -> SceneFactory {
-        |context, _| {
-            let queue = context.gfx().queue;
-            let device = context.gfx().device;
-            let width = context.gfx().swapchain_desc.width as f32;
-            let height = context.gfx().swapchain_desc.height as f32;
-            let aspect = width / height;
-
-            let indices = create_square_indices();
-            let positions = create_square_positions(&rect2(0.0f32, 0.0f32, 1.0f32, 1.0f32))
-                .iter()
-                .map(|v| vec![v.x, v.y, v.z])
-                .flatten()
-                .collect::<Vec<_>>();
-            let texcoords = create_square_texcoords(&rect2(0.0f32, 0.0f32, 1.0f32, 1.0f32))
-                .iter()
-                .map(|v| vec![v.x, v.y])
-                .flatten()
-                .collect::<Vec<_>>();
-
-            let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsage::INDEX,
-            });
-            let position_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Position Buffer"),
-                contents: bytemuck::cast_slice(&positions),
-                usage: wgpu::BufferUsage::VERTEX,
-            });
-            let texcoord_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Texcoord Buffer"),
-                contents: bytemuck::cast_slice(&texcoords),
-                usage: wgpu::BufferUsage::VERTEX,
-            });
+impl HelloWorldRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        aspect: f32,
+    ) -> Self {
+        let indices = create_square_indices();
+        let positions = create_square_positions(&rect2(0.0f32, 0.0f32, 1.0f32, 1.0f32))
+            .iter()
+            .map(|v| vec![v.x, v.y, v.z])
+            .flatten()
+            .collect::<Vec<_>>();
+        let texcoords = create_square_texcoords(&rect2(0.0f32, 0.0f32, 1.0f32, 1.0f32))
+            .iter()
+            .map(|v| vec![v.x, v.y])
+            .flatten()
+            .collect::<Vec<_>>();
 
-            let bind_group_layout =
-                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: None,
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStage::VERTEX,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: false,
-                                min_binding_size: wgpu::BufferSize::new(64),
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStage::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 2,
-                            visibility: wgpu::ShaderStage::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler {
-                                comparison: false,
-                                filtering: true,
-                            },
-                            count: None,
-                        },
-                    ],
-                });
-            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: None,
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[],
-            });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        let position_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Position Buffer"),
+            contents: bytemuck::cast_slice(&positions),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let texcoord_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Texcoord Buffer"),
+            contents: bytemuck::cast_slice(&texcoords),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
 
-            let size = 1u32;
-            let texels = vec![255, 0, 0, 255];
-            let texture_extent = wgpu::Extent3d {
-                width: size,
-                height: size,
-                depth: 1,
-            };
-            let texture = device.create_texture(&wgpu::TextureDescriptor {
-                label: None,
-                size: texture_extent,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
-            });
-            let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-            queue.write_texture(
-                wgpu::TextureCopyView {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
                 },
-                &texels,
-                wgpu::TextureDataLayout {
-                    offset: 0,
-                    bytes_per_row: 4 * size,
-                    rows_per_image: 0,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
                 },
-                texture_extent,
-            );
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        comparison: false,
+                        filtering: true,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
 
-            let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::ClampToEdge,
-                address_mode_v: wgpu::AddressMode::ClampToEdge,
-                address_mode_w: wgpu::AddressMode::ClampToEdge,
-                mag_filter: wgpu::FilterMode::Nearest,
-                min_filter: wgpu::FilterMode::Linear,
-                mipmap_filter: wgpu::FilterMode::Nearest,
-                ..Default::default()
-            });
-            let mut camera = Camera3D::default_with_aspect(aspect);
-            camera.position = vec3(0.0f32, 2.0f32, 4.0f32);
-            camera.target_position = vec3(0.0f32, 0.0f32, 0.0f32);
-            camera.up = vec3(0.0f32, 1.0f32, 0.0f32);
-            camera.update();
-
-            let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Uniform Buffer"),
-                contents: bytemuck::cast_slice(camera.combine().as_slice()),
-                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-            });
+        let size = 1u32;
+        let texels = vec![255, 0, 0, 255];
+        let texture_extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: texture_extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &texels,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * size,
+                rows_per_image: 0,
+            },
+            texture_extent,
+        );
 
-            // Create bind group
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: uniform_buffer.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(&texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::Sampler(&sampler),
-                    },
-                ],
-                label: None,
-            });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let mut camera = Camera3D::default_with_aspect(aspect);
+        camera.position = vec3(0.0f32, 2.0f32, 4.0f32);
+        camera.target_position = vec3(0.0f32, 0.0f32, 0.0f32);
+        camera.up = vec3(0.0f32, 1.0f32, 0.0f32);
+        camera.update();
+        let camera_controller = CameraController::new(
+            CameraControllerConfig {
+                auto_orbit_speed: ROTATION_SPEED,
+                ..CameraControllerConfig::default()
+            },
+            camera.position,
+            camera.target_position,
+        );
 
-            let index_format = wgpu::IndexFormat::Uint32;
-            // Create the render pipeline
-            let vertex_state = wgpu::VertexStateDescriptor {
-                index_format: Some(index_format),
-                vertex_buffers: &[
-                    wgpu::VertexBufferDescriptor {
-                        stride: 3 * std::mem::size_of::<f32>() as u64,
-                        step_mode: wgpu::InputStepMode::Vertex,
-                        attributes: &[wgpu::VertexAttributeDescriptor {
-                            format: wgpu::VertexFormat::Float3,
-                            offset: 0,
-                            shader_location: 0,
-                        }],
-                    },
-                    wgpu::VertexBufferDescriptor {
-                        stride: 2 * std::mem::size_of::<f32>() as u64,
-                        step_mode: wgpu::InputStepMode::Vertex,
-                        attributes: &[wgpu::VertexAttributeDescriptor {
-                            format: wgpu::VertexFormat::Float2,
-                            offset: 0,
-                            shader_location: 1,
-                        }],
-                    },
-                ],
-            };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::cast_slice(camera.combine().as_slice()),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
 
-            let vs_module = device.create_shader_module(&wgpu::include_spirv!(
-                "../../../target/shaders/simple.vert.spv"
-            ));
-            let fs_module = device.create_shader_module(&wgpu::include_spirv!(
-                "../../../target/shaders/simple.frag.spv"
-            ));
+        // Create bind group
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: None,
+        });
 
-            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: None,
-                layout: Some(&pipeline_layout),
-                vertex_stage: wgpu::ProgrammableStageDescriptor {
-                    module: &vs_module,
-                    entry_point: "main",
+        let index_format = wgpu::IndexFormat::Uint32;
+        // Create the render pipeline
+        let vertex_state = wgpu::VertexStateDescriptor {
+            index_format: Some(index_format),
+            vertex_buffers: &[
+                wgpu::VertexBufferDescriptor {
+                    stride: 3 * std::mem::size_of::<f32>() as u64,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float3,
+                        offset: 0,
+                        shader_location: 0,
+                    }],
                 },
-                fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                    module: &fs_module,
-                    entry_point: "main",
-                }),
-                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Back,
-                    ..Default::default()
-                }),
-                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-                color_states: &[wgpu::ColorStateDescriptor {
-                    format: context.gfx().swapchain_desc.format,
-                    color_blend: wgpu::BlendDescriptor::REPLACE,
-                    alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-                depth_stencil_state: None,
-                vertex_state: vertex_state.clone(),
-                sample_count: 1,
-                sample_mask: !0,
-                alpha_to_coverage_enabled: false,
-            });
+                wgpu::VertexBufferDescriptor {
+                    stride: 2 * std::mem::size_of::<f32>() as u64,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float2,
+                        offset: 0,
+                        shader_location: 1,
+                    }],
+                },
+            ],
+        };
 
-            Box::new(HelloWorldScene {
-                index_format,
-                index_count: indices.len(),
-                index_buffer,
-                position_buffer,
-                texcoord_buffer,
-                bind_group,
-                uniform_buffer,
-                pipeline,
-                camera,
-                angle: 0.0f32,
-            })
+        let vs_module = device.create_shader_module(&wgpu::include_spirv!(
+            "../../../target/shaders/simple.vert.spv"
+        ));
+        let fs_module = device.create_shader_module(&wgpu::include_spirv!(
+            "../../../target/shaders/simple.frag.spv"
+        ));
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vs_module,
+                entry_point: "main",
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fs_module,
+                entry_point: "main",
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::Back,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: &[wgpu::ColorStateDescriptor {
+                format,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }],
+            depth_stencil_state: None,
+            vertex_state: vertex_state.clone(),
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        HelloWorldRenderer {
+            index_format,
+            index_count: indices.len(),
+            index_buffer,
+            position_buffer,
+            texcoord_buffer,
+            bind_group,
+            uniform_buffer,
+            pipeline,
+            camera,
+            camera_controller,
+            clear_color: CLEAR_COLOR,
         }
     }
-}
 
-impl Scene for HelloWorldScene {
-    fn update(&mut self, _context: &mut SceneContext, _event: WindowEvent) -> SceneControlFlow {
-        SceneControlFlow::None
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        self.camera_controller.handle_event(event)
     }
 
-    fn render(&mut self, context: &mut SceneRenderContext) -> SceneControlFlow {
-        let frame = context.gfx_rendering().frame();
-        let queue = context.gfx().queue;
-        let device = context.gfx().device;
+    pub fn reset_camera(&mut self) {
+        self.camera_controller.reset();
+    }
+
+    pub fn camera(&self) -> &Camera3D {
+        &self.camera
+    }
 
-        self.angle += 0.01f32;
-        self.camera.position = vec3(self.angle.sin() * 4.0f32, 2.0f32, self.angle.cos() * 4.0f32);
-        self.camera.target_position = vec3(0.0f32, 0.0f32, 0.0f32);
-        self.camera.up = vec3(0.0f32, 1.0f32, 0.0f32);
-        self.camera.update();
+    pub fn camera_controller_mut(&mut self) -> &mut CameraController {
+        &mut self.camera_controller
+    }
+
+    pub fn clear_color(&self) -> [f32; 3] {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, clear_color: [f32; 3]) {
+        self.clear_color = clear_color;
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, delta: f32) {
+        self.camera_controller.update(delta, &mut self.camera);
         queue.write_buffer(
             &self.uniform_buffer,
             0,
             bytemuck::cast_slice(self.camera.combine().as_slice()),
         );
+    }
 
+    pub fn draw(&self, device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
+                    attachment: view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
+                            r: self.clear_color[0] as f64,
+                            g: self.clear_color[1] as f64,
+                            b: self.clear_color[2] as f64,
                             a: 1.0,
                         }),
                         store: true,
@@ -306,59 +353,214 @@ impl Scene for HelloWorldScene {
         }
 
         queue.submit(Some(encoder.finish()));
-        SceneControlFlow::None
     }
 }
 
-/*
-impl HelloWorldScene {
-    state: GameState,
-    renderer: GameRenderer,
+impl HeadlessScene for HelloWorldRenderer {
+    fn update(&mut self, context: &HeadlessContext, delta: f32) {
+        HelloWorldRenderer::update(self, context.queue, delta);
+    }
+
+    fn render(&mut self, context: &HeadlessContext, view: &wgpu::TextureView) {
+        self.draw(context.device, context.queue, view);
+    }
 }
 
-impl Scene for HelloWorldScene {
-    fn update(&mut self, ctx: &mut Context) {
-        let mut entity_command_buffer = ctx.entity_manager.create_command_buffer<GameStateComponent>();
+pub struct HelloWorldScene {
+    renderer: HelloWorldRenderer,
+    screenshot: ScreenshotCapture,
+    input: InputMap,
+    audio: SpatialAudio,
+    debug_ui: DebugUi,
+    text: TextRenderer,
+}
 
-        {
-            let system_1_job = MainSystemJob::process(&self.state.actions, self.state.character.positions);
-            let system_2_job = MainSystemJob::process(&self.state.actions, self.state.character.angles);
-            let system_3_job = MainSystemJob::process(&self.state.input, self.state.character.positions);
+impl HelloWorldScene {
+    pub fn factory() -> SceneFactory {
+        |context, _| {
+            let width = context.gfx().swapchain_desc.width as f32;
+            let height = context.gfx().swapchain_desc.height as f32;
+            let renderer = HelloWorldRenderer::new(
+                context.gfx().device,
+                context.gfx().queue,
+                context.gfx().swapchain_desc.format,
+                width / height,
+            );
+            let mut debug_ui = DebugUi::new(
+                context.gfx().device,
+                context.gfx().queue,
+                context.gfx().swapchain_desc.format,
+            );
+            debug_ui.set_scale_factor(initial_scale_factor(context.gfx().swapchain_desc.width));
+            let fonts = Fonts::new();
+            context
+                .spawner()
+                .spawn_local(load_default_fonts(fonts.clone()));
+            let text = TextRenderer::new(
+                context.gfx().device,
+                context.gfx().swapchain_desc.format,
+                fonts,
+            );
+            let game = GameContext::configured();
+            let audio = game.audio().clone();
+            context.spawner().spawn_local(load_sounds(audio.clone()));
+            context
+                .spawner()
+                .spawn_local(crossfade_to_scene_music(audio.clone(), MUSIC));
+
+            let input = InputMap::new(InputBindings::default());
+            context
+                .spawner()
+                .spawn_local(input.load_bindings(default_input_bindings_path()));
 
-            ctx.job_worker.barrier(&[system_1_job, system_1_job, system_3_job]);
+            Box::new(GameSceneHost::new(
+                game,
+                HelloWorldScene {
+                    renderer,
+                    screenshot: ScreenshotCapture::new(SCREENSHOT_DIRECTORY),
+                    input,
+                    audio: SpatialAudio::new(audio),
+                    debug_ui,
+                    text,
+                },
+            ))
         }
+    }
 
-        // Operate component sync
-        let mut component_factory = ctx.entity_manager.cmd(entity_command_buffer);
-        while let command = component_factory.pull() {
-            match command {
-                ...
-            }
+    pub fn headless_factory() -> HeadlessSceneFactory {
+        |context| {
+            let aspect = context.width as f32 / context.height as f32;
+            Box::new(HelloWorldRenderer::new(
+                context.device,
+                context.queue,
+                context.format,
+                aspect,
+            ))
         }
+    }
 
+    fn handle_event(&mut self, event: &WindowEvent) {
+        if self.debug_ui.handle_event(event) {
+            return;
+        }
+        self.input.handle_event(event);
+        if !self.screenshot.handle_event(event) {
+            self.renderer.handle_event(event);
+        }
+    }
+
+    fn update_debug_ui(&mut self) {
+        let controller = self.renderer.camera_controller_mut();
+        let mut distance = controller.distance();
+        let distance_range = controller.config.min_distance..=controller.config.max_distance;
+        if self
+            .debug_ui
+            .slider("Camera radius", &mut distance, distance_range)
         {
-            // Transform map for rendering
-            let system_4_job = RenderBatchSystemJob::process(
-                &self.state.character.positions,
-                &self.state.character.angles,
-                &self.state.character.status,
-                &self.renderer.character,
-            );
+            controller.set_distance(distance);
+        }
+        self.debug_ui.slider(
+            "Rotation speed",
+            &mut controller.config.auto_orbit_speed,
+            -MAX_ROTATION_SPEED..=MAX_ROTATION_SPEED,
+        );
 
-            // Transform characters for rendering
-            let system_5_job = RenderBatchSystemJob::process(
-                &self.state.character.positions,
-                &self.state.character.angles,
-                &self.state.character.status,
-                &self.renderer.character,
-            );
+        let mut clear_color = self.renderer.clear_color();
+        if self.debug_ui.color("Clear color", &mut clear_color) {
+            self.renderer.set_clear_color(clear_color);
+        }
+        if self.debug_ui.button("Reset") {
+            self.renderer.reset_camera();
+            self.renderer
+                .camera_controller_mut()
+                .config
+                .auto_orbit_speed = ROTATION_SPEED;
+            self.renderer.set_clear_color(CLEAR_COLOR);
+        }
+    }
+
+    /// Draws the scene, the caption and the debug overlay over `view`.
+    fn draw_frame(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        (width, height): (u32, u32),
+    ) {
+        self.renderer.draw(device, queue, view);
+        let style = TextStyle {
+            size: height as f32 / 16.0f32,
+            align: Align::Center,
+            max_width: Some(width as f32 * 0.9f32),
+            ..TextStyle::default()
+        };
+        let caption = self.text.layout(CAPTION, &style);
+        let caption_top = height as f32 * 0.95f32 - caption.height;
+        self.text
+            .queue_layout(caption, width as f32 * 0.05f32, caption_top, style.color);
+        self.text.draw(device, queue, view, (width, height));
+        self.debug_ui.render(device, queue, view);
+    }
+}
 
-            ctx.job_worker.barrier(&[system_4_job, system_5_job]);
+impl GameScene for HelloWorldScene {
+    fn update(&mut self, _game: &mut GameContext, event: GameEvent) -> SceneControlFlow {
+        match event {
+            GameEvent::Window(event) => self.handle_event(&event),
+            GameEvent::Gamepad(event) => self.input.handle_gamepad_event(&event),
         }
+        SceneControlFlow::None
     }
 
-    fn render(&mut self, gfx: &mut Gfx) {
-        self.renderer.render_all(gfx);
+    fn render(
+        &mut self,
+        context: &mut SceneRenderContext,
+        game: &mut GameContext,
+    ) -> SceneControlFlow {
+        let frame = context.gfx_rendering().frame();
+        let queue = context.gfx().queue;
+        let device = context.gfx().device;
+        let time = game.time();
+
+        if self.input.is_just_pressed("reset_camera") {
+            self.renderer.reset_camera();
+        }
+        self.renderer.update(queue, time.delta);
+        self.audio.follow_camera(self.renderer.camera(), time.delta);
+        if self.input.is_just_pressed("jump") {
+            let [x, y, z] = SQUARE_CENTER;
+            self.audio
+                .play("beep", vec3(x, y, z), EmitterOptions::default());
+        }
+        self.audio.update(time.delta);
+        let (width, height) = (
+            context.gfx().swapchain_desc.width,
+            context.gfx().swapchain_desc.height,
+        );
+        self.debug_ui
+            .begin_frame(game.measured_delta(), width, height);
+        self.update_debug_ui();
+        self.draw_frame(device, queue, &frame.view, (width, height));
+
+        let format = context.gfx().swapchain_desc.format;
+        if let Some(target) = self.screenshot.begin(device, width, height, format) {
+            self.draw_frame(device, queue, target.view(), (width, height));
+            match self.screenshot.save(device, queue, target) {
+                Ok(path) => log::info!("Saved screenshot to {:?}", path),
+                Err(e) => log::error!("Failed to save screenshot: {}", e),
+            }
+        }
+
+        self.input.end_frame();
+        SceneControlFlow::None
+    }
+}
+
+async fn load_sounds(audio: Audio) {
+    let mut path = PathBuf::new();
+    path.push(file_util().assets_path());
+    path.push("beep.wav");
+    if let Err(e) = audio.load("beep", path).await {
+        log::error!("{}", e);
     }
 }
-*/
@@ -1,2 +1,4 @@
+pub mod character_scene;
+pub mod context;
 pub mod file_scene;
 pub mod hello_world_scene;
@@ -0,0 +1,568 @@
+use crate::headless::HeadlessSceneFactory;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::save::storage::FileStorage;
+#[cfg(target_arch = "wasm32")]
+use crate::save::storage::LocalStorage;
+use crate::save::storage::Storage;
+use crate::scene::character_scene::CharacterScene;
+use crate::scene::file_scene::FileScene;
+use crate::scene::hello_world_scene::HelloWorldScene;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use tearchan::engine_config::EngineStartupConfig;
+#[cfg(not(target_arch = "wasm32"))]
+use tearchan::fs::file_util;
+use tearchan::scene::factory::SceneFactory;
+use winit::dpi::LogicalSize;
+use winit::window::Fullscreen;
+
+pub const DEFAULT_SETTINGS: &str = include_str!("../../assets/settings.json");
+
+/// Name of the user's settings in `user_storage`.
+pub const USER_SETTINGS_NAME: &str = "settings";
+
+const MAX_WINDOW_SIZE: u32 = 16384;
+const MSAA_SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Vulkan,
+    Metal,
+    Dx12,
+    Dx11,
+    Gl,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 5] = [
+        Backend::Vulkan,
+        Backend::Metal,
+        Backend::Dx12,
+        Backend::Dx11,
+        Backend::Gl,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Vulkan => "vulkan",
+            Backend::Metal => "metal",
+            Backend::Dx12 => "dx12",
+            Backend::Dx11 => "dx11",
+            Backend::Gl => "gl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Backend> {
+        let name = name.to_lowercase();
+        Backend::ALL
+            .iter()
+            .copied()
+            .find(|backend| backend.name() == name)
+    }
+
+    pub fn bits(self) -> wgpu::BackendBit {
+        match self {
+            Backend::Vulkan => wgpu::BackendBit::VULKAN,
+            Backend::Metal => wgpu::BackendBit::METAL,
+            Backend::Dx12 => wgpu::BackendBit::DX12,
+            Backend::Dx11 => wgpu::BackendBit::DX11,
+            Backend::Gl => wgpu::BackendBit::GL,
+        }
+    }
+}
+
+/// The scene the app starts with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartScene {
+    HelloWorld,
+    Character,
+    File,
+}
+
+impl StartScene {
+    pub const ALL: [StartScene; 3] = [
+        StartScene::HelloWorld,
+        StartScene::Character,
+        StartScene::File,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StartScene::HelloWorld => "hello_world",
+            StartScene::Character => "character",
+            StartScene::File => "file",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StartScene> {
+        StartScene::ALL
+            .iter()
+            .copied()
+            .find(|scene| scene.name() == name)
+    }
+
+    pub fn factory(self) -> SceneFactory {
+        match self {
+            StartScene::HelloWorld => HelloWorldScene::factory(),
+            StartScene::Character => CharacterScene::factory(),
+            StartScene::File => FileScene::factory(),
+        }
+    }
+
+    /// For recording without a window; `None` for scenes that draw nothing.
+    pub fn headless_factory(self) -> Option<HeadlessSceneFactory> {
+        match self {
+            StartScene::HelloWorld => Some(HelloWorldScene::headless_factory()),
+            StartScene::Character => Some(CharacterScene::headless_factory()),
+            StartScene::File => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    /// `source` names where the broken JSON came from.
+    Json {
+        source: String,
+        error: serde_json::Error,
+    },
+    Storage(String),
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Json { source, error } => {
+                write!(f, "Invalid settings in {}: {}", source, error)
+            }
+            SettingsError::Storage(e) => write!(f, "Failed to read the settings: {}", e),
+            SettingsError::Invalid { field, message } => {
+                write!(f, "Invalid setting {:?}: {}", field, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// How the app starts. Loaded from `assets/settings.json`, overlaid with the user's settings
+/// and then with command line overrides.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub title: String,
+    /// Window size in logical pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Borderless fullscreen on the current monitor.
+    pub fullscreen: bool,
+    /// Samples per pixel for renderers that support multisampling; 1 turns it off.
+    pub msaa: u32,
+    /// Forces a graphics backend instead of letting wgpu pick one.
+    pub backend: Option<Backend>,
+    pub scene: StartScene,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_SETTINGS).expect("Invalid default settings")
+    }
+}
+
+/// Values given on the command line. Names are checked when applied.
+#[derive(Clone, Debug, Default)]
+pub struct SettingsOverrides {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: Option<bool>,
+    pub msaa: Option<u32>,
+    pub backend: Option<String>,
+    pub scene: Option<String>,
+}
+
+impl Settings {
+    /// Merges JSON layers, later ones winning field by field, and validates the result.
+    /// Each layer is `(source, json)`; `source` only shows up in errors.
+    pub fn from_layers(layers: &[(&str, &str)]) -> Result<Settings, SettingsError> {
+        let mut merged = Value::Object(Default::default());
+        let mut settings = None;
+        for (source, text) in layers {
+            let json_error = |error| SettingsError::Json {
+                source: source.to_string(),
+                error,
+            };
+            let layer: Value = serde_json::from_str(text).map_err(json_error)?;
+            merge(&mut merged, layer);
+            // Checked after every layer so that errors name the file that caused them
+            settings = Some(serde_json::from_value(merged.clone()).map_err(json_error)?);
+        }
+        let settings: Settings = match settings {
+            Some(settings) => settings,
+            None => Settings::default(),
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.title.trim().is_empty() {
+            return Err(invalid("title", "must not be empty".to_string()));
+        }
+        for (field, value) in [("width", self.width), ("height", self.height)].iter() {
+            if *value == 0 || *value > MAX_WINDOW_SIZE {
+                return Err(invalid(
+                    *field,
+                    format!("{} is outside 1..={}", value, MAX_WINDOW_SIZE),
+                ));
+            }
+        }
+        if !MSAA_SAMPLE_COUNTS.contains(&self.msaa) {
+            return Err(invalid(
+                "msaa",
+                format!("{} isn't one of {:?}", self.msaa, MSAA_SAMPLE_COUNTS),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &SettingsOverrides) -> Result<(), SettingsError> {
+        // Left untouched if any override is invalid
+        let mut overridden = self.clone();
+        if let Some(width) = overrides.width {
+            overridden.width = width;
+        }
+        if let Some(height) = overrides.height {
+            overridden.height = height;
+        }
+        if let Some(fullscreen) = overrides.fullscreen {
+            overridden.fullscreen = fullscreen;
+        }
+        if let Some(msaa) = overrides.msaa {
+            overridden.msaa = msaa;
+        }
+        if let Some(name) = &overrides.backend {
+            let backend = Backend::from_name(name).ok_or_else(|| {
+                invalid(
+                    "backend",
+                    format!(
+                        "{:?} isn't one of {}",
+                        name,
+                        names(Backend::ALL.iter().map(|b| b.name()))
+                    ),
+                )
+            })?;
+            overridden.backend = Some(backend);
+        }
+        if let Some(name) = &overrides.scene {
+            let scene = StartScene::from_name(name).ok_or_else(|| {
+                invalid(
+                    "scene",
+                    format!(
+                        "{:?} isn't one of {}",
+                        name,
+                        names(StartScene::ALL.iter().map(|s| s.name()))
+                    ),
+                )
+            })?;
+            overridden.scene = scene;
+        }
+        overridden.validate()?;
+        *self = overridden;
+        Ok(())
+    }
+
+    /// Points wgpu at the chosen backend through `WGPU_BACKEND`. Call before any adapter is
+    /// requested. Logs an error if this machine has no adapter for it, since the engine then
+    /// picks another backend.
+    pub fn apply_backend(&self) {
+        let backend = match self.backend {
+            Some(backend) => backend,
+            None => return,
+        };
+        std::env::set_var("WGPU_BACKEND", backend.name());
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let instance = wgpu::Instance::new(backend.bits());
+            if instance.enumerate_adapters(backend.bits()).next().is_none() {
+                log::error!(
+                    "The {} backend has no adapter on this machine; another one will be used",
+                    backend.name()
+                );
+            }
+        }
+    }
+
+    pub fn startup_config(&self) -> EngineStartupConfig {
+        let mut config = EngineStartupConfig::new_with_title(&self.title, self.scene.factory());
+        let mut window_builder = std::mem::take(&mut config.window_builder)
+            .with_inner_size(LogicalSize::new(self.width, self.height));
+        if self.fullscreen {
+            window_builder = window_builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        config.window_builder = window_builder;
+        config
+    }
+}
+
+/// Where the user's own settings live: `user/` next to the assets, or `localStorage` on
+/// the web.
+pub fn user_storage() -> Box<dyn Storage> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut directory = PathBuf::new();
+        directory.push(file_util().assets_path());
+        directory.pop();
+        directory.push("user");
+        Box::new(FileStorage::new(directory))
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        Box::new(LocalStorage::new("user/"))
+    }
+}
+
+/// The bundled defaults overlaid with the user's settings, if there are any.
+pub fn load_settings() -> Result<Settings, SettingsError> {
+    let user = user_storage()
+        .read(USER_SETTINGS_NAME)
+        .map_err(|e| SettingsError::Storage(e.to_string()))?
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    match user {
+        Some(user) => Settings::from_layers(&[
+            ("assets/settings.json", DEFAULT_SETTINGS),
+            ("the user settings", &user),
+        ]),
+        None => Settings::from_layers(&[("assets/settings.json", DEFAULT_SETTINGS)]),
+    }
+}
+
+/// Like `load_settings`, but logs broken user settings and starts with the defaults instead,
+/// so a bad file never keeps the app from starting.
+pub fn load_settings_or_default() -> Settings {
+    load_settings().unwrap_or_else(|e| {
+        log::error!("{}; using the default settings", e);
+        Settings::default()
+    })
+}
+
+thread_local! {
+    static CURRENT_SETTINGS: RefCell<Option<Settings>> = RefCell::new(None);
+}
+
+/// Makes `settings` visible to scenes through `current_settings`. Call before `launch_app`.
+pub fn configure_settings(settings: Settings) {
+    CURRENT_SETTINGS.with(|current| *current.borrow_mut() = Some(settings));
+}
+
+/// The settings the app was launched with.
+pub fn current_settings() -> Settings {
+    CURRENT_SETTINGS.with(|current| current.borrow().clone().unwrap_or_default())
+}
+
+/// The window's scale factor when a scene is built, worked out from the swap chain since
+/// scenes get no window and winit only sends `ScaleFactorChanged` when it changes.
+///
+/// Desktop windows open at the logical size from the settings, so the ratio is exact unless
+/// the window was resized before the scene was built. Fullscreen and mobile windows don't
+/// follow the settings and start at 1.0 until the first change.
+pub fn initial_scale_factor(swapchain_width: u32) -> f64 {
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android", target_os = "ios")))]
+    {
+        let settings = current_settings();
+        if settings.fullscreen || swapchain_width == 0 {
+            return 1.0f64;
+        }
+        swapchain_width as f64 / settings.width as f64
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = swapchain_width;
+        web_sys::window()
+            .map(|window| window.device_pixel_ratio())
+            .unwrap_or(1.0f64)
+    }
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    {
+        let _ = swapchain_width;
+        1.0f64
+    }
+}
+
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+fn invalid(field: &'static str, message: String) -> SettingsError {
+    SettingsError::Invalid { field, message }
+}
+
+fn names<'a, I: Iterator<Item = &'a str>>(names: I) -> String {
+    names.collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_error_source(result: Result<Settings, SettingsError>) -> String {
+        match result {
+            Err(SettingsError::Json { source, .. }) => source,
+            result => panic!("Expected a JSON error, got {:?}", result),
+        }
+    }
+
+    fn invalid_field(result: Result<impl std::fmt::Debug, SettingsError>) -> &'static str {
+        match result {
+            Err(SettingsError::Invalid { field, .. }) => field,
+            result => panic!("Expected an invalid setting, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn later_layers_win_field_by_field() {
+        let settings = Settings::from_layers(&[
+            ("defaults", DEFAULT_SETTINGS),
+            ("user", r#"{"width": 800, "backend": "vulkan"}"#),
+        ])
+        .unwrap();
+        assert_eq!(settings.width, 800);
+        assert_eq!(settings.backend, Some(Backend::Vulkan));
+        assert_eq!(settings.height, Settings::default().height);
+
+        assert_eq!(Settings::from_layers(&[]).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn errors_name_the_layer_that_caused_them() {
+        let broken = Settings::from_layers(&[("defaults", DEFAULT_SETTINGS), ("user", "{")]);
+        assert_eq!(json_error_source(broken), "user");
+
+        let wrong_type = Settings::from_layers(&[
+            ("defaults", DEFAULT_SETTINGS),
+            ("user", r#"{"width": "wide"}"#),
+            ("extra", r#"{"height": 600}"#),
+        ]);
+        assert_eq!(json_error_source(wrong_type), "user");
+
+        let unknown_field = Settings::from_layers(&[
+            ("defaults", DEFAULT_SETTINGS),
+            ("user", r#"{"colour": "red"}"#),
+        ]);
+        assert_eq!(json_error_source(unknown_field), "user");
+
+        let unknown_scene = Settings::from_layers(&[
+            ("defaults", DEFAULT_SETTINGS),
+            ("user", r#"{"scene": "menu"}"#),
+        ]);
+        assert_eq!(json_error_source(unknown_scene), "user");
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let settings = Settings::default();
+        assert!(settings.validate().is_ok());
+
+        let msaa =
+            Settings::from_layers(&[("defaults", DEFAULT_SETTINGS), ("user", r#"{"msaa": 3}"#)]);
+        assert_eq!(invalid_field(msaa), "msaa");
+
+        let zero_width = Settings {
+            width: 0,
+            ..settings.clone()
+        };
+        assert_eq!(invalid_field(zero_width.validate()), "width");
+
+        let huge_height = Settings {
+            height: MAX_WINDOW_SIZE + 1,
+            ..settings.clone()
+        };
+        assert_eq!(invalid_field(huge_height.validate()), "height");
+
+        let blank_title = Settings {
+            title: " ".to_string(),
+            ..settings
+        };
+        assert_eq!(invalid_field(blank_title.validate()), "title");
+    }
+
+    #[test]
+    fn applies_overrides() {
+        let mut settings = Settings::default();
+        settings
+            .apply_overrides(&SettingsOverrides {
+                width: Some(640),
+                fullscreen: Some(true),
+                msaa: Some(4),
+                backend: Some("Metal".to_string()),
+                scene: Some("character".to_string()),
+                ..SettingsOverrides::default()
+            })
+            .unwrap();
+        assert_eq!(settings.width, 640);
+        assert!(settings.fullscreen);
+        assert_eq!(settings.msaa, 4);
+        assert_eq!(settings.backend, Some(Backend::Metal));
+        assert_eq!(settings.scene, StartScene::Character);
+    }
+
+    #[test]
+    fn invalid_overrides_change_nothing() {
+        let original = Settings::default();
+        let valid = SettingsOverrides {
+            width: Some(640),
+            msaa: Some(2),
+            ..SettingsOverrides::default()
+        };
+        let cases = vec![
+            (
+                "backend",
+                SettingsOverrides {
+                    backend: Some("glide".to_string()),
+                    ..valid.clone()
+                },
+            ),
+            (
+                "scene",
+                SettingsOverrides {
+                    scene: Some("menu".to_string()),
+                    ..valid.clone()
+                },
+            ),
+            (
+                "msaa",
+                SettingsOverrides {
+                    msaa: Some(16),
+                    ..valid.clone()
+                },
+            ),
+            (
+                "height",
+                SettingsOverrides {
+                    height: Some(0),
+                    ..valid
+                },
+            ),
+        ];
+        for (field, overrides) in cases {
+            let mut settings = original.clone();
+            assert_eq!(invalid_field(settings.apply_overrides(&overrides)), field);
+            assert_eq!(settings, original);
+        }
+    }
+}
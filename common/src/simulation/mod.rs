@@ -0,0 +1,171 @@
+use crate::action::character::CustomActionCreatorState;
+use crate::action::Tick;
+use crate::ecs::EntityId;
+use crate::game::demo::DemoWorld;
+use crate::lockstep::{LockstepInput, LockstepSimulation, DEFAULT_TICK_DURATION};
+use crate::map::TilePosition;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// Tries at finding a passable destination before a bot gives up for the tick
+const DESTINATION_ATTEMPTS: usize = 16;
+
+#[derive(Clone, Debug)]
+pub struct SimulationConfig {
+    pub tick_duration: Tick,
+    /// Stops after this many ticks; runs until stopped when `None`.
+    pub ticks: Option<u64>,
+    /// Sleeps between ticks to run at wall-clock speed instead of as fast as possible.
+    pub realtime: bool,
+    pub seed: u64,
+    /// Gives idle characters random errands.
+    pub bots: bool,
+    /// Logs progress every this many ticks; 0 turns it off.
+    pub report_interval: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            tick_duration: DEFAULT_TICK_DURATION,
+            ticks: None,
+            realtime: true,
+            seed: 1,
+            bots: true,
+            report_interval: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SimulationReport {
+    pub ticks: u64,
+    pub elapsed: Duration,
+    /// State hash after the last tick; equal seeds and tick counts give equal hashes.
+    pub hash: u64,
+    /// Ticks that took longer than `tick_duration` to simulate in realtime mode.
+    pub late_ticks: u64,
+}
+
+impl SimulationReport {
+    pub fn ticks_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0f64 {
+            self.ticks as f64 / seconds
+        } else {
+            0.0f64
+        }
+    }
+}
+
+/// Runs the game logic with no window or GPU, for servers, bots and soak tests.
+pub struct HeadlessSimulation {
+    simulation: LockstepSimulation,
+    characters: Vec<EntityId>,
+    items: Vec<EntityId>,
+    config: SimulationConfig,
+}
+
+impl HeadlessSimulation {
+    pub fn new(world: DemoWorld, config: SimulationConfig) -> Self {
+        HeadlessSimulation {
+            simulation: LockstepSimulation::new(world.state, config.seed, config.tick_duration),
+            characters: world.characters,
+            items: world.items,
+            config,
+        }
+    }
+
+    pub fn simulation(&self) -> &LockstepSimulation {
+        &self.simulation
+    }
+
+    /// Runs one tick and returns the state hash.
+    pub fn step(&mut self) -> u64 {
+        if self.config.bots {
+            self.order_idle_characters();
+        }
+        self.simulation.step()
+    }
+
+    /// Steps until the configured tick count is reached or `running` is cleared.
+    pub fn run(&mut self, running: &AtomicBool) -> SimulationReport {
+        let interval = Duration::from_millis(self.config.tick_duration);
+        let started = Instant::now();
+        let mut next = started;
+        let mut ticks = 0;
+        let mut late_ticks = 0;
+        while running.load(Ordering::Relaxed)
+            && self.config.ticks.map(|limit| ticks < limit).unwrap_or(true)
+        {
+            let hash = self.step();
+            ticks += 1;
+
+            if self.config.report_interval > 0 && ticks % self.config.report_interval == 0 {
+                log::info!(
+                    "tick {} hash {:016x} ({:.0} ticks/s)",
+                    self.simulation.tick(),
+                    hash,
+                    ticks as f64 / started.elapsed().as_secs_f64().max(std::f64::EPSILON)
+                );
+            }
+
+            if self.config.realtime {
+                next += interval;
+                let now = Instant::now();
+                if next > now {
+                    std::thread::sleep(next - now);
+                } else {
+                    late_ticks += 1;
+                    next = now;
+                }
+            }
+        }
+        SimulationReport {
+            ticks,
+            elapsed: started.elapsed(),
+            hash: self.simulation.last_hash(),
+            late_ticks,
+        }
+    }
+
+    /// Sends each idle character to bring a random item to a random passable tile.
+    fn order_idle_characters(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+        let tick = self.simulation.tick();
+        for character in self.characters.iter() {
+            if !self.simulation.state().is_idle(*character) {
+                continue;
+            }
+            let rng = self.simulation.rng_mut();
+            let item = self.items[rng.range(0, self.items.len() as i32) as usize];
+            let destination = match random_destination(&mut self.simulation) {
+                Some(destination) => destination,
+                None => continue,
+            };
+            self.simulation.schedule(LockstepInput {
+                tick,
+                entity_id: *character,
+                command: CustomActionCreatorState::BringItemToDestination {
+                    item,
+                    destination: destination.to_position(),
+                },
+            });
+        }
+    }
+}
+
+fn random_destination(simulation: &mut LockstepSimulation) -> Option<TilePosition> {
+    let width = simulation.state().ground.tiles.width() as i32;
+    let height = simulation.state().ground.tiles.height() as i32;
+    for _ in 0..DESTINATION_ATTEMPTS {
+        let rng = simulation.rng_mut();
+        let tile = TilePosition::new(rng.range(0, width), rng.range(0, height));
+        if simulation.state().map_path.is_passable(&tile) {
+            return Some(tile);
+        }
+    }
+    None
+}
@@ -0,0 +1,211 @@
+use rusttype::{point, Font, GlyphId, Scale};
+use std::collections::HashMap;
+
+/// Empty texels kept around every glyph so linear filtering doesn't pick up neighbours.
+const GLYPH_PADDING: u32 = 1;
+
+/// Glyph sizes are rounded to this fraction of a pixel, so text animated through many sizes
+/// doesn't fill the atlas with near-identical copies.
+const SIZE_STEPS_PER_PIXEL: f32 = 4.0f32;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct GlyphKey {
+    pub font: usize,
+    pub id: u16,
+    pub size: u32,
+}
+
+impl GlyphKey {
+    pub fn new(font: usize, id: GlyphId, size: f32) -> Self {
+        GlyphKey {
+            font,
+            id: id.0,
+            size: (size * SIZE_STEPS_PER_PIXEL).round() as u32,
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size as f32 / SIZE_STEPS_PER_PIXEL
+    }
+}
+
+/// Where a glyph's coverage is stored. Glyphs without outlines, like spaces, have no texels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasGlyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Top left of the bitmap relative to the pen position on the baseline.
+    pub left: i32,
+    pub top: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+/// A texture-sized bitmap that glyphs are rasterized into the first time they are drawn.
+///
+/// Glyphs are packed on shelves. When the atlas is full, `insert` returns `None` and the
+/// caller clears it and starts over with the glyphs it currently needs.
+pub struct GlyphAtlas {
+    width: u32,
+    height: u32,
+    coverage: Vec<u8>,
+    glyphs: HashMap<GlyphKey, AtlasGlyph>,
+    shelves: Vec<Shelf>,
+    /// Texel rows touched since the last `take_dirty`.
+    dirty: Option<(u32, u32)>,
+}
+
+impl GlyphAtlas {
+    pub fn new(width: u32, height: u32) -> Self {
+        GlyphAtlas {
+            width,
+            height,
+            coverage: vec![0; (width * height) as usize],
+            glyphs: HashMap::new(),
+            shelves: vec![],
+            dirty: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    pub fn get(&self, key: &GlyphKey) -> Option<AtlasGlyph> {
+        self.glyphs.get(key).copied()
+    }
+
+    /// Returns the glyph, rasterizing it first if needed, or `None` if it doesn't fit.
+    pub fn insert(&mut self, fonts: &[Font<'static>], key: GlyphKey) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return Some(*glyph);
+        }
+        let font = fonts.get(key.font)?;
+        let glyph = font
+            .glyph(GlyphId(key.id))
+            .scaled(Scale::uniform(key.size()))
+            .positioned(point(0.0f32, 0.0f32));
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => {
+                let empty = AtlasGlyph {
+                    x: 0,
+                    y: 0,
+                    width: 0,
+                    height: 0,
+                    left: 0,
+                    top: 0,
+                };
+                self.glyphs.insert(key, empty);
+                return Some(empty);
+            }
+        };
+
+        let width = bounds.width() as u32;
+        let height = bounds.height() as u32;
+        let (x, y) = self.allocate(width + GLYPH_PADDING * 2, height + GLYPH_PADDING * 2)?;
+        let (x, y) = (x + GLYPH_PADDING, y + GLYPH_PADDING);
+        let atlas_width = self.width;
+        let coverage = &mut self.coverage;
+        glyph.draw(|gx, gy, value| {
+            let offset = ((y + gy) * atlas_width + x + gx) as usize;
+            coverage[offset] = (value.max(0.0f32).min(1.0f32) * 255.0f32).round() as u8;
+        });
+        self.mark_dirty(y, y + height);
+
+        let atlas_glyph = AtlasGlyph {
+            x,
+            y,
+            width,
+            height,
+            left: bounds.min.x,
+            top: bounds.min.y,
+        };
+        self.glyphs.insert(key, atlas_glyph);
+        Some(atlas_glyph)
+    }
+
+    pub fn clear(&mut self) {
+        for value in self.coverage.iter_mut() {
+            *value = 0;
+        }
+        self.glyphs.clear();
+        self.shelves.clear();
+        self.dirty = Some((0, self.height));
+    }
+
+    /// Returns the first row and the RGBA texels (white, coverage in alpha) of the rows that
+    /// changed since the last call, for uploading to the texture.
+    pub fn take_dirty(&mut self) -> Option<(u32, u32, Vec<u8>)> {
+        let (top, bottom) = self.dirty.take()?;
+        let start = (top * self.width) as usize;
+        let end = (bottom * self.width) as usize;
+        let mut texels = Vec::with_capacity((end - start) * 4);
+        for value in &self.coverage[start..end] {
+            texels.extend_from_slice(&[255, 255, 255, *value]);
+        }
+        Some((top, bottom - top, texels))
+    }
+
+    fn mark_dirty(&mut self, top: u32, bottom: u32) {
+        self.dirty = Some(match self.dirty {
+            Some((dirty_top, dirty_bottom)) => (dirty_top.min(top), dirty_bottom.max(bottom)),
+            None => (top, bottom),
+        });
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.width {
+            return None;
+        }
+        // The shortest shelf that fits without wasting more than a third of its height
+        let atlas_width = self.width;
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= height
+                    && shelf.height * 2 <= height * 3
+                    && shelf.x + width <= atlas_width
+            })
+            .min_by_key(|shelf| shelf.height);
+        if let Some(shelf) = best {
+            let x = shelf.x;
+            shelf.x += width;
+            return Some((x, shelf.y));
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if y + height > self.height {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            x: width,
+        });
+        Some((0, y))
+    }
+}
@@ -0,0 +1,122 @@
+use rusttype::{Font, GlyphId, Scale, VMetrics};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tearchan::fs::{file_util, read_bytes_from_file};
+
+/// Fonts the example ships with, relative to the assets directory, in fallback order.
+pub const DEFAULT_FONTS: [&str; 1] = ["fonts/DejaVuSans.ttf"];
+
+#[derive(Debug)]
+pub enum FontError {
+    Load(String),
+    Parse(PathBuf),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Load(message) => write!(f, "failed to read a font: {}", message),
+            FontError::Parse(path) => write!(f, "{:?} is not a TTF/OTF font", path),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+/// A font stack: characters are drawn with the first font that has a glyph for them, so a
+/// Latin font can come first and a CJK font can cover the rest.
+///
+/// Cloning is cheap; every clone sees fonts pushed through any other, which lets a scene
+/// hand the stack to its renderer and fill it from an async loader.
+#[derive(Clone, Default)]
+pub struct Fonts {
+    fonts: Arc<Mutex<Vec<Font<'static>>>>,
+}
+
+impl Fonts {
+    pub fn new() -> Self {
+        Fonts::default()
+    }
+
+    /// Parses a TTF or OTF file and appends it to the stack.
+    pub fn push_bytes(&self, bytes: Vec<u8>) -> Option<usize> {
+        let font = Font::try_from_vec(bytes)?;
+        let mut fonts = self.fonts.lock().unwrap();
+        fonts.push(font);
+        Some(fonts.len() - 1)
+    }
+
+    /// Reads a font through `tearchan::fs` and appends it to the stack.
+    pub async fn load<P: AsRef<Path>>(&self, path: P) -> Result<usize, FontError> {
+        let path = path.as_ref().to_path_buf();
+        let bytes = read_bytes_from_file(path.clone())
+            .await
+            .map_err(|e| FontError::Load(format!("{:?}: {:?}", path, e)))?;
+        self.push_bytes(bytes).ok_or(FontError::Parse(path))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fonts loaded so far, in fallback order. Fonts are reference counted, so this is cheap.
+    pub fn snapshot(&self) -> Vec<Font<'static>> {
+        self.fonts.lock().unwrap().clone()
+    }
+}
+
+/// Loads `DEFAULT_FONTS` from the assets directory, one after another so the fallback order
+/// doesn't depend on which read finishes first.
+pub async fn load_default_fonts(fonts: Fonts) {
+    for name in DEFAULT_FONTS.iter() {
+        if let Err(e) = fonts.load(asset_path(name)).await {
+            log::error!("{}", e);
+        }
+    }
+}
+
+fn asset_path(name: &str) -> PathBuf {
+    let mut path = PathBuf::new();
+    path.push(file_util().assets_path());
+    path.push(name);
+    path
+}
+
+/// Index of the first font with a glyph for `c`. Falls back to the first font's missing glyph.
+pub fn find_glyph(fonts: &[Font<'static>], c: char) -> (usize, GlyphId) {
+    fonts
+        .iter()
+        .enumerate()
+        .map(|(index, font)| (index, font.glyph(c).id()))
+        .find(|(_, id)| id.0 != 0)
+        .unwrap_or((0, GlyphId(0)))
+}
+
+/// The tallest ascent and deepest descent of the stack, so lines have the same height whichever
+/// fonts they happen to use.
+pub fn stack_v_metrics(fonts: &[Font<'static>], size: f32) -> VMetrics {
+    let scale = Scale::uniform(size);
+    fonts
+        .iter()
+        .map(|font| font.v_metrics(scale))
+        .fold(None, |metrics: Option<VMetrics>, font| {
+            Some(match metrics {
+                Some(metrics) => VMetrics {
+                    ascent: metrics.ascent.max(font.ascent),
+                    descent: metrics.descent.min(font.descent),
+                    line_gap: metrics.line_gap.max(font.line_gap),
+                },
+                None => font,
+            })
+        })
+        .unwrap_or(VMetrics {
+            ascent: size,
+            descent: 0.0f32,
+            line_gap: 0.0f32,
+        })
+}
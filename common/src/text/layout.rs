@@ -0,0 +1,342 @@
+use crate::text::font::{find_glyph, stack_v_metrics};
+use rusttype::{Font, GlyphId, Scale};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Default for Align {
+    fn default() -> Self {
+        Align::Left
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Pixel height of the em square.
+    pub size: f32,
+    pub color: [f32; 4],
+    pub align: Align,
+    /// Lines longer than this many pixels are wrapped. `None` only breaks at `\n`.
+    pub max_width: Option<f32>,
+    /// Multiplier of the font's line height.
+    pub line_spacing: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            size: 24.0f32,
+            color: [1.0f32, 1.0f32, 1.0f32, 1.0f32],
+            align: Align::Left,
+            max_width: None,
+            line_spacing: 1.0f32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LaidOutGlyph {
+    pub font: usize,
+    pub id: GlyphId,
+    pub character: char,
+    /// Pen position relative to the top left of the layout box; `y` is on the baseline.
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    /// Range into `TextLayout::glyphs`.
+    pub start: usize,
+    pub end: usize,
+    pub width: f32,
+}
+
+/// Glyph positions for a string, ready to be drawn at any point on screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLayout {
+    pub size: f32,
+    pub glyphs: Vec<LaidOutGlyph>,
+    pub lines: Vec<Line>,
+    /// `max_width` when wrapping, otherwise the widest line.
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Characters that must not start a line (Japanese kinsoku shori, plus Latin closing punctuation).
+const NO_LINE_START: &str = ",.!?:;)]}%、。，．・：；？！ー～…‥」』）】〉》〕］｝〙〗ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ々ゝゞヽヾ";
+/// Characters that must not end a line.
+const NO_LINE_END: &str = "([{「『（【〈《〔［｛〘〖";
+
+/// Scripts written without spaces, which may break between any two characters.
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303f // CJK symbols and punctuation
+        | 0x3040..=0x30ff // Hiragana, Katakana
+        | 0x31f0..=0x31ff // Katakana phonetic extensions
+        | 0x3400..=0x4dbf // CJK unified ideographs extension A
+        | 0x4e00..=0x9fff // CJK unified ideographs
+        | 0xac00..=0xd7af // Hangul syllables
+        | 0xf900..=0xfaff // CJK compatibility ideographs
+        | 0xff00..=0xffef // Halfwidth and fullwidth forms
+        | 0x20000..=0x2fa1f) // Supplementary ideographs
+}
+
+/// Whether a line may be broken between `before` and `after`.
+pub fn can_break_between(before: char, after: char) -> bool {
+    if NO_LINE_START.contains(after) || NO_LINE_END.contains(before) {
+        return false;
+    }
+    before.is_whitespace() || is_cjk(before) || is_cjk(after)
+}
+
+struct Measured {
+    c: char,
+    font: usize,
+    id: GlyphId,
+    advance: f32,
+    /// Adjustment against the previous character, applied unless a line starts here.
+    kerning: f32,
+}
+
+/// Lays out UTF-8 `text` with the font stack, wrapping at spaces and between CJK characters.
+///
+/// Lines are aligned inside a box of `max_width`, or of the widest line when not wrapping.
+/// Spaces where a line was wrapped are dropped.
+pub fn layout_text(fonts: &[Font<'static>], text: &str, style: &TextStyle) -> TextLayout {
+    let scale = Scale::uniform(style.size);
+    let v_metrics = stack_v_metrics(fonts, style.size);
+    let line_height =
+        (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap) * style.line_spacing;
+
+    let mut glyphs = vec![];
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let measured = measure(fonts, paragraph.trim_end_matches('\r'), scale);
+        for (start, end) in break_lines(&measured, style.max_width) {
+            let baseline = v_metrics.ascent + line_height * lines.len() as f32;
+            let first = glyphs.len();
+            let mut x = 0.0f32;
+            for (i, m) in measured[start..end].iter().enumerate() {
+                if i > 0 {
+                    x += m.kerning;
+                }
+                glyphs.push(LaidOutGlyph {
+                    font: m.font,
+                    id: m.id,
+                    character: m.c,
+                    x,
+                    y: baseline,
+                });
+                x += m.advance;
+            }
+            let width = visible_width(&measured[start..end]);
+            lines.push(Line {
+                start: first,
+                end: glyphs.len(),
+                width,
+            });
+        }
+    }
+
+    let widest = lines.iter().map(|line| line.width).fold(0.0f32, f32::max);
+    let width = style.max_width.unwrap_or(widest);
+    for line in &lines {
+        let offset = match style.align {
+            Align::Left => 0.0f32,
+            Align::Center => ((width - line.width) * 0.5f32).round(),
+            Align::Right => (width - line.width).round(),
+        };
+        for glyph in &mut glyphs[line.start..line.end] {
+            glyph.x += offset;
+        }
+    }
+
+    TextLayout {
+        size: style.size,
+        glyphs,
+        height: line_height * lines.len() as f32,
+        lines,
+        width,
+    }
+}
+
+fn measure(fonts: &[Font<'static>], text: &str, scale: Scale) -> Vec<Measured> {
+    let mut measured: Vec<Measured> = vec![];
+    for c in text.chars().filter(|c| !c.is_control()) {
+        let (font, id) = if fonts.is_empty() {
+            (0, GlyphId(0))
+        } else {
+            find_glyph(fonts, c)
+        };
+        let advance = match fonts.get(font) {
+            Some(f) => f.glyph(id).scaled(scale).h_metrics().advance_width,
+            None => 0.0f32,
+        };
+        let kerning = match measured.last() {
+            Some(previous) if previous.font == font => {
+                fonts[font].pair_kerning(scale, previous.id, id)
+            }
+            _ => 0.0f32,
+        };
+        measured.push(Measured {
+            c,
+            font,
+            id,
+            advance,
+            kerning,
+        });
+    }
+    measured
+}
+
+/// Width of a line without trailing spaces.
+fn visible_width(line: &[Measured]) -> f32 {
+    let end = line
+        .iter()
+        .rposition(|m| !m.c.is_whitespace())
+        .map_or(0, |i| i + 1);
+    line[..end]
+        .iter()
+        .enumerate()
+        .fold(0.0f32, |width, (i, m)| {
+            if i == 0 {
+                width + m.advance
+            } else {
+                width + m.advance + m.kerning
+            }
+        })
+}
+
+/// Splits a paragraph into `(start, end)` ranges that fit in `max_width`.
+fn break_lines(measured: &[Measured], max_width: Option<f32>) -> Vec<(usize, usize)> {
+    let max_width = match max_width {
+        Some(max_width) => max_width,
+        None => return vec![(0, measured.len())],
+    };
+
+    let mut lines = vec![];
+    let mut start = 0;
+    while start < measured.len() {
+        let mut width = 0.0f32;
+        // Last index where the line could end, and where the next one would then start
+        let mut last_break: Option<usize> = None;
+        let mut end = measured.len();
+        for i in start..measured.len() {
+            let m = &measured[i];
+            if i > start && can_break_between(measured[i - 1].c, m.c) {
+                last_break = Some(i);
+            }
+            width += m.advance;
+            if i > start {
+                width += m.kerning;
+            }
+            // Trailing spaces may hang past the edge
+            if width > max_width && !m.c.is_whitespace() && i > start {
+                end = last_break.unwrap_or(i);
+                break;
+            }
+        }
+        lines.push((start, end));
+        start = end;
+        // Don't begin the next line with the spaces it was wrapped at
+        while start < measured.len() && measured[start].c.is_whitespace() {
+            start += 1;
+        }
+    }
+    if lines.is_empty() {
+        lines.push((0, 0));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps `text` as if every character were one unit wide.
+    fn wrap(text: &str, max_width: f32) -> Vec<String> {
+        let measured = text
+            .chars()
+            .map(|c| Measured {
+                c,
+                font: 0,
+                id: GlyphId(0),
+                advance: 1.0f32,
+                kerning: 0.0f32,
+            })
+            .collect::<Vec<_>>();
+        break_lines(&measured, Some(max_width))
+            .into_iter()
+            .map(|(start, end)| measured[start..end].iter().map(|m| m.c).collect())
+            .collect()
+    }
+
+    #[test]
+    fn breaks_between_japanese_characters() {
+        assert!(can_break_between('あ', 'い'));
+        assert!(can_break_between('漢', '字'));
+        assert!(can_break_between('o', '世'));
+        assert!(can_break_between('界', 'h'));
+        assert!(can_break_between(' ', 'w'));
+        assert!(!can_break_between('h', 'w'));
+        assert_eq!(
+            wrap("あいうえおかきくけこ", 5.0f32),
+            vec!["あいうえお", "かきくけこ"]
+        );
+        assert_eq!(wrap("こんにちはworld", 6.0f32), vec!["こんにちは", "world"]);
+    }
+
+    #[test]
+    fn closing_punctuation_stays_on_the_previous_line() {
+        for c in "、。」』）！？ー…".chars() {
+            assert!(!can_break_between('あ', c), "{} may start a line", c);
+        }
+        assert_eq!(
+            wrap("あいうえお。かき", 5.0f32),
+            vec!["あいうえ", "お。かき"]
+        );
+        assert_eq!(
+            wrap("あいうえ」」かき", 5.0f32),
+            vec!["あいう", "え」」かき"]
+        );
+        assert_eq!(wrap("hello, world", 8.0f32), vec!["hello, ", "world"]);
+    }
+
+    #[test]
+    fn small_kana_stay_with_the_previous_character() {
+        for c in "ぁっゃゅょァッャュョヵヶ々ゝ".chars() {
+            assert!(!can_break_between('き', c), "{} may start a line", c);
+        }
+        assert_eq!(wrap("あいうえきゃく", 5.0f32), vec!["あいうえ", "きゃく"]);
+        assert_eq!(
+            wrap("あいうえちょっと", 6.0f32),
+            vec!["あいうえ", "ちょっと"]
+        );
+    }
+
+    #[test]
+    fn opening_brackets_move_to_the_next_line() {
+        for c in "「『（【〈《〔".chars() {
+            assert!(!can_break_between(c, 'あ'), "{} may end a line", c);
+        }
+        assert_eq!(wrap("あいう「えお」", 4.0f32), vec!["あいう", "「えお」"]);
+        assert_eq!(wrap("あい「『えお", 4.0f32), vec!["あい", "「『えお"]);
+    }
+
+    #[test]
+    fn forces_a_break_when_no_break_is_allowed() {
+        assert_eq!(wrap("。。。。。。", 3.0f32), vec!["。。。", "。。。"]);
+        assert_eq!(wrap("abcdefgh", 4.0f32), vec!["abcd", "efgh"]);
+    }
+
+    #[test]
+    fn wraps_latin_text_at_spaces() {
+        assert_eq!(wrap("ab cd ef", 5.0f32), vec!["ab cd ", "ef"]);
+        assert_eq!(wrap("ab  cd", 3.0f32), vec!["ab  ", "cd"]);
+    }
+}
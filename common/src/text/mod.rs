@@ -0,0 +1,141 @@
+pub mod atlas;
+pub mod font;
+pub mod layout;
+
+use crate::render::quad::{QuadList, QuadRenderer, QuadTexture, Rect};
+use crate::text::atlas::{GlyphAtlas, GlyphKey};
+use crate::text::font::Fonts;
+use crate::text::layout::{layout_text, TextLayout, TextStyle};
+
+pub const ATLAS_SIZE: u32 = 1024;
+
+struct QueuedText {
+    layout: TextLayout,
+    x: f32,
+    y: f32,
+    color: [f32; 4],
+}
+
+/// Draws TTF/OTF text in screen space. Glyphs are rasterized into an atlas the first
+/// time they are drawn at a given size.
+///
+/// Queue text during the frame, then `draw` after the scene.
+pub struct TextRenderer {
+    renderer: QuadRenderer,
+    texture: QuadTexture,
+    atlas: GlyphAtlas,
+    fonts: Fonts,
+    queued: Vec<QueuedText>,
+    quads: QuadList,
+}
+
+impl TextRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, fonts: Fonts) -> Self {
+        let renderer = QuadRenderer::new(device, format);
+        let texture = renderer.create_texture(
+            device,
+            "Glyph Atlas",
+            ATLAS_SIZE,
+            ATLAS_SIZE,
+            wgpu::FilterMode::Linear,
+        );
+        TextRenderer {
+            renderer,
+            texture,
+            atlas: GlyphAtlas::new(ATLAS_SIZE, ATLAS_SIZE),
+            fonts,
+            queued: vec![],
+            quads: QuadList::new(),
+        }
+    }
+
+    pub fn fonts(&self) -> &Fonts {
+        &self.fonts
+    }
+
+    /// Measures `text` without drawing it.
+    pub fn layout(&self, text: &str, style: &TextStyle) -> TextLayout {
+        layout_text(&self.fonts.snapshot(), text, style)
+    }
+
+    /// Draws `text` this frame with the top left of its box at `x`, `y` in pixels.
+    pub fn queue(&mut self, text: &str, x: f32, y: f32, style: &TextStyle) {
+        let layout = self.layout(text, style);
+        self.queue_layout(layout, x, y, style.color);
+    }
+
+    pub fn queue_layout(&mut self, layout: TextLayout, x: f32, y: f32, color: [f32; 4]) {
+        self.queued.push(QueuedText {
+            layout,
+            x,
+            y,
+            color,
+        });
+    }
+
+    /// Draws and clears the queued text over `view`.
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        screen_size: (u32, u32),
+    ) {
+        let queued = std::mem::take(&mut self.queued);
+        let fonts = self.fonts.snapshot();
+        // Nothing to draw with until the first font has loaded
+        if fonts.is_empty() {
+            return;
+        }
+        if !self.build_quads(&fonts, &queued) {
+            // Evict everything and keep only this frame's glyphs
+            self.atlas.clear();
+            if !self.build_quads(&fonts, &queued) {
+                log::warn!("Too many glyphs for a {0}x{0} atlas", ATLAS_SIZE);
+            }
+        }
+        if let Some((top, height, texels)) = self.atlas.take_dirty() {
+            self.texture
+                .write(queue, 0, top, self.atlas.width(), height, &texels);
+        }
+        self.renderer.draw(
+            device,
+            queue,
+            view,
+            screen_size,
+            &[(&self.texture, &self.quads)],
+        );
+    }
+
+    /// Returns false if a glyph didn't fit in the atlas; the glyphs after it are left out.
+    fn build_quads(&mut self, fonts: &[rusttype::Font<'static>], queued: &[QueuedText]) -> bool {
+        self.quads.clear();
+        for text in queued {
+            for glyph in &text.layout.glyphs {
+                let key = GlyphKey::new(glyph.font, glyph.id, text.layout.size);
+                let atlas_glyph = match self.atlas.insert(fonts, key) {
+                    Some(atlas_glyph) => atlas_glyph,
+                    None => return false,
+                };
+                if atlas_glyph.width == 0 {
+                    continue;
+                }
+                // Snapped to whole pixels so glyphs stay as sharp as they were rasterized
+                let rect = Rect::new(
+                    (text.x + glyph.x).round() + atlas_glyph.left as f32,
+                    (text.y + glyph.y).round() + atlas_glyph.top as f32,
+                    atlas_glyph.width as f32,
+                    atlas_glyph.height as f32,
+                );
+                let texcoords = self.texture.texcoords(
+                    atlas_glyph.x,
+                    atlas_glyph.y,
+                    atlas_glyph.width,
+                    atlas_glyph.height,
+                );
+                self.quads.push(rect, texcoords, text.color);
+            }
+        }
+        true
+    }
+}
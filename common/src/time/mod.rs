@@ -0,0 +1,192 @@
+use instant::Instant;
+
+/// Longest delta reported by `FrameClock`, so a suspended app or a throttled browser tab
+/// doesn't make the simulation jump when it resumes.
+pub const MAX_FRAME_DELTA: f32 = 0.25f32;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTime {
+    /// Seconds since the previous frame.
+    pub delta: f32,
+    /// Seconds since the first frame.
+    pub elapsed: f32,
+    pub frame: u64,
+}
+
+/// Measures frame timings. Call `tick` once at the start of every `Scene::render`;
+/// `GameSceneHost` does this for game scenes.
+pub struct FrameClock {
+    last: Option<Instant>,
+    time: FrameTime,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        FrameClock {
+            last: None,
+            time: FrameTime::default(),
+        }
+    }
+
+    pub fn tick(&mut self) -> FrameTime {
+        let delta = self.measure();
+        self.advance(delta)
+    }
+
+    /// Returns the wall-clock time since the previous call without advancing the clock.
+    pub fn measure(&mut self) -> f32 {
+        let now = Instant::now();
+        let delta = match self.last {
+            Some(last) => now.duration_since(last).as_secs_f32().min(MAX_FRAME_DELTA),
+            None => 0.0f32,
+        };
+        self.last = Some(now);
+        delta
+    }
+
+    /// Advances the clock by a given delta instead of the measured one, e.g. when recording or replaying.
+    pub fn advance(&mut self, delta: f32) -> FrameTime {
+        self.time = FrameTime {
+            delta,
+            elapsed: self.time.elapsed + delta,
+            frame: self.time.frame + 1,
+        };
+        self.time
+    }
+
+    pub fn time(&self) -> FrameTime {
+        self.time
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        FrameClock::new()
+    }
+}
+
+/// Splits variable frame deltas into fixed simulation steps.
+///
+/// ```ignore
+/// let steps = self.timestep.advance(time.delta);
+/// for _ in 0..steps {
+///     self.simulation.step(self.timestep.step());
+/// }
+/// let alpha = self.timestep.alpha(); // interpolate rendering between the last two steps
+/// ```
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    /// Panics unless `step` is a positive, finite number of seconds.
+    pub fn new(step: f32) -> Self {
+        assert!(
+            step.is_finite() && step > 0.0f32,
+            "FixedTimestep step must be positive, got {}",
+            step
+        );
+        FixedTimestep {
+            step,
+            accumulator: 0.0f32,
+            max_steps: 8,
+        }
+    }
+
+    /// Panics if `max_steps` is 0, which would never let the simulation catch up.
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        assert!(max_steps > 0, "FixedTimestep max_steps must be at least 1");
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds `delta` seconds and returns how many fixed steps should run this frame.
+    ///
+    /// At most `max_steps` are returned; the remaining time is dropped so a slow device doesn't spiral.
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta;
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+            if steps == self.max_steps {
+                self.accumulator = self.accumulator.min(self.step);
+                break;
+            }
+        }
+        steps
+    }
+
+    /// Progress towards the next step in `0.0..1.0`.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.0f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_accumulates_elapsed_time_and_frames() {
+        let mut clock = FrameClock::new();
+        clock.advance(0.5f32);
+        let time = clock.advance(0.25f32);
+        assert_eq!(
+            time,
+            FrameTime {
+                delta: 0.25f32,
+                elapsed: 0.75f32,
+                frame: 2,
+            }
+        );
+        assert_eq!(clock.time(), time);
+    }
+
+    #[test]
+    fn first_measure_is_zero() {
+        assert_eq!(FrameClock::new().measure(), 0.0f32);
+    }
+
+    #[test]
+    fn fixed_timestep_carries_the_remainder() {
+        let mut timestep = FixedTimestep::new(0.25f32);
+        assert_eq!(timestep.advance(0.125f32), 0);
+        assert_eq!(timestep.alpha(), 0.5f32);
+        assert_eq!(timestep.advance(0.5f32), 2);
+        assert_eq!(timestep.alpha(), 0.5f32);
+    }
+
+    #[test]
+    fn fixed_timestep_drops_time_beyond_max_steps() {
+        let mut timestep = FixedTimestep::new(0.25f32).with_max_steps(2);
+        assert_eq!(timestep.advance(10.0f32), 2);
+        assert_eq!(timestep.alpha(), 1.0f32);
+        assert_eq!(timestep.advance(0.0f32), 1);
+        assert_eq!(timestep.advance(0.0f32), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_timestep_rejects_zero_step() {
+        FixedTimestep::new(0.0f32);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_timestep_rejects_negative_step() {
+        FixedTimestep::new(-0.1f32);
+    }
+
+    #[test]
+    #[should_panic]
+    fn fixed_timestep_rejects_zero_max_steps() {
+        FixedTimestep::new(0.25f32).with_max_steps(0);
+    }
+}
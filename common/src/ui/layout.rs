@@ -0,0 +1,236 @@
+use crate::render::quad::Rect;
+use crate::text::font::Fonts;
+use crate::text::layout::{layout_text, TextStyle};
+
+/// How big a widget is along one axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Size {
+    /// As big as its contents.
+    Fit,
+    /// In logical pixels, scaled like the theme.
+    Fixed(f32),
+    /// Shares the space left in its row or column with other `Fill` siblings, in proportion to
+    /// the weight. Across a row or column, and for anchored widgets, takes the whole parent.
+    Fill(f32),
+}
+
+impl Default for Size {
+    fn default() -> Self {
+        Size::Fit
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Row,
+    Column,
+}
+
+/// Where children go along a row or column when they don't fill it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+/// Where children go across a row or column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CrossAlign {
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+/// A point of the parent's content box that an anchored widget sticks to, with the same
+/// point of the widget.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Fractions of the width and height, from the top left.
+    pub fn factors(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (0.0f32, 0.0f32),
+            Anchor::Top => (0.5f32, 0.0f32),
+            Anchor::TopRight => (1.0f32, 0.0f32),
+            Anchor::Left => (0.0f32, 0.5f32),
+            Anchor::Center => (0.5f32, 0.5f32),
+            Anchor::Right => (1.0f32, 0.5f32),
+            Anchor::BottomLeft => (0.0f32, 1.0f32),
+            Anchor::Bottom => (0.5f32, 1.0f32),
+            Anchor::BottomRight => (1.0f32, 1.0f32),
+        }
+    }
+
+    /// Places a `width` by `height` box in `parent`, then moves it by `offset`.
+    pub fn place(self, parent: Rect, width: f32, height: f32, offset: [f32; 2]) -> Rect {
+        let (fx, fy) = self.factors();
+        Rect::new(
+            parent.x + (parent.width - width) * fx + offset[0],
+            parent.y + (parent.height - height) * fy + offset[1],
+            width,
+            height,
+        )
+    }
+}
+
+/// Measures single-line text for layout, so layout doesn't need a GPU or even fonts.
+pub trait TextMeasure {
+    /// Width and height in pixels of `text` at `size`.
+    fn measure(&self, text: &str, size: f32) -> (f32, f32);
+}
+
+impl TextMeasure for Fonts {
+    fn measure(&self, text: &str, size: f32) -> (f32, f32) {
+        let style = TextStyle {
+            size,
+            ..TextStyle::default()
+        };
+        let layout = layout_text(&self.snapshot(), text, &style);
+        (layout.width, layout.height)
+    }
+}
+
+/// Every character is `advance` em wide and lines are `line_height` em tall. Stands in for
+/// real fonts in tools and tests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedWidthMeasure {
+    pub advance: f32,
+    pub line_height: f32,
+}
+
+impl Default for FixedWidthMeasure {
+    fn default() -> Self {
+        FixedWidthMeasure {
+            advance: 0.5f32,
+            line_height: 1.2f32,
+        }
+    }
+}
+
+impl TextMeasure for FixedWidthMeasure {
+    fn measure(&self, text: &str, size: f32) -> (f32, f32) {
+        (
+            text.chars().count() as f32 * self.advance * size,
+            self.line_height * size,
+        )
+    }
+}
+
+/// Splits `available` main-axis pixels between children, given each one's size and its
+/// preferred extent. Returns each child's extent and the space left over.
+pub fn distribute(sizes: &[(Size, f32)], available: f32) -> (Vec<f32>, f32) {
+    let fixed: f32 = sizes
+        .iter()
+        .map(|(size, preferred)| match size {
+            Size::Fit => *preferred,
+            Size::Fixed(extent) => *extent,
+            Size::Fill(_) => 0.0f32,
+        })
+        .sum();
+    let weights: f32 = sizes
+        .iter()
+        .map(|(size, _)| match size {
+            Size::Fill(weight) => weight.max(0.0f32),
+            _ => 0.0f32,
+        })
+        .sum();
+    let remaining = (available - fixed).max(0.0f32);
+    let extents = sizes
+        .iter()
+        .map(|(size, preferred)| match size {
+            Size::Fit => *preferred,
+            Size::Fixed(extent) => *extent,
+            Size::Fill(weight) if weights > 0.0f32 => remaining * weight.max(0.0f32) / weights,
+            Size::Fill(_) => 0.0f32,
+        })
+        .collect();
+    let left_over = if weights > 0.0f32 { 0.0f32 } else { remaining };
+    (extents, left_over)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribute_gives_fill_widgets_the_space_left() {
+        let sizes = [
+            (Size::Fit, 30.0f32),
+            (Size::Fixed(50.0f32), 10.0f32),
+            (Size::Fill(1.0f32), 99.0f32),
+            (Size::Fill(3.0f32), 0.0f32),
+        ];
+        let (extents, left_over) = distribute(&sizes, 200.0f32);
+        assert_eq!(extents, vec![30.0f32, 50.0f32, 30.0f32, 90.0f32]);
+        assert_eq!(left_over, 0.0f32);
+    }
+
+    #[test]
+    fn distribute_leaves_space_without_fill_widgets() {
+        let sizes = [(Size::Fit, 30.0f32), (Size::Fixed(50.0f32), 10.0f32)];
+        assert_eq!(
+            distribute(&sizes, 200.0f32),
+            (vec![30.0f32, 50.0f32], 120.0f32)
+        );
+        // Zero weights take nothing and leave nothing over
+        let sizes = [(Size::Fit, 30.0f32), (Size::Fill(0.0f32), 10.0f32)];
+        assert_eq!(
+            distribute(&sizes, 200.0f32),
+            (vec![30.0f32, 0.0f32], 170.0f32)
+        );
+    }
+
+    #[test]
+    fn distribute_never_gives_negative_space() {
+        let sizes = [
+            (Size::Fixed(150.0f32), 0.0f32),
+            (Size::Fill(1.0f32), 0.0f32),
+        ];
+        assert_eq!(
+            distribute(&sizes, 100.0f32),
+            (vec![150.0f32, 0.0f32], 0.0f32)
+        );
+        let sizes = [(Size::Fit, 150.0f32)];
+        assert_eq!(distribute(&sizes, 100.0f32), (vec![150.0f32], 0.0f32));
+    }
+
+    #[test]
+    fn anchors_place_boxes_at_the_same_point_of_the_parent() {
+        let parent = Rect::new(100.0f32, 50.0f32, 400.0f32, 200.0f32);
+        let place = |anchor: Anchor, offset| anchor.place(parent, 40.0f32, 20.0f32, offset);
+        assert_eq!(
+            place(Anchor::TopLeft, [0.0f32, 0.0f32]),
+            Rect::new(100.0f32, 50.0f32, 40.0f32, 20.0f32)
+        );
+        assert_eq!(
+            place(Anchor::Center, [0.0f32, 0.0f32]),
+            Rect::new(280.0f32, 140.0f32, 40.0f32, 20.0f32)
+        );
+        assert_eq!(
+            place(Anchor::BottomRight, [-10.0f32, -5.0f32]),
+            Rect::new(450.0f32, 225.0f32, 40.0f32, 20.0f32)
+        );
+        assert_eq!(
+            place(Anchor::Top, [0.0f32, 8.0f32]),
+            Rect::new(280.0f32, 58.0f32, 40.0f32, 20.0f32)
+        );
+        assert_eq!(
+            place(Anchor::Left, [4.0f32, 0.0f32]),
+            Rect::new(104.0f32, 140.0f32, 40.0f32, 20.0f32)
+        );
+    }
+}
@@ -0,0 +1,1119 @@
+pub mod layout;
+pub mod render;
+pub mod theme;
+pub mod widget;
+
+use crate::render::quad::Rect;
+use crate::ui::layout::{distribute, CrossAlign, Direction, Justify, Size, TextMeasure};
+use crate::ui::theme::{load_theme, Theme};
+use crate::ui::widget::{Widget, WidgetKind};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, Touch, TouchPhase, WindowEvent};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WidgetId(usize);
+
+/// What the player did, collected by `Ui::handle_event` until `drain_events`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiEvent {
+    Clicked(WidgetId),
+    ValueChanged { id: WidgetId, value: f32 },
+    Selected { id: WidgetId, index: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Press {
+    Button,
+    Slider,
+    /// Dragging a list scrolls it; a press that didn't move selects a row on release.
+    List {
+        start_y: f32,
+        start_scroll: f32,
+        dragged: bool,
+    },
+}
+
+struct Node {
+    widget: Widget,
+    parent: Option<WidgetId>,
+    children: Vec<WidgetId>,
+    rect: Rect,
+    /// Size of the contents, from the last `layout`.
+    preferred: (f32, f32),
+}
+
+/// A retained widget tree for menus and HUDs.
+///
+/// Build it once, route `WindowEvent`s from `Scene::update` to `handle_event`, then every frame
+/// call `layout`, react to `drain_events` and draw it with a `UiRenderer`. Layout and hit
+/// testing only need a `TextMeasure`, not a GPU.
+pub struct Ui {
+    /// As loaded, in logical pixels.
+    base_theme: Theme,
+    /// `base_theme` scaled to physical pixels.
+    theme: Theme,
+    scale_factor: f32,
+    // Filled by `load_theme` and picked up by `layout`
+    loaded_theme: Arc<Mutex<Option<Theme>>>,
+    nodes: Vec<Option<Node>>,
+    root: WidgetId,
+    pointer: Option<(f32, f32)>,
+    touch_id: Option<u64>,
+    hovered: Option<WidgetId>,
+    pressed: Option<(WidgetId, Press)>,
+    /// Set while a press that landed on the UI is held, so the game doesn't see its release.
+    captured: bool,
+    events: Vec<UiEvent>,
+}
+
+impl Ui {
+    pub fn new(theme: Theme) -> Self {
+        let root = Node {
+            widget: Widget::column()
+                .with_width(Size::Fill(1.0f32))
+                .with_height(Size::Fill(1.0f32)),
+            parent: None,
+            children: vec![],
+            rect: Rect::default(),
+            preferred: (0.0f32, 0.0f32),
+        };
+        Ui {
+            base_theme: theme.clone(),
+            theme,
+            scale_factor: 1.0f32,
+            loaded_theme: Arc::new(Mutex::new(None)),
+            nodes: vec![Some(root)],
+            root: WidgetId(0),
+            pointer: None,
+            touch_id: None,
+            hovered: None,
+            pressed: None,
+            captured: false,
+            events: vec![],
+        }
+    }
+
+    /// The theme in use, scaled by the window's scale factor.
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// `theme` is in logical pixels; it's scaled by the window's scale factor.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme.scaled(self.scale_factor);
+        self.base_theme = theme;
+    }
+
+    /// Reads a theme through `tearchan::fs`; it replaces the current one at the next `layout`.
+    /// Spawn the returned future, e.g. with `context.spawner().spawn_local`.
+    pub fn load_theme<P: AsRef<Path>>(&self, path: P) -> impl Future<Output = ()> {
+        let loaded = self.loaded_theme.clone();
+        let path = path.as_ref().to_path_buf();
+        async move {
+            match load_theme(path).await {
+                Ok(theme) => *loaded.lock().unwrap() = Some(theme),
+                Err(e) => log::error!("{}", e),
+            }
+        }
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Scales the theme for a window with `scale_factor` physical pixels per logical pixel.
+    /// `handle_event` calls this when the window moves to another monitor.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+        self.theme = self.base_theme.scaled(scale_factor);
+    }
+
+    /// A transparent column covering the screen.
+    pub fn root(&self) -> WidgetId {
+        self.root
+    }
+
+    /// Adds `widget` as the last child of `parent`, which must be a panel.
+    pub fn add(&mut self, parent: WidgetId, widget: Widget) -> WidgetId {
+        match self.widget(parent).map(|parent| &parent.kind) {
+            Some(WidgetKind::Panel(_)) => {}
+            _ => panic!("UI widgets can only be added to panels, not {:?}", parent),
+        }
+        let id = WidgetId(self.nodes.len());
+        self.nodes.push(Some(Node {
+            widget,
+            parent: Some(parent),
+            children: vec![],
+            rect: Rect::default(),
+            preferred: (0.0f32, 0.0f32),
+        }));
+        self.node_mut(parent)
+            .expect("Missing parent")
+            .children
+            .push(id);
+        id
+    }
+
+    /// Removes a widget and its children. The root can't be removed.
+    pub fn remove(&mut self, id: WidgetId) {
+        if id == self.root {
+            return;
+        }
+        let parent = match self.node(id) {
+            Some(node) => node.parent,
+            None => return,
+        };
+        if let Some(parent) = parent.and_then(|parent| self.node_mut(parent)) {
+            parent.children.retain(|child| *child != id);
+        }
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                stack.extend(node.children);
+            }
+            if self.hovered == Some(id) {
+                self.hovered = None;
+            }
+            if self.pressed.map(|(pressed, _)| pressed) == Some(id) {
+                self.pressed = None;
+            }
+        }
+    }
+
+    pub fn widget(&self, id: WidgetId) -> Option<&Widget> {
+        self.node(id).map(|node| &node.widget)
+    }
+
+    pub fn widget_mut(&mut self, id: WidgetId) -> Option<&mut Widget> {
+        self.node_mut(id).map(|node| &mut node.widget)
+    }
+
+    pub fn children(&self, id: WidgetId) -> &[WidgetId] {
+        self.node(id).map_or(&[], |node| &node.children)
+    }
+
+    /// Where the widget was placed by the last `layout`.
+    pub fn rect(&self, id: WidgetId) -> Option<Rect> {
+        self.node(id).map(|node| node.rect)
+    }
+
+    /// Replaces the text of a label or button.
+    pub fn set_text(&mut self, id: WidgetId, text: &str) {
+        match self.widget_mut(id).map(|widget| &mut widget.kind) {
+            Some(WidgetKind::Label(current)) | Some(WidgetKind::Button(current)) => {
+                *current = text.to_string();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        if let Some(widget) = self.widget_mut(id) {
+            widget.visible = visible;
+        }
+        if !visible {
+            self.release_hidden();
+        }
+    }
+
+    pub fn is_visible(&self, id: WidgetId) -> bool {
+        self.widget(id).map_or(false, |widget| widget.visible)
+    }
+
+    pub fn value(&self, id: WidgetId) -> Option<f32> {
+        match self.widget(id).map(|widget| &widget.kind) {
+            Some(WidgetKind::Slider(slider)) => Some(slider.value),
+            _ => None,
+        }
+    }
+
+    pub fn selected(&self, id: WidgetId) -> Option<usize> {
+        match self.widget(id).map(|widget| &widget.kind) {
+            Some(WidgetKind::List(list)) => list.selected,
+            _ => None,
+        }
+    }
+
+    /// Last position of the mouse, or of the touch being tracked.
+    pub fn pointer(&self) -> Option<(f32, f32)> {
+        self.pointer
+    }
+
+    pub fn is_hovered(&self, id: WidgetId) -> bool {
+        self.hovered == Some(id)
+    }
+
+    pub fn is_pressed(&self, id: WidgetId) -> bool {
+        self.pressed.map(|(pressed, _)| pressed) == Some(id)
+    }
+
+    pub fn drain_events(&mut self) -> Vec<UiEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Visible widgets in drawing order, parents before children.
+    pub fn visible_widgets(&self) -> Vec<(WidgetId, &Widget, Rect)> {
+        let mut widgets = vec![];
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let node = match self.node(id) {
+                Some(node) if node.widget.visible => node,
+                _ => continue,
+            };
+            widgets.push((id, &node.widget, node.rect));
+            stack.extend(node.children.iter().rev());
+        }
+        widgets
+    }
+
+    /// The topmost widget under a point. Panels without a background are see-through.
+    pub fn hit_test(&self, x: f32, y: f32) -> Option<WidgetId> {
+        self.visible_widgets()
+            .into_iter()
+            .rev()
+            .find(|(_, widget, rect)| {
+                let solid = match &widget.kind {
+                    WidgetKind::Panel(panel) => panel.background,
+                    _ => true,
+                };
+                solid && rect.contains(x, y)
+            })
+            .map(|(id, _, _)| id)
+    }
+
+    /// Sizes and places every widget in a `width` by `height` screen.
+    pub fn layout(&mut self, width: f32, height: f32, measure: &dyn TextMeasure) {
+        let loaded = self.loaded_theme.lock().unwrap().take();
+        if let Some(theme) = loaded {
+            self.set_theme(theme);
+        }
+        self.measure(self.root, measure);
+        self.arrange(self.root, Rect::new(0.0f32, 0.0f32, width, height));
+        // Widgets may have moved under a still pointer
+        self.hovered = self.pointer.and_then(|(x, y)| self.hit_test(x, y));
+    }
+
+    fn measure(&mut self, id: WidgetId, measure: &dyn TextMeasure) -> (f32, f32) {
+        // Sizes of the children that flow in this panel's row or column
+        let mut child_sizes = vec![];
+        for child in self.children(id).to_vec() {
+            let preferred = self.measure(child, measure);
+            let widget = &self.node(child).expect("Missing child").widget;
+            if widget.visible && widget.anchor.is_none() {
+                child_sizes.push(outer_size(widget, preferred, self.scale_factor));
+            }
+        }
+
+        let theme = &self.theme;
+        let node = self.node(id).expect("Missing widget");
+        let preferred =
+            match &node.widget.kind {
+                WidgetKind::Label(text) => measure.measure(text, theme.font_size),
+                WidgetKind::Button(text) => {
+                    let (width, height) = measure.measure(text, theme.font_size);
+                    (
+                        (width + theme.button.padding[0] * 2.0f32).max(theme.button.min_width),
+                        height + theme.button.padding[1] * 2.0f32,
+                    )
+                }
+                WidgetKind::Slider(_) => (theme.slider.width, theme.slider.height),
+                WidgetKind::List(list) => {
+                    let widest = list
+                        .items
+                        .iter()
+                        .map(|item| measure.measure(item, theme.font_size).0)
+                        .fold(0.0f32, f32::max);
+                    (
+                        (widest + theme.list.padding * 2.0f32).max(theme.list.min_width),
+                        theme.list.row_height * list.visible_rows as f32,
+                    )
+                }
+                WidgetKind::Panel(panel) => {
+                    let gaps = theme.panel.gap * child_sizes.len().saturating_sub(1) as f32;
+                    let (main, cross) = child_sizes.iter().fold(
+                        (gaps, 0.0f32),
+                        |(main, cross), (width, height)| match panel.direction {
+                            Direction::Row => (main + width, cross.max(*height)),
+                            Direction::Column => (main + height, cross.max(*width)),
+                        },
+                    );
+                    let padding = self.padding(id) * 2.0f32;
+                    match panel.direction {
+                        Direction::Row => (main + padding, cross + padding),
+                        Direction::Column => (cross + padding, main + padding),
+                    }
+                }
+            };
+        self.node_mut(id).expect("Missing widget").preferred = preferred;
+        preferred
+    }
+
+    fn arrange(&mut self, id: WidgetId, rect: Rect) {
+        let padding = self.padding(id);
+        let scale = self.scale_factor;
+        let node = self.node_mut(id).expect("Missing widget");
+        node.rect = rect;
+        let panel = match &node.widget.kind {
+            WidgetKind::Panel(panel) => panel.clone(),
+            _ => return,
+        };
+        let content = Rect::new(
+            rect.x + padding,
+            rect.y + padding,
+            (rect.width - padding * 2.0f32).max(0.0f32),
+            (rect.height - padding * 2.0f32).max(0.0f32),
+        );
+
+        let mut flowing = vec![];
+        let mut anchored = vec![];
+        for child in self.children(id).to_vec() {
+            let node = self.node(child).expect("Missing child");
+            if !node.widget.visible {
+                continue;
+            }
+            match node.widget.anchor {
+                Some((anchor, offset)) => {
+                    let width = extent(
+                        scale_size(node.widget.width, scale),
+                        node.preferred.0,
+                        content.width,
+                    );
+                    let height = extent(
+                        scale_size(node.widget.height, scale),
+                        node.preferred.1,
+                        content.height,
+                    );
+                    let offset = [offset[0] * scale, offset[1] * scale];
+                    anchored.push((child, anchor.place(content, width, height, offset)));
+                }
+                None => flowing.push(child),
+            }
+        }
+
+        let (main_available, cross_available) = match panel.direction {
+            Direction::Row => (content.width, content.height),
+            Direction::Column => (content.height, content.width),
+        };
+        let gap = self.theme.panel.gap;
+        let gaps = gap * flowing.len().saturating_sub(1) as f32;
+        let sizes = flowing
+            .iter()
+            .map(|child| {
+                let node = self.node(*child).expect("Missing child");
+                match panel.direction {
+                    Direction::Row => (scale_size(node.widget.width, scale), node.preferred.0),
+                    Direction::Column => (scale_size(node.widget.height, scale), node.preferred.1),
+                }
+            })
+            .collect::<Vec<_>>();
+        let (extents, left_over) = distribute(&sizes, main_available - gaps);
+        let (mut position, spacing) = match panel.justify {
+            Justify::Start => (0.0f32, gap),
+            Justify::Center => (left_over * 0.5f32, gap),
+            Justify::End => (left_over, gap),
+            Justify::SpaceBetween if flowing.len() > 1 => {
+                (0.0f32, gap + left_over / (flowing.len() - 1) as f32)
+            }
+            Justify::SpaceBetween => (0.0f32, gap),
+        };
+
+        let mut placed = vec![];
+        for (child, main) in flowing.iter().zip(extents) {
+            let node = self.node(*child).expect("Missing child");
+            let (cross_size, cross_preferred) = match panel.direction {
+                Direction::Row => (scale_size(node.widget.height, scale), node.preferred.1),
+                Direction::Column => (scale_size(node.widget.width, scale), node.preferred.0),
+            };
+            let cross = match (cross_size, panel.align) {
+                (Size::Fit, CrossAlign::Stretch) => cross_available,
+                (size, _) => extent(size, cross_preferred, cross_available),
+            };
+            let cross_position = match panel.align {
+                CrossAlign::Start | CrossAlign::Stretch => 0.0f32,
+                CrossAlign::Center => (cross_available - cross) * 0.5f32,
+                CrossAlign::End => cross_available - cross,
+            };
+            let child_rect = match panel.direction {
+                Direction::Row => Rect::new(
+                    content.x + position,
+                    content.y + cross_position,
+                    main,
+                    cross,
+                ),
+                Direction::Column => Rect::new(
+                    content.x + cross_position,
+                    content.y + position,
+                    cross,
+                    main,
+                ),
+            };
+            placed.push((*child, child_rect));
+            position += main + spacing;
+        }
+
+        for (child, child_rect) in placed.into_iter().chain(anchored) {
+            self.arrange(child, child_rect);
+        }
+    }
+
+    /// Only panels with a background are padded, so panels that just group widgets don't
+    /// push them apart.
+    fn padding(&self, id: WidgetId) -> f32 {
+        match self.widget(id).map(|widget| &widget.kind) {
+            Some(WidgetKind::Panel(panel)) if panel.background => self.theme.panel.padding,
+            _ => 0.0f32,
+        }
+    }
+
+    /// Returns true if the UI used the event, in which case the scene should ignore it.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.set_scale_factor(*scale_factor as f32);
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.move_pointer(position.x as f32, position.y as f32)
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if self.pressed.is_none() {
+                    self.pointer = None;
+                    self.hovered = None;
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => self.press_pointer(),
+                ElementState::Released => self.release_pointer(true),
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let row_height = self.theme.list.row_height;
+                let rows = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / row_height,
+                };
+                match self.hovered.and_then(|id| self.widget_mut(id)) {
+                    Some(Widget {
+                        kind: WidgetKind::List(list),
+                        enabled: true,
+                        ..
+                    }) => {
+                        let scroll = list.scroll - rows;
+                        list.scroll_to(scroll);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            WindowEvent::Touch(Touch {
+                phase,
+                location,
+                id,
+                ..
+            }) => {
+                let is_tracked = self.touch_id == Some(*id);
+                let (x, y) = (location.x as f32, location.y as f32);
+                match phase {
+                    TouchPhase::Started if self.touch_id.is_none() => {
+                        self.move_pointer(x, y);
+                        let captured = self.press_pointer();
+                        if captured {
+                            self.touch_id = Some(*id);
+                        } else {
+                            self.pointer = None;
+                            self.hovered = None;
+                        }
+                        captured
+                    }
+                    TouchPhase::Moved if is_tracked => self.move_pointer(x, y),
+                    TouchPhase::Ended | TouchPhase::Cancelled if is_tracked => {
+                        self.pointer = Some((x, y));
+                        self.release_pointer(*phase == TouchPhase::Ended);
+                        self.touch_id = None;
+                        self.pointer = None;
+                        self.hovered = None;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn move_pointer(&mut self, x: f32, y: f32) -> bool {
+        self.pointer = Some((x, y));
+        self.hovered = self.hit_test(x, y);
+        let (id, press) = match self.pressed {
+            Some(pressed) => pressed,
+            None => return self.captured,
+        };
+        match press {
+            Press::Slider => self.drag_slider(id, x),
+            Press::List {
+                start_y,
+                start_scroll,
+                dragged,
+            } => {
+                let row_height = self.theme.list.row_height;
+                let dragged = dragged || (y - start_y).abs() > row_height * 0.5f32;
+                if dragged {
+                    if let Some(WidgetKind::List(list)) = self.kind_mut(id) {
+                        list.scroll_to(start_scroll - (y - start_y) / row_height);
+                    }
+                    self.pressed = Some((
+                        id,
+                        Press::List {
+                            start_y,
+                            start_scroll,
+                            dragged,
+                        },
+                    ));
+                }
+            }
+            Press::Button => {}
+        }
+        true
+    }
+
+    fn press_pointer(&mut self) -> bool {
+        let (x, y) = match self.pointer {
+            Some(pointer) => pointer,
+            None => return false,
+        };
+        let id = match self.hit_test(x, y) {
+            Some(id) => id,
+            None => return false,
+        };
+        self.captured = true;
+        self.hovered = Some(id);
+        let widget = self.widget(id).expect("Missing widget");
+        if !widget.is_interactive() {
+            return true;
+        }
+        let press = match &widget.kind {
+            WidgetKind::Button(_) => Press::Button,
+            WidgetKind::Slider(_) => Press::Slider,
+            WidgetKind::List(list) => Press::List {
+                start_y: y,
+                start_scroll: list.scroll,
+                dragged: false,
+            },
+            WidgetKind::Panel(_) | WidgetKind::Label(_) => return true,
+        };
+        self.pressed = Some((id, press));
+        if press == Press::Slider {
+            self.drag_slider(id, x);
+        }
+        true
+    }
+
+    /// `completed` is false when the press was cancelled, e.g. by the system taking the touch.
+    fn release_pointer(&mut self, completed: bool) -> bool {
+        let captured = self.captured;
+        self.captured = false;
+        let (id, press) = match self.pressed.take() {
+            Some(pressed) => pressed,
+            None => return captured,
+        };
+        let over = match self.pointer {
+            Some((x, y)) => self.hit_test(x, y) == Some(id),
+            None => false,
+        };
+        if !completed || !over {
+            return captured;
+        }
+        match press {
+            Press::Button => self.events.push(UiEvent::Clicked(id)),
+            Press::List { dragged: false, .. } => {
+                let row = self.list_row_at(id, self.pointer.map_or(0.0f32, |(_, y)| y));
+                if let Some(index) = row {
+                    if let Some(WidgetKind::List(list)) = self.kind_mut(id) {
+                        list.selected = Some(index);
+                    }
+                    self.events.push(UiEvent::Selected { id, index });
+                }
+            }
+            Press::Slider | Press::List { .. } => {}
+        }
+        captured
+    }
+
+    fn drag_slider(&mut self, id: WidgetId, x: f32) {
+        let rect = match self.rect(id) {
+            Some(rect) => rect,
+            None => return,
+        };
+        let thumb = self.theme.slider.thumb_width;
+        let track = (rect.width - thumb).max(1.0f32);
+        let fraction = (x - rect.x - thumb * 0.5f32) / track;
+        let changed = match self.kind_mut(id) {
+            Some(WidgetKind::Slider(slider)) => {
+                let previous = slider.value;
+                slider.set_fraction(fraction);
+                Some(slider.value).filter(|value| (*value - previous).abs() > std::f32::EPSILON)
+            }
+            _ => None,
+        };
+        if let Some(value) = changed {
+            self.events.push(UiEvent::ValueChanged { id, value });
+        }
+    }
+
+    /// Index of the list item drawn at `y`, if any.
+    pub fn list_row_at(&self, id: WidgetId, y: f32) -> Option<usize> {
+        let node = self.node(id)?;
+        let list = match &node.widget.kind {
+            WidgetKind::List(list) => list,
+            _ => return None,
+        };
+        if y < node.rect.y || y >= node.rect.bottom() {
+            return None;
+        }
+        let row = ((y - node.rect.y) / self.theme.list.row_height).floor() as usize;
+        let index = row + list.scroll.round() as usize;
+        Some(index).filter(|index| *index < list.items.len())
+    }
+
+    /// Clears the hover and press of widgets that were hidden.
+    fn release_hidden(&mut self) {
+        let visible = self
+            .visible_widgets()
+            .into_iter()
+            .map(|(id, _, _)| id)
+            .collect::<Vec<_>>();
+        if self.hovered.map_or(false, |id| !visible.contains(&id)) {
+            self.hovered = None;
+        }
+        if let Some((id, _)) = self.pressed {
+            if !visible.contains(&id) {
+                self.pressed = None;
+            }
+        }
+    }
+
+    fn kind_mut(&mut self, id: WidgetId) -> Option<&mut WidgetKind> {
+        self.widget_mut(id).map(|widget| &mut widget.kind)
+    }
+
+    fn node(&self, id: WidgetId) -> Option<&Node> {
+        self.nodes.get(id.0).and_then(|node| node.as_ref())
+    }
+
+    fn node_mut(&mut self, id: WidgetId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.0).and_then(|node| node.as_mut())
+    }
+}
+
+/// The size a widget asks for in its parent's row or column.
+fn outer_size(widget: &Widget, preferred: (f32, f32), scale: f32) -> (f32, f32) {
+    let pick = |size: Size, preferred: f32| match size {
+        Size::Fixed(extent) => extent * scale,
+        Size::Fit | Size::Fill(_) => preferred,
+    };
+    (
+        pick(widget.width, preferred.0),
+        pick(widget.height, preferred.1),
+    )
+}
+
+/// `Fixed` sizes are in logical pixels, like the theme.
+fn scale_size(size: Size, scale: f32) -> Size {
+    match size {
+        Size::Fixed(extent) => Size::Fixed(extent * scale),
+        size => size,
+    }
+}
+
+/// Extent of a widget along an axis where `Fill` takes all of `available`.
+fn extent(size: Size, preferred: f32, available: f32) -> f32 {
+    match size {
+        Size::Fit => preferred,
+        Size::Fixed(extent) => extent,
+        Size::Fill(_) => available,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::layout::{Anchor, FixedWidthMeasure};
+    use winit::dpi::{PhysicalPosition, PhysicalSize};
+    use winit::event::{DeviceId, ModifiersState};
+
+    const SCREEN: (f32, f32) = (800.0f32, 600.0f32);
+
+    #[allow(deprecated)]
+    fn moved(x: f32, y: f32) -> WindowEvent<'static> {
+        WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: PhysicalPosition::new(x as f64, y as f64),
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    #[allow(deprecated)]
+    fn mouse(state: ElementState) -> WindowEvent<'static> {
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    fn layout(ui: &mut Ui) {
+        ui.layout(SCREEN.0, SCREEN.1, &FixedWidthMeasure::default());
+    }
+
+    fn center(ui: &Ui, id: WidgetId) -> (f32, f32) {
+        let rect = ui.rect(id).unwrap();
+        (rect.x + rect.width * 0.5f32, rect.y + rect.height * 0.5f32)
+    }
+
+    /// Presses at `from`, drags through `to` and releases there.
+    fn drag(ui: &mut Ui, from: (f32, f32), to: &[(f32, f32)]) {
+        ui.handle_event(&moved(from.0, from.1));
+        ui.handle_event(&mouse(ElementState::Pressed));
+        for (x, y) in to {
+            ui.handle_event(&moved(*x, *y));
+        }
+        ui.handle_event(&mouse(ElementState::Released));
+    }
+
+    fn click(ui: &mut Ui, at: (f32, f32)) {
+        drag(ui, at, &[]);
+    }
+
+    /// A row `width` wide at the top left, holding `count` 50 pixel wide labels.
+    fn row_of_labels(ui: &mut Ui, row: Widget, width: f32, count: usize) -> Vec<WidgetId> {
+        let row = ui.add(
+            ui.root(),
+            row.with_width(Size::Fixed(width))
+                .anchored(Anchor::TopLeft, [0.0f32, 0.0f32]),
+        );
+        (0..count)
+            .map(|_| ui.add(row, Widget::label("x").with_width(Size::Fixed(50.0f32))))
+            .collect()
+    }
+
+    #[test]
+    fn anchored_widgets_stick_to_their_parent() {
+        let theme = Theme::default();
+        let mut ui = Ui::new(theme.clone());
+        let root = ui.root();
+        let hint = ui.add(
+            root,
+            Widget::label("abcd").anchored(Anchor::BottomRight, [-10.0f32, -20.0f32]),
+        );
+        let panel = ui.add(
+            root,
+            Widget::column()
+                .with_background()
+                .anchored(Anchor::Center, [0.0f32, 0.0f32]),
+        );
+        let button = ui.add(panel, Widget::button("Resume"));
+        layout(&mut ui);
+
+        let text_height = 1.2f32 * theme.font_size;
+        let hint_rect = ui.rect(hint).unwrap();
+        assert_eq!(hint_rect.width, 4.0f32 * 0.5f32 * theme.font_size);
+        assert_eq!(hint_rect.right(), SCREEN.0 - 10.0f32);
+        assert_eq!(hint_rect.bottom(), SCREEN.1 - 20.0f32);
+
+        let button_rect = ui.rect(button).unwrap();
+        assert_eq!(button_rect.width, theme.button.min_width);
+        assert_eq!(
+            button_rect.height,
+            text_height + theme.button.padding[1] * 2.0f32
+        );
+        let panel_rect = ui.rect(panel).unwrap();
+        assert_eq!(
+            panel_rect.width,
+            button_rect.width + theme.panel.padding * 2.0f32
+        );
+        assert_eq!(center(&ui, panel), (SCREEN.0 * 0.5f32, SCREEN.1 * 0.5f32));
+        assert_eq!(button_rect.x, panel_rect.x + theme.panel.padding);
+        assert_eq!(button_rect.y, panel_rect.y + theme.panel.padding);
+    }
+
+    #[test]
+    fn fill_widgets_share_the_row() {
+        let gap = Theme::default().panel.gap;
+        let mut ui = Ui::new(Theme::default());
+        let row = ui.add(
+            ui.root(),
+            Widget::row()
+                .with_width(Size::Fixed(400.0f32))
+                .anchored(Anchor::TopLeft, [0.0f32, 0.0f32]),
+        );
+        let fixed = ui.add(row, Widget::label("x").with_width(Size::Fixed(100.0f32)));
+        let one = ui.add(row, Widget::button("A").with_width(Size::Fill(1.0f32)));
+        let three = ui.add(row, Widget::button("B").with_width(Size::Fill(3.0f32)));
+        layout(&mut ui);
+
+        let share = (400.0f32 - 100.0f32 - gap * 2.0f32) / 4.0f32;
+        assert_eq!(ui.rect(fixed).unwrap().width, 100.0f32);
+        assert_eq!(ui.rect(one).unwrap().width, share);
+        assert_eq!(ui.rect(three).unwrap().width, share * 3.0f32);
+        assert_eq!(ui.rect(three).unwrap().right(), 400.0f32);
+    }
+
+    #[test]
+    fn justify_places_children_along_the_row() {
+        let gap = Theme::default().panel.gap;
+        let left_over = 400.0f32 - 100.0f32 - gap;
+        let cases = [
+            (Justify::Start, [0.0f32, 50.0f32 + gap]),
+            (
+                Justify::Center,
+                [left_over * 0.5f32, left_over * 0.5f32 + 50.0f32 + gap],
+            ),
+            (Justify::End, [left_over, 350.0f32]),
+            (Justify::SpaceBetween, [0.0f32, 350.0f32]),
+        ];
+        for (justify, expected) in cases.iter() {
+            let mut ui = Ui::new(Theme::default());
+            let labels = row_of_labels(&mut ui, Widget::row().with_justify(*justify), 400.0f32, 2);
+            layout(&mut ui);
+            let xs = labels
+                .iter()
+                .map(|label| ui.rect(*label).unwrap().x)
+                .collect::<Vec<_>>();
+            assert_eq!(xs, expected.to_vec(), "{:?}", justify);
+        }
+
+        // A lone child of a spaced row stays at the start
+        let mut ui = Ui::new(Theme::default());
+        let labels = row_of_labels(
+            &mut ui,
+            Widget::row().with_justify(Justify::SpaceBetween),
+            400.0f32,
+            1,
+        );
+        layout(&mut ui);
+        assert_eq!(ui.rect(labels[0]).unwrap().x, 0.0f32);
+    }
+
+    #[test]
+    fn cross_align_places_children_across_the_column() {
+        let slider_width = Theme::default().slider.width;
+        // Where a 40 pixel wide child goes, and how wide a fitting one gets
+        let cases = [
+            (CrossAlign::Start, 0.0f32, slider_width),
+            (CrossAlign::Center, 130.0f32, slider_width),
+            (CrossAlign::End, 260.0f32, slider_width),
+            (CrossAlign::Stretch, 0.0f32, 300.0f32),
+        ];
+        for (align, fixed_x, fit_width) in cases.iter() {
+            let mut ui = Ui::new(Theme::default());
+            let column = ui.add(
+                ui.root(),
+                Widget::column()
+                    .with_align(*align)
+                    .with_width(Size::Fixed(300.0f32))
+                    .anchored(Anchor::TopLeft, [0.0f32, 0.0f32]),
+            );
+            let fit = ui.add(column, Widget::slider(0.0f32, 0.0f32..=1.0f32));
+            let fixed = ui.add(column, Widget::label("x").with_width(Size::Fixed(40.0f32)));
+            let fill = ui.add(column, Widget::label("x").with_width(Size::Fill(1.0f32)));
+            layout(&mut ui);
+
+            assert_eq!(ui.rect(fit).unwrap().width, *fit_width, "{:?}", align);
+            // Fixed sizes win over stretching
+            assert_eq!(ui.rect(fixed).unwrap().x, *fixed_x, "{:?}", align);
+            assert_eq!(ui.rect(fixed).unwrap().width, 40.0f32, "{:?}", align);
+            assert_eq!(ui.rect(fill).unwrap().width, 300.0f32, "{:?}", align);
+        }
+    }
+
+    #[test]
+    fn hit_test_sees_through_panels_without_a_background() {
+        let mut ui = Ui::new(Theme::default());
+        let root = ui.root();
+        let group = ui.add(
+            root,
+            Widget::column()
+                .with_width(Size::Fixed(400.0f32))
+                .with_height(Size::Fixed(300.0f32))
+                .anchored(Anchor::TopLeft, [0.0f32, 0.0f32]),
+        );
+        let button = ui.add(group, Widget::button("Go"));
+        let panel = ui.add(
+            root,
+            Widget::column()
+                .with_background()
+                .anchored(Anchor::BottomRight, [0.0f32, 0.0f32]),
+        );
+        let label = ui.add(panel, Widget::label("Text"));
+        layout(&mut ui);
+
+        let (x, y) = center(&ui, button);
+        assert_eq!(ui.hit_test(x, y), Some(button));
+        // Inside the group but off the button
+        assert_eq!(ui.hit_test(390.0f32, 290.0f32), None);
+        let (x, y) = center(&ui, label);
+        assert_eq!(ui.hit_test(x, y), Some(label));
+        let panel_rect = ui.rect(panel).unwrap();
+        assert_eq!(
+            ui.hit_test(panel_rect.x + 1.0f32, panel_rect.y + 1.0f32),
+            Some(panel)
+        );
+
+        ui.set_visible(group, false);
+        let (x, y) = center(&ui, button);
+        assert_eq!(ui.hit_test(x, y), None);
+    }
+
+    #[test]
+    fn press_and_release_on_a_button_clicks_it() {
+        let mut ui = Ui::new(Theme::default());
+        let button = ui.add(ui.root(), Widget::button("Go"));
+        let disabled = ui.add(ui.root(), Widget::button("No").with_enabled(false));
+        layout(&mut ui);
+
+        let at = center(&ui, button);
+        click(&mut ui, at);
+        assert_eq!(ui.drain_events(), vec![UiEvent::Clicked(button)]);
+        assert!(ui.drain_events().is_empty());
+
+        // Sliding off before releasing cancels
+        drag(&mut ui, at, &[(SCREEN.0 - 1.0f32, SCREEN.1 - 1.0f32)]);
+        assert!(ui.drain_events().is_empty());
+
+        // Disabled buttons swallow the press without clicking
+        let (x, y) = center(&ui, disabled);
+        assert!(!ui.handle_event(&moved(x, y)));
+        assert!(ui.handle_event(&mouse(ElementState::Pressed)));
+        assert!(ui.handle_event(&mouse(ElementState::Released)));
+        assert!(ui.drain_events().is_empty());
+
+        // Presses off the UI are left to the game
+        ui.handle_event(&moved(SCREEN.0 - 1.0f32, SCREEN.1 - 1.0f32));
+        assert!(!ui.handle_event(&mouse(ElementState::Pressed)));
+        assert!(!ui.handle_event(&mouse(ElementState::Released)));
+    }
+
+    #[test]
+    fn dragging_a_slider_changes_its_value() {
+        let theme = Theme::default();
+        let mut ui = Ui::new(theme.clone());
+        let slider = ui.add(
+            ui.root(),
+            Widget::slider(1.0f32, 0.0f32..=4.0f32).with_step(1.0f32),
+        );
+        layout(&mut ui);
+
+        let rect = ui.rect(slider).unwrap();
+        let y = rect.y + rect.height * 0.5f32;
+        let left = rect.x + theme.slider.thumb_width * 0.5f32;
+        let right = rect.right() - theme.slider.thumb_width * 0.5f32;
+        drag(
+            &mut ui,
+            (left, y),
+            &[(right, y), (right + 100.0f32, y + 100.0f32)],
+        );
+        assert_eq!(
+            ui.drain_events(),
+            vec![
+                UiEvent::ValueChanged {
+                    id: slider,
+                    value: 0.0f32,
+                },
+                UiEvent::ValueChanged {
+                    id: slider,
+                    value: 4.0f32,
+                },
+            ]
+        );
+        assert_eq!(ui.value(slider), Some(4.0f32));
+
+        // Values snap to the step
+        let x = left + (right - left) * 0.6f32;
+        click(&mut ui, (x, y));
+        assert_eq!(ui.value(slider), Some(2.0f32));
+    }
+
+    #[test]
+    fn lists_select_on_click_and_scroll_on_drag() {
+        let row_height = Theme::default().list.row_height;
+        let mut ui = Ui::new(Theme::default());
+        let items = (0..8).map(|i| format!("Slot {}", i)).collect();
+        let list = ui.add(ui.root(), Widget::list(items, 3));
+        layout(&mut ui);
+
+        let rect = ui.rect(list).unwrap();
+        let x = rect.x + 10.0f32;
+        let row_y = |row: f32| rect.y + row_height * (row + 0.5f32);
+        click(&mut ui, (x, row_y(1.0f32)));
+        assert_eq!(
+            ui.drain_events(),
+            vec![UiEvent::Selected { id: list, index: 1 }]
+        );
+        assert_eq!(ui.selected(list), Some(1));
+
+        // Dragging up two rows scrolls down two items without selecting
+        drag(
+            &mut ui,
+            (x, row_y(2.0f32)),
+            &[(x, row_y(1.0f32)), (x, row_y(0.0f32))],
+        );
+        assert!(ui.drain_events().is_empty());
+        assert_eq!(ui.selected(list), Some(1));
+        match &ui.widget(list).unwrap().kind {
+            WidgetKind::List(list) => assert_eq!(list.scroll, 2.0f32),
+            kind => panic!("Not a list: {:?}", kind),
+        }
+        click(&mut ui, (x, row_y(0.0f32)));
+        assert_eq!(
+            ui.drain_events(),
+            vec![UiEvent::Selected { id: list, index: 2 }]
+        );
+
+        // Scrolling stops at the last item
+        drag(&mut ui, (x, row_y(2.0f32)), &[(x, row_y(-20.0f32))]);
+        match &ui.widget(list).unwrap().kind {
+            WidgetKind::List(list) => assert_eq!(list.scroll, list.max_scroll()),
+            kind => panic!("Not a list: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn scale_factor_scales_the_theme_and_layout() {
+        let theme = Theme::default();
+        let mut ui = Ui::new(theme.clone());
+        let label = ui.add(
+            ui.root(),
+            Widget::label("x").anchored(Anchor::TopLeft, [10.0f32, 20.0f32]),
+        );
+        let button = ui.add(
+            ui.root(),
+            Widget::button("Go").with_width(Size::Fixed(100.0f32)),
+        );
+
+        let mut size = PhysicalSize::new(SCREEN.0 as u32, SCREEN.1 as u32);
+        let changed = WindowEvent::ScaleFactorChanged {
+            scale_factor: 2.0f64,
+            new_inner_size: &mut size,
+        };
+        assert!(!ui.handle_event(&changed));
+        assert_eq!(ui.theme(), &theme.scaled(2.0f32));
+        layout(&mut ui);
+        let label_rect = ui.rect(label).unwrap();
+        assert_eq!((label_rect.x, label_rect.y), (20.0f32, 40.0f32));
+        assert_eq!(label_rect.height, 1.2f32 * theme.font_size * 2.0f32);
+        assert_eq!(ui.rect(button).unwrap().width, 200.0f32);
+
+        // New themes are scaled too
+        let mut bigger = theme.clone();
+        bigger.font_size *= 2.0f32;
+        ui.set_theme(bigger.clone());
+        assert_eq!(ui.theme(), &bigger.scaled(2.0f32));
+    }
+}
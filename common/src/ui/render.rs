@@ -0,0 +1,185 @@
+use crate::render::quad::{QuadList, QuadRenderer, QuadTexture, Rect};
+use crate::text::font::Fonts;
+use crate::text::layout::{Align, TextStyle};
+use crate::text::TextRenderer;
+use crate::ui::theme::Color;
+use crate::ui::widget::WidgetKind;
+use crate::ui::Ui;
+
+/// Draws a `Ui` in screen space: widget backgrounds first, then all text on top.
+pub struct UiRenderer {
+    renderer: QuadRenderer,
+    white: QuadTexture,
+    quads: QuadList,
+    text: TextRenderer,
+}
+
+impl UiRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        fonts: Fonts,
+    ) -> Self {
+        let renderer = QuadRenderer::new(device, format);
+        let white = renderer.create_texture(device, "UI", 1, 1, wgpu::FilterMode::Nearest);
+        white.write(queue, 0, 0, 1, 1, &[255, 255, 255, 255]);
+        UiRenderer {
+            renderer,
+            white,
+            quads: QuadList::new(),
+            text: TextRenderer::new(device, format, fonts),
+        }
+    }
+
+    /// Also measures text for `Ui::layout`.
+    pub fn fonts(&self) -> &Fonts {
+        self.text.fonts()
+    }
+
+    /// Draws `ui` as placed by its last `layout` over `view`.
+    pub fn draw(
+        &mut self,
+        ui: &Ui,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        screen_size: (u32, u32),
+    ) {
+        self.quads.clear();
+        let theme = ui.theme();
+        for (id, widget, rect) in ui.visible_widgets() {
+            let text_color = if widget.enabled {
+                theme.text_color
+            } else {
+                theme.disabled_text_color
+            };
+            match &widget.kind {
+                WidgetKind::Panel(panel) => {
+                    if panel.background {
+                        self.fill(rect, theme.panel.background);
+                    }
+                }
+                WidgetKind::Label(text) => {
+                    self.text(text, rect, Align::Left, 0.0f32, text_color, theme.font_size);
+                }
+                WidgetKind::Button(text) => {
+                    let color = if !widget.enabled {
+                        theme.button.disabled
+                    } else if ui.is_pressed(id) {
+                        theme.button.pressed
+                    } else if ui.is_hovered(id) {
+                        theme.button.hovered
+                    } else {
+                        theme.button.normal
+                    };
+                    self.fill(rect, color);
+                    self.text(
+                        text,
+                        rect,
+                        Align::Center,
+                        0.0f32,
+                        text_color,
+                        theme.font_size,
+                    );
+                }
+                WidgetKind::Slider(slider) => {
+                    let slider_theme = &theme.slider;
+                    let track = (rect.width - slider_theme.thumb_width).max(0.0f32);
+                    let thumb_x = rect.x + track * slider.fraction();
+                    self.fill(rect, slider_theme.track);
+                    self.fill(
+                        Rect::new(
+                            rect.x,
+                            rect.y,
+                            thumb_x - rect.x + slider_theme.thumb_width * 0.5f32,
+                            rect.height,
+                        ),
+                        slider_theme.fill,
+                    );
+                    self.fill(
+                        Rect::new(thumb_x, rect.y, slider_theme.thumb_width, rect.height),
+                        slider_theme.thumb,
+                    );
+                }
+                WidgetKind::List(list) => {
+                    let list_theme = &theme.list;
+                    self.fill(rect, list_theme.background);
+                    let first = list.scroll.round() as usize;
+                    let hovered_row = if ui.is_hovered(id) {
+                        ui.pointer().and_then(|(_, y)| ui.list_row_at(id, y))
+                    } else {
+                        None
+                    };
+                    let rows = list
+                        .items
+                        .iter()
+                        .enumerate()
+                        .skip(first)
+                        .take(list.visible_rows);
+                    for (row, (index, item)) in rows.enumerate() {
+                        let row_rect = Rect::new(
+                            rect.x,
+                            rect.y + list_theme.row_height * row as f32,
+                            rect.width,
+                            list_theme.row_height,
+                        );
+                        if list.selected == Some(index) {
+                            self.fill(row_rect, list_theme.selected);
+                        } else if hovered_row == Some(index) && widget.enabled {
+                            self.fill(row_rect, list_theme.hovered);
+                        }
+                        self.text(
+                            item,
+                            row_rect,
+                            Align::Left,
+                            list_theme.padding,
+                            text_color,
+                            theme.font_size,
+                        );
+                    }
+                }
+            }
+        }
+
+        self.renderer.draw(
+            device,
+            queue,
+            view,
+            screen_size,
+            &[(&self.white, &self.quads)],
+        );
+        self.text.draw(device, queue, view, screen_size);
+    }
+
+    fn fill(&mut self, rect: Rect, color: Color) {
+        self.quads
+            .push(rect, Rect::new(0.0f32, 0.0f32, 1.0f32, 1.0f32), color);
+    }
+
+    /// Queues one line of text, centered vertically in `rect` and inset by `padding`.
+    /// Widgets are sized to their text, so it isn't wrapped.
+    fn text(
+        &mut self,
+        text: &str,
+        rect: Rect,
+        align: Align,
+        padding: f32,
+        color: Color,
+        size: f32,
+    ) {
+        let style = TextStyle {
+            size,
+            color,
+            ..TextStyle::default()
+        };
+        let layout = self.text.layout(text, &style);
+        let x = match align {
+            Align::Left => rect.x + padding,
+            Align::Center => rect.x + (rect.width - layout.width) * 0.5f32,
+            Align::Right => rect.right() - padding - layout.width,
+        };
+        let y = rect.y + (rect.height - layout.height) * 0.5f32;
+        self.text.queue_layout(layout, x, y, color);
+    }
+}
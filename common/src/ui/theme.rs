@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tearchan::fs::{file_util, read_bytes_from_file};
+
+/// The theme the example ships with, relative to the assets directory.
+pub const UI_THEME_FILE: &str = "ui_theme.json";
+
+/// Built in, for when the assets haven't been read yet.
+pub const DEFAULT_THEME: &str = include_str!("../../assets/ui_theme.json");
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Load(String),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Load(message) => write!(f, "failed to read the UI theme: {}", message),
+            ThemeError::Parse(e) => write!(f, "invalid UI theme: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl From<serde_json::Error> for ThemeError {
+    fn from(e: serde_json::Error) -> Self {
+        ThemeError::Parse(e)
+    }
+}
+
+pub type Color = [f32; 4];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PanelTheme {
+    /// Drawn behind panels that ask for a background.
+    pub background: Color,
+    pub padding: f32,
+    /// Space between children.
+    pub gap: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonTheme {
+    pub normal: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+    pub disabled: Color,
+    /// Horizontal and vertical space around the text.
+    pub padding: [f32; 2],
+    pub min_width: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SliderTheme {
+    pub width: f32,
+    pub height: f32,
+    pub track: Color,
+    pub fill: Color,
+    pub thumb: Color,
+    pub thumb_width: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListTheme {
+    pub background: Color,
+    pub hovered: Color,
+    pub selected: Color,
+    pub row_height: f32,
+    /// Space before the text of each row.
+    pub padding: f32,
+    pub min_width: f32,
+}
+
+/// Colors and metrics of every widget, in pixels. The default is `assets/ui_theme.json`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+    pub font_size: f32,
+    pub text_color: Color,
+    pub disabled_text_color: Color,
+    pub panel: PanelTheme,
+    pub button: ButtonTheme,
+    pub slider: SliderTheme,
+    pub list: ListTheme,
+}
+
+impl Theme {
+    pub fn from_slice(bytes: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn to_vec(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+
+    /// A copy with every metric multiplied by `scale`, e.g. the window's scale factor.
+    pub fn scaled(&self, scale: f32) -> Theme {
+        let mut theme = self.clone();
+        theme.font_size *= scale;
+        theme.panel.padding *= scale;
+        theme.panel.gap *= scale;
+        theme.button.padding = [
+            theme.button.padding[0] * scale,
+            theme.button.padding[1] * scale,
+        ];
+        theme.button.min_width *= scale;
+        theme.slider.width *= scale;
+        theme.slider.height *= scale;
+        theme.slider.thumb_width *= scale;
+        theme.list.row_height *= scale;
+        theme.list.padding *= scale;
+        theme.list.min_width *= scale;
+        theme
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        serde_json::from_str(DEFAULT_THEME).expect("Invalid default UI theme")
+    }
+}
+
+/// Path of `UI_THEME_FILE` in the assets directory.
+pub fn default_theme_path() -> PathBuf {
+    let mut path = PathBuf::new();
+    path.push(file_util().assets_path());
+    path.push(UI_THEME_FILE);
+    path
+}
+
+/// Reads and parses a theme file through `tearchan::fs`.
+pub async fn load_theme<P: AsRef<Path>>(path: P) -> Result<Theme, ThemeError> {
+    let path = path.as_ref().to_path_buf();
+    let bytes = read_bytes_from_file(path.clone())
+        .await
+        .map_err(|e| ThemeError::Load(format!("{:?}: {:?}", path, e)))?;
+    Ok(Theme::from_slice(&bytes)?)
+}
@@ -0,0 +1,215 @@
+use crate::ui::layout::{Anchor, CrossAlign, Direction, Justify, Size};
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Panel {
+    pub direction: Direction,
+    pub justify: Justify,
+    pub align: CrossAlign,
+    /// Fills the panel with the theme's panel color.
+    pub background: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Values snap to multiples of this, if set.
+    pub step: Option<f32>,
+}
+
+impl Slider {
+    /// Position of the value between `min` and `max`, in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            ((self.value - self.min) / (self.max - self.min))
+                .max(0.0f32)
+                .min(1.0f32)
+        } else {
+            0.0f32
+        }
+    }
+
+    pub fn set_fraction(&mut self, fraction: f32) {
+        let mut value = self.min + (self.max - self.min) * fraction.max(0.0f32).min(1.0f32);
+        if let Some(step) = self.step.filter(|step| *step > 0.0f32) {
+            value = self.min + ((value - self.min) / step).round() * step;
+        }
+        self.value = value.max(self.min).min(self.max);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct List {
+    pub items: Vec<String>,
+    pub selected: Option<usize>,
+    /// Rows shown at once; longer lists scroll.
+    pub visible_rows: usize,
+    /// Index of the first visible row, fractional while dragging.
+    pub scroll: f32,
+}
+
+impl List {
+    pub fn max_scroll(&self) -> f32 {
+        self.items.len().saturating_sub(self.visible_rows) as f32
+    }
+
+    pub fn scroll_to(&mut self, scroll: f32) {
+        self.scroll = scroll.max(0.0f32).min(self.max_scroll());
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WidgetKind {
+    Panel(Panel),
+    Label(String),
+    Button(String),
+    Slider(Slider),
+    List(List),
+}
+
+/// A node of the UI tree. Only panels have children.
+///
+/// ```ignore
+/// let menu = ui.add(ui.root(), Widget::column().with_background().anchored(Anchor::Center, [0.0, 0.0]));
+/// ui.add(menu, Widget::label("Paused"));
+/// let resume = ui.add(menu, Widget::button("Resume").with_width(Size::Fill(1.0)));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Widget {
+    pub kind: WidgetKind,
+    pub width: Size,
+    pub height: Size,
+    /// Anchored widgets are placed inside their parent instead of flowing in its row or column.
+    /// The offset is in logical pixels.
+    pub anchor: Option<(Anchor, [f32; 2])>,
+    pub visible: bool,
+    /// Disabled widgets are drawn dimmed and ignore input.
+    pub enabled: bool,
+}
+
+impl Widget {
+    pub fn new(kind: WidgetKind) -> Self {
+        Widget {
+            kind,
+            width: Size::Fit,
+            height: Size::Fit,
+            anchor: None,
+            visible: true,
+            enabled: true,
+        }
+    }
+
+    pub fn panel(direction: Direction) -> Self {
+        Widget::new(WidgetKind::Panel(Panel {
+            direction,
+            justify: Justify::Start,
+            align: CrossAlign::Start,
+            background: false,
+        }))
+    }
+
+    pub fn row() -> Self {
+        Widget::panel(Direction::Row)
+    }
+
+    pub fn column() -> Self {
+        Widget::panel(Direction::Column)
+    }
+
+    pub fn label(text: &str) -> Self {
+        Widget::new(WidgetKind::Label(text.to_string()))
+    }
+
+    pub fn button(text: &str) -> Self {
+        Widget::new(WidgetKind::Button(text.to_string()))
+    }
+
+    pub fn slider(value: f32, range: RangeInclusive<f32>) -> Self {
+        Widget::new(WidgetKind::Slider(Slider {
+            value,
+            min: *range.start(),
+            max: *range.end(),
+            step: None,
+        }))
+    }
+
+    pub fn list(items: Vec<String>, visible_rows: usize) -> Self {
+        Widget::new(WidgetKind::List(List {
+            items,
+            selected: None,
+            visible_rows: visible_rows.max(1),
+            scroll: 0.0f32,
+        }))
+    }
+
+    pub fn with_width(mut self, width: Size) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_height(mut self, height: Size) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn anchored(mut self, anchor: Anchor, offset: [f32; 2]) -> Self {
+        self.anchor = Some((anchor, offset));
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Only affects panels.
+    pub fn with_justify(mut self, justify: Justify) -> Self {
+        if let WidgetKind::Panel(panel) = &mut self.kind {
+            panel.justify = justify;
+        }
+        self
+    }
+
+    /// Only affects panels.
+    pub fn with_align(mut self, align: CrossAlign) -> Self {
+        if let WidgetKind::Panel(panel) = &mut self.kind {
+            panel.align = align;
+        }
+        self
+    }
+
+    /// Only affects panels.
+    pub fn with_background(mut self) -> Self {
+        if let WidgetKind::Panel(panel) = &mut self.kind {
+            panel.background = true;
+        }
+        self
+    }
+
+    /// Only affects sliders.
+    pub fn with_step(mut self, step: f32) -> Self {
+        if let WidgetKind::Slider(slider) = &mut self.kind {
+            slider.step = Some(step);
+        }
+        self
+    }
+
+    /// Only affects lists.
+    pub fn with_selected(mut self, index: usize) -> Self {
+        if let WidgetKind::List(list) = &mut self.kind {
+            list.selected = Some(index).filter(|index| *index < list.items.len());
+        }
+        self
+    }
+
+    /// Whether the widget reacts to the pointer.
+    pub fn is_interactive(&self) -> bool {
+        self.enabled
+            && match self.kind {
+                WidgetKind::Button(_) | WidgetKind::Slider(_) | WidgetKind::List(_) => true,
+                WidgetKind::Panel(_) | WidgetKind::Label(_) => false,
+            }
+    }
+}
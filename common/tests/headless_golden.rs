@@ -0,0 +1,37 @@
+use common::headless::golden::{assert_golden, GoldenTolerance};
+use common::headless::{HeadlessConfig, HeadlessError, HeadlessRunner};
+use common::scene::hello_world_scene::HelloWorldScene;
+use std::path::PathBuf;
+
+const FRAMES: usize = 30;
+
+fn golden_path(name: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests");
+    path.push("golden");
+    path.push(name);
+    path
+}
+
+/// Regenerate the golden image with `UPDATE_GOLDEN=1 cargo test -p common --test headless_golden`.
+#[test]
+fn hello_world_matches_golden() {
+    let runner = match HeadlessRunner::new(HeadlessConfig::default()) {
+        Ok(runner) => runner,
+        // Nothing to render with; set WGPU_FALLBACK_ADAPTER on CI to require a software adapter
+        Err(HeadlessError::AdapterNotFound) => {
+            eprintln!("Skipping the golden image test: no graphics adapter");
+            return;
+        }
+        Err(e) => panic!("{}", e),
+    };
+
+    let image = runner.render_frames(HelloWorldScene::headless_factory(), FRAMES);
+    if let Err(e) = assert_golden(
+        &image,
+        golden_path("hello_world.png"),
+        GoldenTolerance::default(),
+    ) {
+        panic!("{} (adapter: {:?})", e, runner.adapter_info());
+    }
+}
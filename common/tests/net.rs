@@ -0,0 +1,123 @@
+use common::game::demo::create_demo_world;
+use common::map::TilePosition;
+use common::net::client::{ClientEvent, NetClient};
+use common::net::protocol::{
+    decode, encode, ClientMessage, PlayerCommand, ServerMessage, PROTOCOL_VERSION,
+};
+use common::net::server::{Server, ServerHandle};
+use common::net::socket::Socket;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+const TICK_DURATION: u64 = 20;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn spawn_server() -> ServerHandle {
+    let server = Server::bind("127.0.0.1:0", create_demo_world().state, TICK_DURATION)
+        .expect("Failed to bind the server");
+    server.spawn().expect("Failed to start the server")
+}
+
+/// Polls `client` until `done` returns true for one of its events.
+fn poll_until<F>(client: &mut NetClient, mut done: F)
+where
+    F: FnMut(&NetClient, &ClientEvent) -> bool,
+{
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        let events = client.poll().expect("Lost the connection");
+        if events.iter().any(|event| done(client, event)) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("Timed out waiting for the server");
+}
+
+/// Sends `message` over a raw socket and returns the server's first reply.
+fn exchange(url: &str, message: &ClientMessage) -> ServerMessage {
+    let mut socket = Socket::connect(url).expect("Failed to connect");
+    socket.send(encode(message).unwrap()).unwrap();
+    let deadline = Instant::now() + TIMEOUT;
+    while Instant::now() < deadline {
+        if let Some(text) = socket.receive().expect("Lost the connection") {
+            return decode(&text).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("Timed out waiting for the server");
+}
+
+#[test]
+fn clients_join_and_see_their_commands_play_out() {
+    let server = spawn_server();
+    let mut client = NetClient::connect(server.url(), "tester").unwrap();
+    poll_until(&mut client, |_, event| {
+        matches!(event, ClientEvent::Joined { .. })
+    });
+    assert!(client.client_id().is_some());
+
+    let replica = client.replica().unwrap();
+    let entity_id = *replica.character_status.entities().next().unwrap();
+    let start = *replica.positions.get(&entity_id).unwrap();
+    let destination = TilePosition::new(9, 7);
+    client
+        .send_command(PlayerCommand::MoveTo {
+            entity_id,
+            destination,
+        })
+        .unwrap();
+
+    poll_until(&mut client, |client, event| {
+        *event == ClientEvent::Updated
+            && client.replica().unwrap().positions.get(&entity_id) != Some(&start)
+    });
+    // Nothing else gives orders, so the character is on its way to the destination
+    let position = client.replica().unwrap().positions.get(&entity_id).unwrap();
+    let target = destination.to_position();
+    assert!(position.distance(&target) < start.distance(&target));
+}
+
+#[test]
+fn other_protocol_versions_are_turned_away() {
+    let server = spawn_server();
+    let reply = exchange(
+        server.url(),
+        &ClientMessage::Hello {
+            version: PROTOCOL_VERSION + 1,
+            name: "future".to_string(),
+        },
+    );
+    assert!(matches!(reply, ServerMessage::Error { .. }), "{:?}", reply);
+}
+
+#[test]
+fn commands_before_hello_are_rejected() {
+    let server = spawn_server();
+    let reply = exchange(
+        server.url(),
+        &ClientMessage::Command {
+            command: PlayerCommand::MoveTo {
+                entity_id: 1,
+                destination: TilePosition::new(0, 0),
+            },
+        },
+    );
+    assert!(
+        matches!(reply, ServerMessage::Rejected { .. }),
+        "{:?}",
+        reply
+    );
+}
+
+#[test]
+fn silent_connections_dont_block_others() {
+    let server = spawn_server();
+    let address = server.url().trim_start_matches("ws://").to_string();
+    let _silent = TcpStream::connect(&address).unwrap();
+
+    let mut client = NetClient::connect(server.url(), "tester").unwrap();
+    poll_until(&mut client, |_, event| {
+        matches!(event, ClientEvent::Joined { .. })
+    });
+}
@@ -0,0 +1,54 @@
+use common::args::{unknown_argument, ArgParser};
+use common::settings::SettingsOverrides;
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct Args {
+    pub record_directory: Option<PathBuf>,
+    pub record_encoder: Option<String>,
+    pub record_frames: Option<usize>,
+    pub record_fps: Option<u32>,
+    pub record_size: Option<(u32, u32)>,
+    pub record_input: Option<PathBuf>,
+    pub replay_input: Option<PathBuf>,
+    pub fixed_delta: Option<f32>,
+    pub settings: SettingsOverrides,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = ArgParser::new(args);
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next_flag() {
+            match arg.as_str() {
+                "--record" => parsed.record_directory = Some(args.path(&arg)?),
+                "--record-encoder" => parsed.record_encoder = Some(args.value(&arg)?),
+                "--record-frames" => parsed.record_frames = Some(args.number(&arg)?),
+                "--record-fps" => parsed.record_fps = Some(args.number(&arg)?),
+                "--record-size" => parsed.record_size = Some(args.size(&arg)?),
+                "--record-input" => parsed.record_input = Some(args.path(&arg)?),
+                "--replay-input" => parsed.replay_input = Some(args.path(&arg)?),
+                "--fixed-delta" => parsed.fixed_delta = Some(args.number(&arg)?),
+                "--width" => parsed.settings.width = Some(args.number(&arg)?),
+                "--height" => parsed.settings.height = Some(args.number(&arg)?),
+                "--fullscreen" => parsed.settings.fullscreen = Some(true),
+                "--windowed" => parsed.settings.fullscreen = Some(false),
+                "--msaa" => parsed.settings.msaa = Some(args.number(&arg)?),
+                "--backend" => parsed.settings.backend = Some(args.value(&arg)?),
+                "--scene" => parsed.settings.scene = Some(args.value(&arg)?),
+                _ => return Err(unknown_argument(&arg)),
+            }
+        }
+        if parsed.record_fps == Some(0) {
+            return Err("--record-fps must be at least 1".to_string());
+        }
+        if parsed.record_input.is_some() && parsed.replay_input.is_some() {
+            return Err("--record-input and --replay-input can't be used together".to_string());
+        }
+        Ok(parsed)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.record_directory.is_some() || self.record_encoder.is_some()
+    }
+}
@@ -1,6 +1,79 @@
+mod args;
+
+use crate::args::Args;
+use common::capture::recorder::{record, RecordConfig};
+use common::headless::{HeadlessConfig, HeadlessRunner};
+use common::input::record::{configure_input_session, InputSessionConfig};
+use common::settings::{configure_settings, load_settings_or_default, Settings};
+
 fn main() {
     std::env::set_var("RUST_LOG", "info");
     env_logger::init();
 
-    common::launch_app();
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // Broken settings files fall back to the defaults like on every other platform; only
+    // bad arguments stop the app
+    let mut settings = load_settings_or_default();
+    if let Err(e) = settings.apply_overrides(&args.settings) {
+        log::error!("{}", e);
+        std::process::exit(2);
+    }
+
+    if args.is_recording() {
+        settings.apply_backend();
+        record_app(args, settings);
+        return;
+    }
+
+    let input_session = match (args.record_input, args.replay_input) {
+        (Some(path), _) => InputSessionConfig::Record {
+            path,
+            fixed_delta: args.fixed_delta,
+        },
+        (_, Some(path)) => InputSessionConfig::Replay { path },
+        _ => InputSessionConfig::Live,
+    };
+    configure_input_session(input_session);
+
+    common::launch_app_with_settings(settings);
+}
+
+/// Records the scene chosen by the settings, at the window size unless `--record-size` is given.
+fn record_app(args: Args, settings: Settings) {
+    let factory = match settings.scene.headless_factory() {
+        Some(factory) => factory,
+        None => {
+            log::error!("The {} scene can't be recorded", settings.scene.name());
+            std::process::exit(2);
+        }
+    };
+    let (width, height) = args
+        .record_size
+        .unwrap_or((settings.width, settings.height));
+    configure_settings(settings);
+    let runner = HeadlessRunner::new(HeadlessConfig {
+        width,
+        height,
+        ..HeadlessConfig::default()
+    })
+    .expect("Failed to create a headless renderer");
+    log::info!("Recording with {:?}", runner.adapter_info());
+
+    let config = RecordConfig {
+        frames: args.record_frames.unwrap_or(300),
+        fps: args.record_fps.unwrap_or(60),
+        directory: args.record_directory,
+        encoder: args.record_encoder,
+    };
+    if let Err(e) = record(&runner, factory, &config) {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,41 @@
+use common::args::{unknown_argument, ArgParser};
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct Args {
+    pub ticks: Option<u64>,
+    pub tick: Option<u64>,
+    pub seed: Option<u64>,
+    pub fast: bool,
+    pub no_bots: bool,
+    pub report: Option<u64>,
+    pub save: Option<PathBuf>,
+    pub verify: bool,
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = ArgParser::new(args);
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next_flag() {
+            match arg.as_str() {
+                "--ticks" => parsed.ticks = Some(args.number(&arg)?),
+                "--tick" => parsed.tick = Some(args.number(&arg)?),
+                "--seed" => parsed.seed = Some(args.number(&arg)?),
+                "--fast" => parsed.fast = true,
+                "--no-bots" => parsed.no_bots = true,
+                "--report" => parsed.report = Some(args.number(&arg)?),
+                "--save" => parsed.save = Some(args.path(&arg)?),
+                "--verify" => parsed.verify = true,
+                _ => return Err(unknown_argument(&arg)),
+            }
+        }
+        if parsed.tick == Some(0) {
+            return Err("--tick must be at least 1".to_string());
+        }
+        if parsed.verify && parsed.save.is_some() {
+            return Err("--verify and --save can't be used together".to_string());
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,76 @@
+mod args;
+
+use crate::args::Args;
+use common::args::stop_on_interrupt;
+use common::game::demo::create_demo_world;
+use common::lockstep::harness::{verify_determinism, HarnessConfig};
+use common::save::save_game_state;
+use common::simulation::{HeadlessSimulation, SimulationConfig};
+
+fn main() {
+    std::env::set_var("RUST_LOG", "info");
+    env_logger::init();
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    if args.verify {
+        verify(&args);
+        return;
+    }
+
+    let defaults = SimulationConfig::default();
+    let config = SimulationConfig {
+        tick_duration: args.tick.unwrap_or(defaults.tick_duration),
+        ticks: args.ticks,
+        realtime: !args.fast,
+        seed: args.seed.unwrap_or(defaults.seed),
+        bots: !args.no_bots,
+        report_interval: args.report.unwrap_or(0),
+    };
+    let mut simulation = HeadlessSimulation::new(create_demo_world(), config);
+    // Interrupted runs stop after the current tick and still report and save
+    let report = simulation.run(&stop_on_interrupt());
+    log::info!(
+        "Ran {} ticks in {:.2?} ({:.0} ticks/s, {} late), final hash {:016x}",
+        report.ticks,
+        report.elapsed,
+        report.ticks_per_second(),
+        report.late_ticks,
+        report.hash
+    );
+
+    if let Some(path) = args.save {
+        let result = save_game_state(simulation.simulation().state())
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| std::fs::write(&path, bytes).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::error!("Failed to save to {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        log::info!("Saved the final state to {}", path.display());
+    }
+}
+
+/// Checks that the simulation gives the same hashes when repeated and when threaded.
+fn verify(args: &Args) {
+    let defaults = HarnessConfig::default();
+    let config = HarnessConfig {
+        seed: args.seed.unwrap_or(defaults.seed),
+        ticks: args.ticks.unwrap_or(defaults.ticks),
+        tick_duration: args.tick.unwrap_or(defaults.tick_duration),
+        ..defaults
+    };
+    match verify_determinism(&config) {
+        Ok(()) => log::info!("{} ticks matched across runs", config.ticks),
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
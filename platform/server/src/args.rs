@@ -0,0 +1,33 @@
+use common::args::{unknown_argument, ArgParser};
+
+pub struct Args {
+    pub address: String,
+    pub tick: u64,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            address: "127.0.0.1:9000".to_string(),
+            tick: 50,
+        }
+    }
+}
+
+impl Args {
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = ArgParser::new(args);
+        let mut parsed = Args::default();
+        while let Some(arg) = args.next_flag() {
+            match arg.as_str() {
+                "--address" => parsed.address = args.value(&arg)?,
+                "--tick" => parsed.tick = args.number(&arg)?,
+                _ => return Err(unknown_argument(&arg)),
+            }
+        }
+        if parsed.tick == 0 {
+            return Err("--tick must be at least 1".to_string());
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,31 @@
+mod args;
+
+use crate::args::Args;
+use common::args::stop_on_interrupt;
+use common::game::demo::create_demo_world;
+use common::net::server::Server;
+
+fn main() {
+    std::env::set_var("RUST_LOG", "info");
+    env_logger::init();
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let state = create_demo_world().state;
+    let mut server = match Server::bind(&args.address, state, args.tick) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    log::info!("Serving {} every {} ms", server.url(), args.tick);
+    server.run(&stop_on_interrupt());
+    log::info!("Stopped");
+}
@@ -3,5 +3,15 @@ pub fn launch_from_android() {
     std::env::set_var("RUST_LOG", "info");
     env_logger::init();
 
+    // Only the activity knows where the app's internal storage is
+    #[cfg(target_os = "android")]
+    {
+        let internal_data_path = ndk_glue::native_activity()
+            .internal_data_path()
+            .to_string_lossy()
+            .into_owned();
+        common::prefs::location::configure_preferences_directory(internal_data_path.into());
+    }
+
     common::launch_app();
 }